@@ -0,0 +1,319 @@
+use std::borrow::Cow;
+use std::ops::Range;
+
+use eframe::egui::{Color32, Stroke, TextFormat};
+use serde::{Deserialize, Serialize};
+
+const ESC: char = '\x1b';
+const BEL: char = '\x07';
+
+/// What to do with the ANSI escape sequences of colorized console output.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AnsiEscapes {
+    /// Show the sequences as they are.
+    Keep,
+    /// Remove the sequences.
+    #[default]
+    Strip,
+    /// Remove the sequences, showing the text in the colors they describe.
+    Interpret,
+}
+
+impl AnsiEscapes {
+    pub fn label(&self) -> &'static str {
+        match self {
+            AnsiEscapes::Keep => "Keep",
+            AnsiEscapes::Strip => "Strip",
+            AnsiEscapes::Interpret => "Colors",
+        }
+    }
+
+    /// The text of `line` as displayed, without any styling.
+    pub fn apply<'a>(&self, line: &'a str) -> Cow<'a, str> {
+        match self {
+            AnsiEscapes::Keep => Cow::Borrowed(line),
+            AnsiEscapes::Strip | AnsiEscapes::Interpret => strip_ansi(line),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnsiColor {
+    /// One of the 256 palette colors, the first 16 being the basic and bright colors.
+    Indexed(u8),
+    Rgb(Color32),
+}
+
+impl AnsiColor {
+    /// Bold text is shown in the bright variant of the basic colors, like most terminals do.
+    pub fn color32(self, bold: bool) -> Color32 {
+        match self {
+            AnsiColor::Indexed(n) if bold && n < 8 => indexed_color(n + 8),
+            AnsiColor::Indexed(n) => indexed_color(n),
+            AnsiColor::Rgb(c) => c,
+        }
+    }
+}
+
+/// The xterm palette.
+fn indexed_color(n: u8) -> Color32 {
+    const BASIC: [(u8, u8, u8); 16] = [
+        (0, 0, 0),
+        (205, 0, 0),
+        (0, 205, 0),
+        (205, 205, 0),
+        (0, 0, 238),
+        (205, 0, 205),
+        (0, 205, 205),
+        (229, 229, 229),
+        (127, 127, 127),
+        (255, 0, 0),
+        (0, 255, 0),
+        (255, 255, 0),
+        (92, 92, 255),
+        (255, 0, 255),
+        (0, 255, 255),
+        (255, 255, 255),
+    ];
+    const CUBE: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+    match n {
+        0..=15 => {
+            let (r, g, b) = BASIC[n as usize];
+            Color32::from_rgb(r, g, b)
+        }
+        16..=231 => {
+            let n = n - 16;
+            Color32::from_rgb(
+                CUBE[(n / 36) as usize],
+                CUBE[(n / 6 % 6) as usize],
+                CUBE[(n % 6) as usize],
+            )
+        }
+        232..=255 => Color32::from_gray(8 + (n - 232) * 10),
+    }
+}
+
+/// The styling described by the SGR sequences up to a point in a line.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AnsiStyle {
+    pub fg: Option<AnsiColor>,
+    pub bg: Option<AnsiColor>,
+    pub bold: bool,
+    pub italics: bool,
+    pub underline: bool,
+}
+
+impl AnsiStyle {
+    /// `base` with the colors and decorations of the style applied.
+    pub fn apply(&self, base: &TextFormat) -> TextFormat {
+        let color = self.fg.map(|c| c.color32(self.bold)).unwrap_or(base.color);
+
+        TextFormat {
+            color,
+            background: self.bg.map(|c| c.color32(false)).unwrap_or(base.background),
+            italics: self.italics || base.italics,
+            underline: if self.underline {
+                Stroke::new(1.0, color)
+            } else {
+                base.underline
+            },
+            ..base.clone()
+        }
+    }
+
+    /// Applies the parameters of a SGR sequence, e.g. `1;31` of `ESC[1;31m`.
+    fn apply_sgr(&mut self, params: &str) {
+        let params: Vec<u16> = params
+            .split([';', ':'])
+            .map(|p| p.parse().unwrap_or(0))
+            .collect();
+        let mut params = params.into_iter();
+
+        while let Some(param) = params.next() {
+            match param {
+                0 => *self = Self::default(),
+                1 => self.bold = true,
+                22 => self.bold = false,
+                3 => self.italics = true,
+                23 => self.italics = false,
+                4 => self.underline = true,
+                24 => self.underline = false,
+                30..=37 => self.fg = Some(AnsiColor::Indexed((param - 30) as u8)),
+                38 => self.fg = extended_color(&mut params),
+                39 => self.fg = None,
+                40..=47 => self.bg = Some(AnsiColor::Indexed((param - 40) as u8)),
+                48 => self.bg = extended_color(&mut params),
+                49 => self.bg = None,
+                90..=97 => self.fg = Some(AnsiColor::Indexed((param - 90 + 8) as u8)),
+                100..=107 => self.bg = Some(AnsiColor::Indexed((param - 100 + 8) as u8)),
+                _ => (),
+            }
+        }
+    }
+}
+
+/// The color of `38;5;n` and `38;2;r;g;b` (or 48 for backgrounds).
+fn extended_color(params: &mut impl Iterator<Item = u16>) -> Option<AnsiColor> {
+    let mut component = || params.next().map(|c| c.min(255) as u8);
+
+    match component()? {
+        5 => component().map(AnsiColor::Indexed),
+        2 => Some(AnsiColor::Rgb(Color32::from_rgb(
+            component()?,
+            component()?,
+            component()?,
+        ))),
+        _ => None,
+    }
+}
+
+/// A range of the text returned by [`parse_ansi`] and its styling.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnsiSpan {
+    pub range: Range<usize>,
+    pub style: AnsiStyle,
+}
+
+/// Separates the text of `line` from its ANSI escape sequences (CSI sequences such as SGR colors,
+/// OSC sequences such as hyperlinks, and two character escapes), returning the text and the
+/// ranges of it styled by SGR sequences.
+///
+/// A sequence cut short, by the end of the line (e.g. of a truncated line) or by characters which
+/// can't be part of it, is dropped.
+pub fn parse_ansi(line: &str) -> (Cow<'_, str>, Vec<AnsiSpan>) {
+    if !line.contains(ESC) {
+        return (Cow::Borrowed(line), Vec::new());
+    }
+
+    let bytes = line.as_bytes();
+    let mut text = String::with_capacity(line.len());
+    let mut spans = Vec::new();
+    let mut style = AnsiStyle::default();
+    let mut span_start = 0;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let Some(offset) = line[i..].find(ESC) else {
+            text.push_str(&line[i..]);
+            break;
+        };
+
+        text.push_str(&line[i..i + offset]);
+        // Past the ESC, everything which makes up a sequence is ASCII so any index stepped to
+        // while parsing it is a char boundary.
+        i += offset + 1;
+
+        match bytes.get(i) {
+            // CSI: parameters and intermediates, terminated by a byte in @..=~
+            Some(b'[') => {
+                let params_start = i + 1;
+                let mut end = params_start;
+
+                while bytes.get(end).is_some_and(|b| (0x20..=0x3F).contains(b)) {
+                    end += 1;
+                }
+
+                match bytes.get(end) {
+                    Some(&final_byte) if (0x40..=0x7E).contains(&final_byte) => {
+                        if final_byte == b'm' {
+                            let mut new_style = style;
+                            new_style.apply_sgr(&line[params_start..end]);
+
+                            if new_style != style {
+                                push_span(&mut spans, span_start..text.len(), style);
+                                span_start = text.len();
+                                style = new_style;
+                            }
+                        }
+
+                        i = end + 1;
+                    }
+                    _ => i = end,
+                }
+            }
+            // OSC: terminated by BEL or ST (ESC \)
+            Some(b']') => {
+                let rest = &line[i + 1..];
+
+                i = match rest.find([BEL, ESC]) {
+                    Some(end) if rest[end..].starts_with(BEL) => i + 1 + end + 1,
+                    Some(end) if rest[end..].starts_with("\x1b\\") => i + 1 + end + 2,
+                    // Another sequence starts before this one ended.
+                    Some(end) => i + 1 + end,
+                    None => bytes.len(),
+                };
+            }
+            // Two character sequences, e.g. ESC 7
+            Some(b) if b.is_ascii_graphic() => i += 1,
+            _ => (),
+        }
+    }
+
+    push_span(&mut spans, span_start..text.len(), style);
+
+    (Cow::Owned(text), spans)
+}
+
+fn push_span(spans: &mut Vec<AnsiSpan>, range: Range<usize>, style: AnsiStyle) {
+    if !range.is_empty() && style != AnsiStyle::default() {
+        spans.push(AnsiSpan { range, style });
+    }
+}
+
+/// Removes ANSI escape sequences from `line`, see [`parse_ansi`].
+pub fn strip_ansi(line: &str) -> Cow<'_, str> {
+    parse_ansi(line).0
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_ansi, strip_ansi, AnsiColor, AnsiSpan, AnsiStyle};
+    use eframe::egui::Color32;
+
+    #[test]
+    pub fn test_strip_ansi() {
+        assert_eq!(strip_ansi("plain text"), "plain text");
+        assert_eq!(
+            strip_ansi("\x1b[1;31merror\x1b[0m: something failed"),
+            "error: something failed"
+        );
+        assert_eq!(
+            strip_ansi("\x1b]8;;https://example.com\x1b\\link\x1b]8;;\x07!"),
+            "link!"
+        );
+        assert_eq!(strip_ansi("\x1b7saved\x1b8"), "saved");
+        assert_eq!(strip_ansi("cut short \x1b[38;5"), "cut short ");
+        assert_eq!(
+            strip_ansi("cut short \x1b[38;5… [truncated, 100 bytes]"),
+            "cut short … [truncated, 100 bytes]"
+        );
+    }
+
+    #[test]
+    pub fn test_parse_ansi() {
+        let (text, spans) = parse_ansi("\x1b[1;31merror\x1b[0m: \x1b[38;2;1;2;3mdetails\x1b[m");
+
+        assert_eq!(text, "error: details");
+        assert_eq!(
+            spans,
+            vec![
+                AnsiSpan {
+                    range: 0..5,
+                    style: AnsiStyle {
+                        fg: Some(AnsiColor::Indexed(1)),
+                        bold: true,
+                        ..Default::default()
+                    },
+                },
+                AnsiSpan {
+                    range: 7..14,
+                    style: AnsiStyle {
+                        fg: Some(AnsiColor::Rgb(Color32::from_rgb(1, 2, 3))),
+                        ..Default::default()
+                    },
+                },
+            ]
+        );
+    }
+}
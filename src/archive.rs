@@ -0,0 +1,163 @@
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+use flate2::read::GzDecoder;
+
+use crate::Error;
+
+/// Archives whose members can be opened as log files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveKind {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+impl ArchiveKind {
+    /// The kind of archive `path` is, going by its extension.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_string_lossy().to_lowercase();
+
+        if name.ends_with(".zip") {
+            Some(Self::Zip)
+        } else if name.ends_with(".tar") {
+            Some(Self::Tar)
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(Self::TarGz)
+        } else {
+            None
+        }
+    }
+}
+
+fn zip_error(e: zip::result::ZipError) -> Error {
+    Error::Other(Box::new(e))
+}
+
+fn open_tar(path: &Path, kind: ArchiveKind) -> Result<tar::Archive<Box<dyn Read>>, Error> {
+    let file = BufReader::new(File::open(path)?);
+
+    let reader: Box<dyn Read> = match kind {
+        ArchiveKind::TarGz => Box::new(GzDecoder::new(file)),
+        _ => Box::new(file),
+    };
+
+    Ok(tar::Archive::new(reader))
+}
+
+/// The names of the files within the archive at `path`, in the order they're stored.
+///
+/// Listing the members of a compressed tar archive requires decompressing all of it.
+pub fn list_members(path: &Path) -> Result<Vec<String>, Error> {
+    let kind = ArchiveKind::from_path(path).ok_or("Not a supported archive")?;
+
+    match kind {
+        ArchiveKind::Zip => {
+            let archive = zip::ZipArchive::new(File::open(path)?).map_err(zip_error)?;
+
+            Ok(archive
+                .file_names()
+                .filter(|name| !name.ends_with('/'))
+                .map(String::from)
+                .collect())
+        }
+        ArchiveKind::Tar | ArchiveKind::TarGz => {
+            let mut members = Vec::new();
+
+            for entry in open_tar(path, kind)?.entries()? {
+                let entry = entry?;
+
+                if entry.header().entry_type().is_file() {
+                    members.push(entry.path()?.to_string_lossy().into_owned());
+                }
+            }
+
+            Ok(members)
+        }
+    }
+}
+
+/// Calls `f` with a reader of the decompressed contents of `member` within the archive at `path`,
+/// and their size.
+pub fn with_member<T>(
+    path: &Path,
+    member: &str,
+    f: impl FnOnce(&mut dyn Read, u64) -> Result<T, Error>,
+) -> Result<T, Error> {
+    let kind = ArchiveKind::from_path(path).ok_or("Not a supported archive")?;
+
+    match kind {
+        ArchiveKind::Zip => {
+            let mut archive = zip::ZipArchive::new(File::open(path)?).map_err(zip_error)?;
+            let mut file = archive.by_name(member).map_err(zip_error)?;
+            let size = file.size();
+
+            f(&mut file, size)
+        }
+        ArchiveKind::Tar | ArchiveKind::TarGz => {
+            for entry in open_tar(path, kind)?.entries()? {
+                let mut entry = entry?;
+
+                if entry.path()?.to_string_lossy() == member {
+                    let size = entry.size();
+                    return f(&mut entry, size);
+                }
+            }
+
+            Err(format!("{member} not found in {}", path.display()).into())
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Write;
+
+    use super::{list_members, with_member, ArchiveKind};
+
+    #[test]
+    pub fn test_zip_members() {
+        let path = std::env::temp_dir().join(format!(
+            "logglance_test_zip_members_{}.zip",
+            std::process::id()
+        ));
+
+        {
+            let mut zip = zip::ZipWriter::new(
+                std::fs::File::create(&path).expect("Should be able to create test archive"),
+            );
+            let options = zip::write::SimpleFileOptions::default();
+
+            zip.add_directory("logs/", options)
+                .expect("Should add directory");
+            zip.start_file("logs/app.log", options)
+                .expect("Should add file");
+            zip.write_all(b"first line\nsecond line\n")
+                .expect("Should write file");
+            zip.start_file("logs/other.log", options)
+                .expect("Should add file");
+            zip.finish().expect("Should finish archive");
+        }
+
+        let members = list_members(&path);
+        let member = with_member(&path, "logs/app.log", |r, size| {
+            let mut contents = String::new();
+            r.read_to_string(&mut contents)?;
+            Ok((contents, size))
+        });
+        let missing = with_member(&path, "missing.log", |_, _| Ok(()));
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(ArchiveKind::from_path(&path), Some(ArchiveKind::Zip));
+        assert_eq!(
+            members.expect("Should list members"),
+            vec!["logs/app.log", "logs/other.log"]
+        );
+
+        let (contents, size) = member.expect("Should read member");
+        assert_eq!(contents, "first line\nsecond line\n");
+        assert_eq!(size, contents.len() as u64);
+        assert!(missing.is_err());
+    }
+}
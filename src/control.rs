@@ -0,0 +1,371 @@
+//! A local socket (a Unix socket, or a named pipe on Windows) which lets scripts drive the
+//! application, e.g. to open a file, filter it and count the matching lines.
+//!
+//! Commands are JSON objects, one per line, each answered by a JSON object on a line of its own:
+//!
+//! ```text
+//! {"command": "open", "path": "/var/log/app.log"}
+//! {"command": "set_filter", "path": "/var/log/app.log", "search": "ERROR|WARN", "mode": "Regex"}
+//! {"command": "count_matches", "path": "/var/log/app.log"}
+//! {"command": "export", "path": "/var/log/app.log", "destination": "/tmp/errors.log"}
+//! ```
+//!
+//! The commands are carried out by the GUI thread, see [`crate::Message::Control`].
+//!
+//! Anyone able to connect can read the open files and write files as the user, so the socket is
+//! only accessible to the user: on Unix it's created in a directory only the user can access, on
+//! Windows the pipe only grants access to its owner.
+
+use std::path::PathBuf;
+use std::sync::mpsc::Sender;
+
+use eframe::egui;
+use log::{debug, error, info};
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+
+use crate::logfile::SearchMode;
+use crate::{Error, Message};
+
+/// Longest command accepted, so a connection can't make the line being read grow without bounds.
+const MAX_COMMAND_LENGTH: u64 = 64 * 1024;
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum ControlCommand {
+    Open {
+        path: PathBuf,
+    },
+    /// Filters the open file at `path` by `search`.
+    SetFilter {
+        path: PathBuf,
+        search: String,
+        #[serde(default)]
+        mode: SearchMode,
+        #[serde(default)]
+        case_insensitive: bool,
+    },
+    /// Number of lines of the open file at `path` matching its filter.
+    CountMatches {
+        path: PathBuf,
+    },
+    /// Writes the lines of the open file at `path` matching its filter to `destination`, which
+    /// has to be an absolute path. Existing files are never overwritten, so a command can't
+    /// clobber e.g. the user's configuration.
+    Export {
+        path: PathBuf,
+        destination: PathBuf,
+    },
+}
+
+#[derive(Serialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct ControlResponse {
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Number of matching lines, for `count_matches` and `export`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub matches: Option<usize>,
+    /// Number of lines read so far, for `count_matches`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lines: Option<usize>,
+}
+
+impl ControlResponse {
+    pub fn ok() -> Self {
+        Self {
+            ok: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn error(message: impl Into<String>) -> Self {
+        Self {
+            ok: false,
+            error: Some(message.into()),
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(unix)]
+pub fn socket_path() -> Result<PathBuf, Error> {
+    Ok(socket_dir()?.join("logglance.sock"))
+}
+
+#[cfg(windows)]
+pub fn socket_path() -> Result<PathBuf, Error> {
+    let user = std::env::var("USERNAME").unwrap_or_default();
+
+    Ok(PathBuf::from(format!(r"\\.\pipe\logglance-{user}")))
+}
+
+/// Directory only the user can access, so no one else can connect to the socket created in it,
+/// not even before its permissions could be changed.
+#[cfg(unix)]
+fn socket_dir() -> Result<PathBuf, Error> {
+    use std::os::unix::fs::{DirBuilderExt, MetadataExt, PermissionsExt};
+
+    // Only accessible to the user, see the XDG Base Directory Specification.
+    if let Some(dir) = std::env::var_os("XDG_RUNTIME_DIR") {
+        return Ok(PathBuf::from(dir));
+    }
+
+    // SAFETY: geteuid has no preconditions and always succeeds.
+    let uid = unsafe { libc::geteuid() };
+    let dir = std::env::temp_dir().join(format!("logglance-{uid}"));
+
+    match std::fs::DirBuilder::new().mode(0o700).create(&dir) {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {}
+        Err(e) => return Err(e.into()),
+    }
+
+    // It may have been created by someone else, the temporary directory being shared.
+    let metadata = std::fs::symlink_metadata(&dir)?;
+
+    if !metadata.is_dir() || metadata.uid() != uid || metadata.permissions().mode() & 0o077 != 0 {
+        return Err(format!(
+            "{} isn't a directory only accessible to the user",
+            dir.display()
+        )
+        .into());
+    }
+
+    Ok(dir)
+}
+
+/// Starts listening for commands, which are sent to the GUI thread through `sender`.
+#[cfg(unix)]
+pub fn listen(sender: Sender<Message>, ctx: egui::Context) -> Result<JoinHandle<()>, Error> {
+    use tokio::net::UnixListener;
+
+    let path = socket_path()?;
+
+    if path.exists() {
+        if std::os::unix::net::UnixStream::connect(&path).is_ok() {
+            return Err(format!("{} is used by another instance", path.display()).into());
+        }
+
+        // Left behind by an instance which didn't exit cleanly.
+        std::fs::remove_file(&path)?;
+    }
+
+    let listener = UnixListener::bind(&path)?;
+    info!("Listening for commands on {}", path.display());
+
+    Ok(tokio::spawn(async move {
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    tokio::spawn(serve(stream, sender.clone(), ctx.clone()));
+                }
+                Err(e) => error!("Unable to accept control connection: {e:?}"),
+            }
+        }
+    }))
+}
+
+/// Starts listening for commands, which are sent to the GUI thread through `sender`.
+#[cfg(windows)]
+pub fn listen(sender: Sender<Message>, ctx: egui::Context) -> Result<JoinHandle<()>, Error> {
+    use tokio::net::windows::named_pipe::ServerOptions;
+
+    let path = socket_path()?;
+    let mut server = create_pipe(ServerOptions::new().first_pipe_instance(true), &path)?;
+    info!("Listening for commands on {}", path.display());
+
+    Ok(tokio::spawn(async move {
+        loop {
+            if let Err(e) = server.connect().await {
+                error!("Unable to accept control connection: {e:?}");
+                continue;
+            }
+
+            let connected = server;
+            server = match create_pipe(&ServerOptions::new(), &path) {
+                Ok(server) => server,
+                Err(e) => {
+                    error!("Unable to keep listening for commands: {e:?}");
+                    return;
+                }
+            };
+
+            tokio::spawn(serve(connected, sender.clone(), ctx.clone()));
+        }
+    }))
+}
+
+/// Creates an instance of the pipe only its owner, the user, can connect to. By default everyone
+/// can read from it.
+#[cfg(windows)]
+fn create_pipe(
+    options: &tokio::net::windows::named_pipe::ServerOptions,
+    path: &std::path::Path,
+) -> Result<tokio::net::windows::named_pipe::NamedPipeServer, Error> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::Foundation::LocalFree;
+    use windows_sys::Win32::Security::Authorization::{
+        ConvertStringSecurityDescriptorToSecurityDescriptorW, SDDL_REVISION_1,
+    };
+    use windows_sys::Win32::Security::SECURITY_ATTRIBUTES;
+
+    // A protected DACL granting everything to the owner and nothing to anyone else.
+    let sddl: Vec<u16> = std::ffi::OsStr::new("D:P(A;;GA;;;OW)")
+        .encode_wide()
+        .chain(Some(0))
+        .collect();
+    let mut descriptor = std::ptr::null_mut();
+
+    // SAFETY: `sddl` is null terminated and `descriptor` is freed below.
+    let converted = unsafe {
+        ConvertStringSecurityDescriptorToSecurityDescriptorW(
+            sddl.as_ptr(),
+            SDDL_REVISION_1,
+            &mut descriptor,
+            std::ptr::null_mut(),
+        )
+    };
+
+    if converted == 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+
+    let mut attributes = SECURITY_ATTRIBUTES {
+        nLength: std::mem::size_of::<SECURITY_ATTRIBUTES>() as u32,
+        lpSecurityDescriptor: descriptor,
+        bInheritHandle: 0,
+    };
+
+    // SAFETY: `attributes` is valid for the duration of the call, the pipe doesn't keep it.
+    let server = unsafe {
+        options.create_with_security_attributes_raw(path, &mut attributes as *mut _ as *mut _)
+    };
+
+    // SAFETY: allocated by ConvertStringSecurityDescriptorToSecurityDescriptorW above.
+    unsafe { LocalFree(descriptor) };
+
+    Ok(server?)
+}
+
+/// Answers the commands of a connection until it's closed.
+async fn serve(stream: impl AsyncRead + AsyncWrite, sender: Sender<Message>, ctx: egui::Context) {
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut reader = BufReader::new(reader);
+    let mut line = Vec::new();
+
+    loop {
+        line.clear();
+
+        match (&mut reader)
+            .take(MAX_COMMAND_LENGTH + 1)
+            .read_until(b'\n', &mut line)
+            .await
+        {
+            Ok(0) | Err(_) => return,
+            Ok(_) => {}
+        }
+
+        if !line.ends_with(b"\n") && line.len() as u64 > MAX_COMMAND_LENGTH {
+            // The rest of the line is still to be read, so the connection can't be used anymore.
+            let response = ControlResponse::error(format!(
+                "Commands can't be longer than {MAX_COMMAND_LENGTH} bytes"
+            ));
+            respond(&mut writer, &response).await;
+            return;
+        }
+
+        if line.trim_ascii().is_empty() {
+            continue;
+        }
+
+        let response = match serde_json::from_slice::<ControlCommand>(&line) {
+            Ok(command) => {
+                debug!("Control command: {command:?}");
+                let (responder, response) = oneshot::channel();
+
+                if sender.send(Message::Control(command, responder)).is_err() {
+                    return;
+                }
+
+                ctx.request_repaint();
+                response
+                    .await
+                    .unwrap_or_else(|_| ControlResponse::error("The command was dropped"))
+            }
+            Err(e) => ControlResponse::error(format!("Invalid command: {e}")),
+        };
+
+        if !respond(&mut writer, &response).await {
+            return;
+        }
+    }
+}
+
+/// Writes the response on a line of its own. False if the connection has been closed.
+async fn respond(writer: &mut (impl AsyncWrite + Unpin), response: &ControlResponse) -> bool {
+    let mut json = serde_json::to_string(response).unwrap_or_default();
+    json.push('\n');
+
+    writer.write_all(json.as_bytes()).await.is_ok()
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use eframe::egui;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+    use super::{serve, ControlCommand, ControlResponse, MAX_COMMAND_LENGTH};
+    use crate::logfile::SearchMode;
+
+    #[test]
+    pub fn test_parse_commands() {
+        let command: ControlCommand = serde_json::from_str(
+            r#"{"command": "set_filter", "path": "app.log", "search": "ERROR|WARN", "mode": "Regex"}"#,
+        )
+        .expect("Should parse command");
+
+        assert_eq!(
+            command,
+            ControlCommand::SetFilter {
+                path: PathBuf::from("app.log"),
+                search: String::from("ERROR|WARN"),
+                mode: SearchMode::Regex,
+                case_insensitive: false,
+            }
+        );
+
+        assert!(serde_json::from_str::<ControlCommand>(r#"{"command": "quit"}"#).is_err());
+        assert_eq!(
+            serde_json::to_string(&ControlResponse::error("Not open")).expect("Should serialize"),
+            r#"{"ok":false,"error":"Not open"}"#
+        );
+    }
+
+    #[tokio::test]
+    pub async fn test_reject_long_commands() {
+        let (client, server) = tokio::io::duplex(1024);
+        let (sender, _receiver) = std::sync::mpsc::channel();
+        let served = tokio::spawn(serve(server, sender, egui::Context::default()));
+        let (reader, mut writer) = tokio::io::split(client);
+
+        tokio::spawn(async move {
+            let command = vec![b' '; MAX_COMMAND_LENGTH as usize + 1];
+            let _ = writer.write_all(&command).await;
+        });
+
+        let response = BufReader::new(reader)
+            .lines()
+            .next_line()
+            .await
+            .expect("Should read response")
+            .expect("Should respond");
+
+        assert!(response.contains("can't be longer"), "{response}");
+        served.await.expect("Should stop serving the connection");
+    }
+}
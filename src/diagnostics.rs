@@ -0,0 +1,221 @@
+use std::collections::VecDeque;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use eframe::egui::{self, Color32, ComboBox, RichText, ScrollArea};
+use log::{Level, LevelFilter, Log, Metadata};
+
+use crate::watcher::FileWatcher;
+
+const MAX_RECORDS: usize = 2000;
+
+static RECORDS: Mutex<VecDeque<Record>> = Mutex::new(VecDeque::new());
+static START: OnceLock<Instant> = OnceLock::new();
+
+#[derive(Debug, Clone)]
+pub struct Record {
+    pub level: Level,
+    pub target: String,
+    pub message: String,
+    /// Time since the logger was initialized.
+    pub elapsed: Duration,
+}
+
+/// Captures log records into a bounded ring buffer for the diagnostics window, then passes them
+/// on to the wrapped logger (e.g. env_logger writing to stderr).
+struct DiagnosticsLogger {
+    inner: Box<dyn Log>,
+}
+
+impl DiagnosticsLogger {
+    /// Everything from our own crate down to debug, only info and up from dependencies.
+    fn captures(metadata: &Metadata) -> bool {
+        metadata.level() <= Level::Info
+            || (metadata.level() <= Level::Debug
+                && metadata.target().starts_with(env!("CARGO_CRATE_NAME")))
+    }
+}
+
+impl Log for DiagnosticsLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        Self::captures(metadata) || self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &log::Record) {
+        if Self::captures(record.metadata()) {
+            if let Ok(mut records) = RECORDS.lock() {
+                if records.len() >= MAX_RECORDS {
+                    records.pop_front();
+                }
+
+                records.push_back(Record {
+                    level: record.level(),
+                    target: record.target().to_owned(),
+                    message: record.args().to_string(),
+                    elapsed: START.get_or_init(Instant::now).elapsed(),
+                });
+            }
+        }
+
+        if self.inner.enabled(record.metadata()) {
+            self.inner.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+/// Installs the diagnostics logger as the global logger, wrapping `inner`.
+pub fn init(inner: Box<dyn Log>, inner_max_level: LevelFilter) -> Result<(), log::SetLoggerError> {
+    START.get_or_init(Instant::now);
+    log::set_boxed_logger(Box::new(DiagnosticsLogger { inner }))?;
+    log::set_max_level(inner_max_level.max(LevelFilter::Debug));
+
+    Ok(())
+}
+
+/// A copy of the currently captured records at or above `level`.
+pub fn records(level: LevelFilter) -> Vec<Record> {
+    RECORDS
+        .lock()
+        .map(|r| r.iter().filter(|r| r.level <= level).cloned().collect())
+        .unwrap_or_default()
+}
+
+pub fn clear() {
+    if let Ok(mut records) = RECORDS.lock() {
+        records.clear();
+    }
+}
+
+fn level_color(level: Level) -> Option<Color32> {
+    match level {
+        Level::Error => Some(Color32::RED),
+        Level::Warn => Some(Color32::YELLOW),
+        _ => None,
+    }
+}
+
+#[derive(Debug)]
+pub struct DiagnosticsPanel {
+    pub level: LevelFilter,
+    /// When, and at which frame number, the frame rate was last sampled.
+    frame_sample: Option<(Instant, u64)>,
+    /// Frames painted per second, to tell whether the app goes idle while nothing changes.
+    frame_rate: f64,
+}
+
+impl Default for DiagnosticsPanel {
+    fn default() -> Self {
+        Self {
+            level: LevelFilter::Info,
+            frame_sample: None,
+            frame_rate: 0.0,
+        }
+    }
+}
+
+impl DiagnosticsPanel {
+    pub fn ui(&mut self, ui: &mut egui::Ui, watcher: &FileWatcher) {
+        let records = records(self.level);
+        self.sample_frame_rate(ui.ctx());
+
+        ui.horizontal(|ui| {
+            ui.label(format!("File watcher: {}", watcher.backend()));
+
+            if watcher.is_available() {
+                ui.label(format!("watching {} directories", watcher.watched_dirs()));
+            } else {
+                ui.colored_label(Color32::RED, "unavailable, files aren't followed");
+            }
+        });
+
+        if let Some(error) = watcher.last_error() {
+            ui.colored_label(Color32::YELLOW, format!("Last watch error: {error}"));
+        }
+
+        // This window repaints twice a second by itself, so that's what an idle app shows.
+        ui.label(format!("Frames per second: {:.1}", self.frame_rate));
+
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            ComboBox::from_label("Level")
+                .selected_text(self.level.as_str())
+                .show_ui(ui, |ui| {
+                    for level in [
+                        LevelFilter::Error,
+                        LevelFilter::Warn,
+                        LevelFilter::Info,
+                        LevelFilter::Debug,
+                    ] {
+                        ui.selectable_value(&mut self.level, level, level.as_str());
+                    }
+                });
+
+            if ui.button("Copy").clicked() {
+                let text = records
+                    .iter()
+                    .map(|r| {
+                        format!(
+                            "[{:.3}s {} {}] {}",
+                            r.elapsed.as_secs_f64(),
+                            r.level,
+                            r.target,
+                            r.message
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                ui.output_mut(|o| o.copied_text = text);
+            }
+
+            if ui.button("Clear").clicked() {
+                clear();
+            }
+        });
+
+        ui.separator();
+
+        ScrollArea::both()
+            .auto_shrink([false, false])
+            .stick_to_bottom(true)
+            .show(ui, |ui| {
+                for record in &records {
+                    let mut text = RichText::new(format!(
+                        "[{:>9.3}s {:<5} {}] {}",
+                        record.elapsed.as_secs_f64(),
+                        record.level,
+                        record.target,
+                        record.message
+                    ))
+                    .monospace();
+
+                    if let Some(color) = level_color(record.level) {
+                        text = text.color(color);
+                    }
+
+                    ui.label(text);
+                }
+            });
+
+        // New records don't trigger a repaint by themselves.
+        ui.ctx().request_repaint_after(Duration::from_millis(500));
+    }
+
+    fn sample_frame_rate(&mut self, ctx: &egui::Context) {
+        let now = Instant::now();
+        let frame = ctx.frame_nr();
+
+        match self.frame_sample {
+            Some((at, start)) if now - at >= Duration::from_secs(1) => {
+                self.frame_rate = frame.saturating_sub(start) as f64 / (now - at).as_secs_f64();
+                self.frame_sample = Some((now, frame));
+            }
+            Some(_) => {}
+            None => self.frame_sample = Some((now, frame)),
+        }
+    }
+}
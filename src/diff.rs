@@ -0,0 +1,181 @@
+//! Line level diff between two versions of a file, e.g. to show what changed since it was last
+//! reloaded.
+
+/// A row of a diff, by the index of its line within the old or the new lines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    /// In both, by its index within the new lines.
+    Kept(usize),
+    Added(usize),
+    Removed(usize),
+}
+
+/// Beyond this many added and removed lines the lines which differ are shown as removed and then
+/// added rather than diffed, as the memory used grows with the square of it.
+pub const MAX_EDIT_DISTANCE: usize = 1000;
+
+/// The shortest sequence of lines kept, added and removed turning `old` into `new`, using the
+/// Myers diff algorithm. The lines both start and end with are kept without being diffed.
+pub fn diff_lines<T: PartialEq>(old: &[T], new: &[T]) -> Vec<Change> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let old_middle = &old[prefix..old.len() - suffix];
+    let new_middle = &new[prefix..new.len() - suffix];
+
+    let mut changes: Vec<Change> = (0..prefix).map(Change::Kept).collect();
+
+    match shortest_edit(old_middle, new_middle) {
+        Some(middle) => changes.extend(middle.into_iter().map(|change| match change {
+            Change::Kept(i) => Change::Kept(prefix + i),
+            Change::Added(i) => Change::Added(prefix + i),
+            Change::Removed(i) => Change::Removed(prefix + i),
+        })),
+        None => {
+            changes.extend((prefix..prefix + old_middle.len()).map(Change::Removed));
+            changes.extend((prefix..prefix + new_middle.len()).map(Change::Added));
+        }
+    }
+
+    changes.extend((new.len() - suffix..new.len()).map(Change::Kept));
+    changes
+}
+
+/// The Myers diff of `a` and `b`, None if it takes more than [`MAX_EDIT_DISTANCE`] edits.
+fn shortest_edit<T: PartialEq>(a: &[T], b: &[T]) -> Option<Vec<Change>> {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let limit = (a.len() + b.len()).min(MAX_EDIT_DISTANCE) as isize;
+
+    if (n - m).abs() > limit {
+        return None;
+    }
+
+    // The furthest x reached on each diagonal k = x - y, offset to be indexed from 0.
+    let offset = limit + 1;
+    let mut v = vec![0isize; 2 * limit as usize + 3];
+    // The diagonals -d..=d of `v` before each step d, to trace the path back.
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+
+    for d in 0..=limit {
+        trace.push(v[(offset - d) as usize..=(offset + d) as usize].to_vec());
+
+        for k in (-d..=d).step_by(2) {
+            let down = k == -d || (k != d && v[(offset + k - 1) as usize] < v[(offset + k + 1) as usize]);
+            let mut x = if down {
+                v[(offset + k + 1) as usize]
+            } else {
+                v[(offset + k - 1) as usize] + 1
+            };
+            let mut y = x - k;
+
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+
+            v[(offset + k) as usize] = x;
+
+            if x >= n && y >= m {
+                return Some(backtrack(&trace, n, m));
+            }
+        }
+    }
+
+    None
+}
+
+/// Follows the path found by [`shortest_edit`] from the end back to the start.
+fn backtrack(trace: &[Vec<isize>], n: isize, m: isize) -> Vec<Change> {
+    let mut changes = Vec::new();
+    let (mut x, mut y) = (n, m);
+
+    for (d, v) in trace.iter().enumerate().skip(1).rev() {
+        let d = d as isize;
+        let get = |k: isize| v[(k + d) as usize];
+        let k = x - y;
+
+        let previous_k = if k == -d || (k != d && get(k - 1) < get(k + 1)) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let previous_x = get(previous_k);
+        let previous_y = previous_x - previous_k;
+
+        while x > previous_x && y > previous_y {
+            changes.push(Change::Kept(y as usize - 1));
+            x -= 1;
+            y -= 1;
+        }
+
+        if x == previous_x {
+            changes.push(Change::Added(y as usize - 1));
+        } else {
+            changes.push(Change::Removed(x as usize - 1));
+        }
+
+        (x, y) = (previous_x, previous_y);
+    }
+
+    while x > 0 && y > 0 {
+        changes.push(Change::Kept(y as usize - 1));
+        x -= 1;
+        y -= 1;
+    }
+
+    changes.reverse();
+    changes
+}
+
+#[cfg(test)]
+mod test {
+    use super::{diff_lines, Change, MAX_EDIT_DISTANCE};
+
+    #[test]
+    pub fn test_diff_lines() {
+        let old = ["a", "b", "c", "a", "b", "b", "a"];
+        let new = ["c", "b", "a", "b", "a", "c"];
+        let changes = diff_lines(&old, &new);
+
+        // Myers finds 5 edits for this classic example.
+        let edits = changes.iter().filter(|c| !matches!(c, Change::Kept(_))).count();
+        assert_eq!(edits, 5);
+
+        // Applying the changes to the old lines gives the new lines.
+        let kept_or_added: Vec<&str> = changes
+            .iter()
+            .filter_map(|c| match *c {
+                Change::Kept(i) | Change::Added(i) => Some(new[i]),
+                Change::Removed(_) => None,
+            })
+            .collect();
+        assert_eq!(kept_or_added, new);
+
+        let kept_or_removed: Vec<&str> = changes
+            .iter()
+            .filter_map(|c| match *c {
+                Change::Kept(i) => Some(new[i]),
+                Change::Removed(i) => Some(old[i]),
+                Change::Added(_) => None,
+            })
+            .collect();
+        assert_eq!(kept_or_removed, old);
+
+        assert_eq!(
+            diff_lines(&["x", "y", "z"], &["x", "z", "w"]),
+            vec![Change::Kept(0), Change::Removed(1), Change::Kept(1), Change::Added(2)]
+        );
+
+        // Too different to diff, shown as replaced.
+        let old: Vec<usize> = (0..MAX_EDIT_DISTANCE).collect();
+        let new: Vec<usize> = (MAX_EDIT_DISTANCE..2 * MAX_EDIT_DISTANCE + 1).collect();
+        let changes = diff_lines(&old, &new);
+        assert_eq!(changes[0], Change::Removed(0));
+        assert_eq!(changes[MAX_EDIT_DISTANCE], Change::Added(0));
+    }
+}
@@ -0,0 +1,144 @@
+use eframe::egui::{self, Color32, Grid};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::severity::{detect_severity, Severity};
+
+/// The user supplied patterns of a [`FormatProfile`], which is what gets persisted.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct FormatPatterns {
+    pub timestamp: String,
+    pub level: String,
+    /// Matches the first line of each entry when grouping multi-line entries.
+    pub entry_start: String,
+}
+
+/// Describes the lines of a log format: the timestamp they start with and how to extract their
+/// level.
+///
+/// Each pattern is a regex. The extracted level is the group named `level` if present, otherwise
+/// the first group or the whole match. An empty level pattern falls back to the built-in level
+/// detection, an invalid pattern extracts nothing.
+///
+/// The entry start pattern tells which lines start a new entry, the others being continuations of
+/// the preceding entry (e.g. stack traces). An empty pattern falls back to lines starting with a
+/// timestamp, or every line if there's no timestamp pattern either.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(from = "FormatPatterns", into = "FormatPatterns")]
+pub struct FormatProfile {
+    pub patterns: FormatPatterns,
+    timestamp_regex: Option<Result<Regex, regex::Error>>,
+    level_regex: Option<Result<Regex, regex::Error>>,
+    entry_start_regex: Option<Result<Regex, regex::Error>>,
+}
+
+fn compile(pattern: &str) -> Option<Result<Regex, regex::Error>> {
+    if pattern.is_empty() {
+        None
+    } else {
+        Some(RegexBuilder::new(pattern).unicode(true).build())
+    }
+}
+
+fn extract<'a>(regex: &Regex, name: &str, line: &'a str) -> Option<&'a str> {
+    let captures = regex.captures(line)?;
+
+    captures
+        .name(name)
+        .or_else(|| captures.get(1))
+        .or_else(|| captures.get(0))
+        .map(|m| m.as_str())
+}
+
+impl From<FormatPatterns> for FormatProfile {
+    fn from(patterns: FormatPatterns) -> Self {
+        Self {
+            timestamp_regex: compile(&patterns.timestamp),
+            level_regex: compile(&patterns.level),
+            entry_start_regex: compile(&patterns.entry_start),
+            patterns,
+        }
+    }
+}
+
+impl From<FormatProfile> for FormatPatterns {
+    fn from(profile: FormatProfile) -> Self {
+        profile.patterns
+    }
+}
+
+impl FormatProfile {
+    pub fn severity(&self, line: &str) -> Option<Severity> {
+        match self.level_regex.as_ref() {
+            Some(Ok(r)) => extract(r, "level", line).and_then(Severity::from_level_str),
+            Some(Err(_)) => None,
+            None => detect_severity(line),
+        }
+    }
+
+    pub fn is_entry_start(&self, line: &str) -> bool {
+        match (self.entry_start_regex.as_ref(), self.timestamp_regex.as_ref()) {
+            (Some(Ok(r)), _) => r.is_match(line),
+            (None, Some(Ok(r))) => r.find(line).is_some_and(|m| m.start() == 0),
+            _ => true,
+        }
+    }
+
+    /// Returns true if any of the patterns changed.
+    pub fn ui(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut timestamp_changed = false;
+        let mut level_changed = false;
+        let mut entry_start_changed = false;
+
+        Grid::new(ui.next_auto_id())
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("Timestamp regex").on_hover_text(
+                    "Matches the timestamp at the start of lines, which start entries when grouping",
+                );
+                timestamp_changed = ui
+                    .text_edit_singleline(&mut self.patterns.timestamp)
+                    .changed();
+                ui.end_row();
+
+                ui.label("Level regex").on_hover_text(
+                    "Extracts the level, from the group named `level` if there is one.\nLeave empty to detect upper case level names.",
+                );
+                level_changed = ui.text_edit_singleline(&mut self.patterns.level).changed();
+                ui.end_row();
+
+                ui.label("Entry start regex").on_hover_text(
+                    "Matches the first line of each entry, when grouping multi-line entries.\nLeave empty to use lines starting with a timestamp.",
+                );
+                entry_start_changed = ui
+                    .text_edit_singleline(&mut self.patterns.entry_start)
+                    .changed();
+                ui.end_row();
+            });
+
+        if timestamp_changed {
+            self.timestamp_regex = compile(&self.patterns.timestamp);
+        }
+
+        if level_changed {
+            self.level_regex = compile(&self.patterns.level);
+        }
+
+        if entry_start_changed {
+            self.entry_start_regex = compile(&self.patterns.entry_start);
+        }
+
+        for (name, regex) in [
+            ("timestamp", &self.timestamp_regex),
+            ("level", &self.level_regex),
+            ("entry start", &self.entry_start_regex),
+        ] {
+            if let Some(Err(e)) = regex {
+                ui.colored_label(Color32::RED, format!("Invalid {name} regex supplied: {e:?}"));
+            }
+        }
+
+        timestamp_changed || level_changed || entry_start_changed
+    }
+}
@@ -1,18 +1,44 @@
 use std::{
-    collections::VecDeque,
+    collections::{HashMap, VecDeque},
+    ffi::OsString,
     fmt::{Debug, Display},
-    path::PathBuf,
-    sync::mpsc::{channel, Receiver, Sender},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{channel, Receiver, Sender},
+        Arc,
+    },
+    time::{Duration, Instant},
 };
 
 use log::{debug, error};
 
-use eframe::egui::{self, CentralPanel, TopBottomPanel};
-use egui_tiles::{Behavior, Container, SimplificationOptions, Tile, Tiles, Tree, UiResponse};
+use eframe::egui::{self, CentralPanel, Key, Modifiers, TopBottomPanel};
+use egui_tiles::{
+    Behavior, Container, SimplificationOptions, Tile, TileId, Tiles, Tree, UiResponse,
+};
 use serde::{Deserialize, Serialize};
 
+pub mod ansi;
+pub mod archive;
+#[cfg(feature = "control-socket")]
+pub mod control;
+pub mod diagnostics;
+pub mod diff;
+pub mod format;
 pub mod logfile;
-use logfile::LogFile;
+pub mod logfmt;
+pub mod reveal;
+pub mod settings;
+pub mod severity;
+pub mod table;
+pub mod watcher;
+pub mod workspace;
+use archive::ArchiveKind;
+use diagnostics::DiagnosticsPanel;
+use logfile::{DisplayOptions, LogFile};
+use settings::{ReopenBehaviour, Settings};
+use watcher::FileWatcher;
 
 pub const APPLICATION_NAME: &str = "LogGlance";
 pub const IS_WEB: bool = cfg!(target_arch = "wasm32");
@@ -70,42 +96,64 @@ impl From<String> for Error {
 impl Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Self::Io(e) => std::fmt::Display::fmt(&e, f),
-            //Self::Send(e) => std::fmt::Display::fmt(e, f),
-            Self::Receive(e) => std::fmt::Display::fmt(e, f),
-            Self::Notify(e) => std::fmt::Display::fmt(e, f),
+            Self::Io(e) => write!(f, "I/O error: {e}"),
+            //Self::Send(e) => write!(f, "Channel send error: {e}"),
+            Self::Receive(e) => write!(f, "Channel receive error: {e}"),
+            Self::Notify(e) => write!(f, "FS watch error: {e}"),
+            // Mostly messages written for the user, which don't need a prefix.
             Self::Other(e) => std::fmt::Display::fmt(e, f),
         }
     }
 }
 
 impl std::error::Error for Error {
-    fn cause(&self) -> Option<&dyn std::error::Error> {
-        /*
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
-            Self::IoError(e) => Some(e),
-            Self::Other(e) => Some(e)
+            Self::Io(e) => Some(e),
+            //Self::Send(e) => Some(e),
+            Self::Receive(e) => Some(e),
+            Self::Notify(e) => Some(e),
+            Self::Other(e) => Some(e.as_ref()),
         }
-        */
-        None
     }
+}
 
-    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
-        None
+const MAX_RECENT_FILES: usize = 20;
+/// The files listed by the file dialogs for opening and saving workspaces.
+const WORKSPACE_FILTER: Option<(&str, &[&str])> = Some(("Workspace", &["json"]));
+/// How long transient status messages, e.g. about the files just opened, are shown for.
+const STATUS_DURATION: Duration = Duration::from_secs(3);
+
+const TAB_NUMBER_KEYS: [Key; 9] = [
+    Key::Num1,
+    Key::Num2,
+    Key::Num3,
+    Key::Num4,
+    Key::Num5,
+    Key::Num6,
+    Key::Num7,
+    Key::Num8,
+    Key::Num9,
+];
+
+/// Splits a location such as `app.log:14233` into the path and line number. Arguments without a
+/// line number (or which aren't valid UTF-8) are taken as a path as a whole, as are those naming
+/// an existing file, e.g. `backup.log:2`.
+pub fn parse_location(arg: OsString) -> (PathBuf, Option<u64>) {
+    if Path::new(&arg).exists() {
+        return (PathBuf::from(arg), None);
     }
 
-    fn description(&self) -> &str {
-        match self {
-            Self::Io(_e) => "IO Error",
-            //Self::Send(_e) => "Channel Send error",
-            Self::Receive(_e) => "Channel Receive error",
-            Self::Notify(_e) => "FS Notify error",
-            Self::Other(_e) => "Unknown error"
+    if let Some((path, line)) = arg.to_str().and_then(|a| a.rsplit_once(':')) {
+        if let Ok(line) = line.parse() {
+            if !path.is_empty() {
+                return (PathBuf::from(path), Some(line));
+            }
         }
     }
-}
 
-const MAX_RECENT_FILES: usize = 20;
+    (PathBuf::from(arg), None)
+}
 
 
 #[derive(Serialize, Deserialize)]
@@ -114,9 +162,14 @@ pub enum TabPane {
 }
 
 impl TabPane {
-    pub fn ui(&mut self, ui: &mut eframe::egui::Ui) -> egui_tiles::UiResponse {
+    pub fn ui(
+        &mut self,
+        ui: &mut eframe::egui::Ui,
+        watcher: &FileWatcher,
+        settings: &Settings,
+    ) -> egui_tiles::UiResponse {
         match self {
-            Self::LogFile(f) => f.ui(ui),
+            Self::LogFile(f) => f.ui(ui, watcher, settings),
         }
 
         UiResponse::None
@@ -134,18 +187,108 @@ impl Debug for TabPane {
 #[derive(Debug)]
 pub enum Message {
     FilesPicked(Vec<PathBuf>),
+    /// A file picked to be opened from a byte offset, which is asked for next.
+    OffsetFilePicked(PathBuf),
+    /// A workspace file picked to replace the open files with.
+    WorkspacePicked(PathBuf),
+    /// Where to save the open files as a workspace.
+    WorkspaceSavePicked(PathBuf),
+    /// The files within an archive being opened, or why they couldn't be listed.
+    ArchiveListed(PathBuf, Result<Vec<String>, String>),
+    /// A command received over the control socket, answered through the sender.
+    #[cfg(feature = "control-socket")]
+    Control(
+        control::ControlCommand,
+        tokio::sync::oneshot::Sender<control::ControlResponse>,
+    ),
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct LogTool {
     tree: Tree<TabPane>,
     recent_files: VecDeque<PathBuf>,
+    /// Recent files selected to be opened together.
+    #[serde(skip)]
+    selected_recent_files: Vec<PathBuf>,
+    #[serde(default)]
+    settings: Settings,
+    /// Display options given to newly opened files.
+    #[serde(default)]
+    default_display: DisplayOptions,
+    /// Files opened while already open, waiting for the user to pick what to do.
+    #[serde(skip)]
+    pending_reopen: Vec<(PathBuf, TileId)>,
+    #[serde(skip)]
+    remember_reopen_choice: bool,
+    /// Archives opened, waiting for the user to pick which file within them to open.
+    #[serde(skip)]
+    pending_archives: Vec<PendingArchive>,
+    /// Commands received over the control socket, waiting for their file to be filtered.
+    #[cfg(feature = "control-socket")]
+    #[serde(skip)]
+    pending_commands: Vec<(
+        control::ControlCommand,
+        tokio::sync::oneshot::Sender<control::ControlResponse>,
+    )>,
+    /// File being opened from an offset, waiting for the user to enter the offset.
+    #[serde(skip)]
+    pending_offset: Option<PendingOffset>,
+    /// Set when opening files went beyond the configured maximum, until the warning is dismissed.
+    #[serde(skip)]
+    too_many_files: bool,
+    /// Whether a file dialog is open, it doesn't block the rest of the application.
+    #[serde(skip)]
+    file_dialog_open: Arc<AtomicBool>,
+    /// Number of files opened together and when, shown in the menu bar for a moment.
+    #[serde(skip)]
+    opened_files: Option<(usize, Instant)>,
+    /// Whether large files were opened in restricted mode, by path.
+    #[serde(default)]
+    restrict_decisions: HashMap<PathBuf, bool>,
+    #[serde(skip)]
+    show_settings: bool,
+    #[serde(skip)]
+    show_diagnostics: bool,
+    /// Show every file's lines as they are, suspending their filters and highlights.
+    #[serde(skip)]
+    show_raw: bool,
+    #[serde(skip)]
+    show_about: bool,
+    #[serde(skip)]
+    diagnostics: DiagnosticsPanel,
     #[serde(skip)]
     messages: MessageChannel,
     #[serde(skip)]
     behaviour: TabBehaviour,
 }
 
+/// What a file dialog is shown for, see [`LogTool::pick_file`].
+#[derive(Debug, Clone, Copy)]
+enum FilePick {
+    /// Files to open, in the order they were selected in.
+    Many,
+    /// A single file to open.
+    One,
+    /// Where to save a file, suggesting the given file name.
+    Save(&'static str),
+}
+
+#[derive(Debug)]
+struct PendingArchive {
+    path: PathBuf,
+    /// The files within the archive, or why they couldn't be listed. None while being listed.
+    members: Option<Result<Vec<String>, String>>,
+    selected: Option<usize>,
+}
+
+#[derive(Debug)]
+struct PendingOffset {
+    path: PathBuf,
+    /// Size of the file, or why it couldn't be determined.
+    size: Result<u64, String>,
+    offset: u64,
+}
+
 #[derive(Debug)]
 pub struct MessageChannel {
     sender: Sender<Message>,
@@ -160,22 +303,119 @@ impl Default for MessageChannel {
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
-pub struct TabBehaviour {}
+pub struct TabBehaviour {
+    /// Shared between all open files to avoid creating one watcher per file.
+    #[serde(skip)]
+    pub watcher: FileWatcher,
+    /// Copy of [`LogTool`]'s settings, kept in sync every frame.
+    #[serde(skip)]
+    pub settings: Settings,
+    /// Tab being renamed and the alias being edited, shown in a window by [`LogTool`].
+    #[serde(skip)]
+    pub renaming: Option<(TileId, String)>,
+    /// Error of an action taken from a tab, shown in a window by [`LogTool`].
+    #[serde(skip)]
+    pub error: Option<String>,
+    /// The shown pane keyboard shortcuts such as `/` apply to, the one last clicked.
+    #[serde(skip)]
+    pub keyboard_pane: Option<TileId>,
+    /// Panes whose snapshot button was clicked, opened in tabs of their own by [`LogTool`].
+    #[serde(skip)]
+    pub snapshot_requests: Vec<TileId>,
+    /// Files to open from an earlier offset than they were read from, and the suggested offset.
+    #[serde(skip)]
+    pub earlier_requests: Vec<(PathBuf, u64)>,
+    /// Copy of [`LogTool`]'s raw view toggle, see [`LogFile::show_raw`].
+    #[serde(skip)]
+    pub show_raw: bool,
+}
 
 impl Behavior<TabPane> for TabBehaviour {
     fn tab_title_for_pane(&mut self, pane: &TabPane) -> egui::WidgetText {
         match pane {
-            TabPane::LogFile(f) => f.filename.clone().into(),
+            TabPane::LogFile(f) => {
+                let mut title = if f.is_loading() {
+                    egui::RichText::new(format!("{} (loading…)", f.title()))
+                } else {
+                    egui::RichText::new(f.title())
+                };
+
+                if self.settings.color_tabs_by_severity {
+                    if let Some(color) = f.max_severity().and_then(|s| s.color()) {
+                        title = title.color(color);
+                    }
+                }
+
+                title.into()
+            }
         }
     }
 
+    fn on_tab_button(
+        &mut self,
+        tiles: &Tiles<TabPane>,
+        tile_id: TileId,
+        button_response: egui::Response,
+    ) -> egui::Response {
+        let Some(Tile::Pane(TabPane::LogFile(f))) = tiles.get(tile_id) else {
+            return button_response;
+        };
+
+        let mut rename = button_response.double_clicked();
+
+        button_response.context_menu(|ui| {
+            if ui.button("Rename…").clicked() {
+                rename = true;
+                ui.close_menu();
+            }
+
+            if ui.button("Show in folder").clicked() {
+                if let Err(e) = reveal::reveal(&f.path) {
+                    self.error = Some(format!("Unable to show {} in folder: {e}", f.filename));
+                }
+
+                ui.close_menu();
+            }
+
+            copy_path_buttons(ui, &f.path);
+        });
+
+        if rename {
+            self.renaming = Some((tile_id, f.title().to_owned()));
+        }
+
+        button_response
+    }
+
     fn pane_ui(
         &mut self,
         ui: &mut egui::Ui,
-        _tile_id: egui_tiles::TileId,
+        tile_id: egui_tiles::TileId,
         pane: &mut TabPane,
     ) -> UiResponse {
-        pane.ui(ui)
+        let clicked = ui.input(|i| i.pointer.any_pressed()) && ui.rect_contains_pointer(ui.max_rect());
+
+        if clicked || self.keyboard_pane.is_none() {
+            self.keyboard_pane = Some(tile_id);
+        }
+
+        let TabPane::LogFile(file) = pane;
+        file.has_keyboard = self.keyboard_pane == Some(tile_id);
+        file.show_raw = self.show_raw;
+
+        let response = pane.ui(ui, &self.watcher, &self.settings);
+
+        let TabPane::LogFile(file) = pane;
+
+        if file.take_snapshot_request() {
+            self.snapshot_requests.push(tile_id);
+        }
+
+        if let Some(offset) = file.take_earlier_request() {
+            self.earlier_requests.push((file.path.clone(), offset));
+        }
+
+        response
     }
 
     fn simplification_options(&self) -> SimplificationOptions {
@@ -190,16 +430,9 @@ impl Behavior<TabPane> for TabBehaviour {
     }
 
     fn on_tab_close(&mut self, tiles: &mut Tiles<TabPane>, tile_id: egui_tiles::TileId) -> bool {
-        if let Some(tile) = tiles.get(tile_id) {
-            match tile {
-                Tile::Pane(tab_pane) => match tab_pane {
-                    TabPane::LogFile(lfile) => {
-                        if let Some(thread) = lfile.thread.as_ref() {
-                            thread.abort();
-                        }
-                    }
-                },
-                _ => (),
+        if let Some(Tile::Pane(TabPane::LogFile(lfile))) = tiles.get(tile_id) {
+            if let Some(thread) = lfile.thread.as_ref() {
+                thread.abort();
             }
         }
 
@@ -214,11 +447,160 @@ impl LogTool {
 
         // Load previous app state (if any).
         // Note that you must enable the `persistence` feature for this to work.
-        if let Some(storage) = cc.storage {
-            return eframe::get_value(storage, eframe::APP_KEY).unwrap_or_default();
+        let mut app: Self = match cc.storage {
+            Some(storage) => eframe::get_value(storage, eframe::APP_KEY).unwrap_or_default(),
+            None => Default::default(),
+        };
+
+        // Restored files start their readers on their first frame.
+        if cc.storage.is_some() && !app.settings.restore_session {
+            app.tree = Self::create_tree();
+        }
+
+        #[cfg(feature = "control-socket")]
+        if let Err(e) = control::listen(app.messages.sender.clone(), cc.egui_ctx.clone()) {
+            error!("Unable to listen for commands: {e}");
         }
 
-        Default::default()
+        app
+    }
+
+    /// The open file at `path`, not counting files within archives.
+    #[cfg(feature = "control-socket")]
+    fn find_file(&mut self, path: &std::path::Path) -> Option<&mut LogFile> {
+        let path = std::path::absolute(path).ok()?;
+
+        self.tree.tiles.tiles_mut().find_map(|tile| match tile {
+            Tile::Pane(TabPane::LogFile(file))
+                if file.archive_member.is_none()
+                    && std::path::absolute(&file.path).ok().as_ref() == Some(&path) =>
+            {
+                Some(file)
+            }
+            _ => None,
+        })
+    }
+
+    /// Carries out the commands received over the control socket, answering each once its file
+    /// has been filtered. The files are filtered in the background, also while not shown.
+    #[cfg(feature = "control-socket")]
+    fn handle_pending_commands(&mut self, ctx: &egui::Context) {
+        for (command, responder) in std::mem::take(&mut self.pending_commands) {
+            match self.handle_control(&command, ctx) {
+                Some(response) => {
+                    let _ = responder.send(response);
+                    ctx.request_repaint();
+                }
+                None => self.pending_commands.push((command, responder)),
+            }
+        }
+    }
+
+    /// Carries out a command received over the control socket. None if it has to wait for the
+    /// file to be filtered, it's repainted once done.
+    #[cfg(feature = "control-socket")]
+    fn handle_control(
+        &mut self,
+        command: &control::ControlCommand,
+        ctx: &egui::Context,
+    ) -> Option<control::ControlResponse> {
+        use control::{ControlCommand, ControlResponse};
+        use logfile::{Filter, Search};
+        use std::io::Write;
+
+        let not_open = |path: &std::path::Path| {
+            Some(ControlResponse::error(format!("{} isn't open", path.display())))
+        };
+        let default_format = self.settings.default_format.clone();
+
+        let response = match command {
+            ControlCommand::Open { path } => {
+                // Relative to the working directory, the way the files are told apart.
+                let path = std::path::absolute(path).unwrap_or(path.clone());
+
+                if !path.exists() {
+                    return Some(ControlResponse::error(format!("{} doesn't exist", path.display())));
+                }
+
+                if self.find_file(&path).is_none() {
+                    self.open_files(vec![path]);
+                }
+
+                ControlResponse::ok()
+            }
+            ControlCommand::SetFilter {
+                path,
+                search,
+                mode,
+                case_insensitive,
+            } => {
+                let search = Search::new(search.clone(), *mode, *case_insensitive);
+
+                if !search.is_empty() && !search.is_ready() {
+                    return Some(ControlResponse::error("Invalid search"));
+                }
+
+                match self.find_file(path) {
+                    Some(file) => {
+                        file.set_filter(Filter::new(search));
+                        ControlResponse::ok()
+                    }
+                    None => return not_open(path),
+                }
+            }
+            ControlCommand::CountMatches { path } => match self.find_file(path) {
+                Some(file) => {
+                    if !file.update_filtering(ctx, &default_format) {
+                        return None;
+                    }
+
+                    let (matches, lines) = file.count_matches();
+
+                    ControlResponse {
+                        matches: Some(matches),
+                        lines: Some(lines),
+                        ..ControlResponse::ok()
+                    }
+                }
+                None => return not_open(path),
+            },
+            ControlCommand::Export { path, destination } => {
+                // Relative paths would depend on where the application was started from.
+                if !destination.is_absolute() {
+                    return Some(ControlResponse::error("The destination has to be an absolute path"));
+                }
+
+                match self.find_file(path) {
+                    Some(file) => {
+                        if !file.update_filtering(ctx, &default_format) {
+                            return None;
+                        }
+
+                        let lines = file.filtered_lines();
+                        // Never overwrites a file, see `ControlCommand::Export`.
+                        let written = std::fs::OpenOptions::new()
+                            .write(true)
+                            .create_new(true)
+                            .open(destination)
+                            .and_then(|mut f| f.write_all(lines.concat().as_bytes()));
+
+                        match written {
+                            Ok(()) => ControlResponse {
+                                matches: Some(lines.len()),
+                                ..ControlResponse::ok()
+                            },
+                            Err(e) => ControlResponse::error(format!(
+                                "Unable to write {}: {e}",
+                                destination.display()
+                            )),
+                        }
+                    }
+                    None => return not_open(path),
+                }
+            }
+        };
+
+        Some(response)
     }
 
     fn create_tree() -> egui_tiles::Tree<TabPane> {
@@ -234,7 +616,7 @@ impl LogTool {
         Tree::new("logtool_treepanes", root, tiles)
     }
 
-    pub fn add_tile(&mut self, tab: TabPane) {
+    pub fn add_tile(&mut self, tab: TabPane) -> TileId {
         debug!("Add {:?}", tab);
         let id = self.tree.tiles.insert_pane(tab);
 
@@ -245,9 +627,8 @@ impl LogTool {
                     root_tile.add_child(id);
                     debug!("to {:?}", root_tile);
 
-                    match root_tile {
-                        Container::Tabs(r) => r.set_active(id),
-                        _ => (),
+                    if let Container::Tabs(r) = root_tile {
+                        r.set_active(id);
                     }
                 }
                 Some(Tile::Pane(_)) => (),
@@ -257,6 +638,713 @@ impl LogTool {
             self.tree.root = Some(self.tree.tiles.insert_tab_tile(vec![id]));
             debug!("No root!");
         }
+
+        id
+    }
+
+    /// Opens each of the files in a new tab, or switches to its tab if it's already open. The tabs
+    /// are added in the order of `files`.
+    ///
+    /// When opening several files, those already open are left as they are rather than asking
+    /// about each of them, and the tab of the first file is shown.
+    pub fn open_files(&mut self, files: Vec<PathBuf>) {
+        debug!("{files:?}");
+
+        let several = files.len() > 1;
+        let mut first_tile = None;
+
+        for (i, path) in files.iter().enumerate() {
+            let tile = if ArchiveKind::from_path(path).is_some() {
+                // Listing the files of a compressed tar archive requires decompressing all of it.
+                let sender = self.messages.sender.clone();
+                let archive = path.clone();
+
+                std::thread::spawn(move || {
+                    let members = archive::list_members(&archive).map_err(|e| e.to_string());
+
+                    if let Err(e) = sender.send(Message::ArchiveListed(archive, members)) {
+                        error!("Unable to send the files within the archive: {e}");
+                    }
+                });
+
+                self.pending_archives.push(PendingArchive {
+                    members: None,
+                    path: path.clone(),
+                    selected: None,
+                });
+                None
+            } else if let Some(id) = self.find_open_tile(path).filter(|_| several) {
+                Some(id)
+            } else {
+                self.open_file(path.clone())
+            };
+
+            if i == 0 {
+                first_tile = tile;
+            }
+        }
+
+        if let Some(id) = first_tile.filter(|_| several) {
+            self.tree.make_active(|t_id, _t| id == t_id);
+        }
+
+        // In reverse, so the first file ends up at the top of the recent files.
+        for path in files.into_iter().rev() {
+            self.add_recent_file(path);
+        }
+    }
+
+    /// Adds `path` to the top of the recent files, relative to the log root if it's within it.
+    fn add_recent_file(&mut self, path: PathBuf) {
+        let path = self.settings.relative_to_log_root(&path);
+
+        // TODO: Move from whatever position to front
+        if !self.recent_files.contains(&path) {
+            self.recent_files.push_front(path);
+        } else {
+            let filtered = self
+                .recent_files
+                .iter()
+                .filter(|p| p != &&path)
+                .map(|p| p.to_owned());
+            self.recent_files = VecDeque::from_iter(filtered);
+            self.recent_files.push_front(path);
+        }
+
+        if self.recent_files.len() > MAX_RECENT_FILES {
+            self.recent_files.pop_back();
+        }
+    }
+
+    /// Asks for the byte offset to start reading a file at, see [`LogFile::with_start_offset`].
+    fn offset_window(&mut self, ctx: &egui::Context) {
+        let Some(pending) = self.pending_offset.as_mut() else {
+            return;
+        };
+
+        let mut open = true;
+        let mut confirmed = false;
+
+        egui::Window::new("Open from offset")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(format!("Start reading {} at", pending.path.display()));
+
+                match pending.size {
+                    Err(ref e) => {
+                        ui.colored_label(ui.visuals().error_fg_color, e);
+                    }
+                    Ok(size) => {
+                        ui.horizontal(|ui| {
+                            ui.add(
+                                egui::DragValue::new(&mut pending.offset)
+                                    .range(0..=size)
+                                    .speed(1024 * 1024)
+                                    .suffix(" bytes"),
+                            );
+                            ui.label(format!(
+                                "{} of {}",
+                                logfile::humanreadable_bytes(pending.offset),
+                                logfile::humanreadable_bytes(size)
+                            ));
+                        });
+
+                        ui.label(
+                            "Reading starts at the first line starting at or after the offset, \
+                            and follows the end of the file.",
+                        );
+
+                        confirmed = ui.button("Open").clicked();
+                    }
+                }
+            });
+
+        if confirmed {
+            if let Some(pending) = self.pending_offset.take() {
+                self.add_tile(TabPane::LogFile(
+                    LogFile::new(pending.path.clone(), Vec::new(), self.default_display.clone())
+                        .with_start_offset(pending.offset),
+                ));
+                self.add_recent_file(pending.path);
+            }
+        } else if !open {
+            self.pending_offset = None;
+        }
+    }
+
+    /// The tab of the file at `path`, if it's open.
+    fn find_open_tile(&self, path: &std::path::Path) -> Option<TileId> {
+        let mut matching_tile = None;
+
+        for (id, tile) in self.tree.tiles.iter() {
+            match tile {
+                Tile::Pane(pane) => match pane {
+                    TabPane::LogFile(file) => {
+                        if file.path == path && file.archive_member.is_none() {
+                            matching_tile = Some(*id);
+                        }
+                    }
+                },
+                Tile::Container(_) => (),
+            }
+        }
+
+        matching_tile
+    }
+
+    /// Opens the file at `path`, returns the tab showing it unless asking whether to open it again.
+    fn open_file(&mut self, path: PathBuf) -> Option<TileId> {
+        match (self.find_open_tile(&path), self.settings.reopen) {
+            (Some(id), ReopenBehaviour::GoToExisting) => {
+                self.tree.make_active(|t_id, _t| id == t_id);
+                Some(id)
+            }
+            (Some(id), ReopenBehaviour::Ask) => {
+                self.pending_reopen.push((path, id));
+                None
+            }
+            (None, _) | (Some(_), ReopenBehaviour::OpenNew) => {
+                Some(self.open_new_view(path))
+            }
+        }
+    }
+
+    fn open_new_view(&mut self, path: PathBuf) -> TileId {
+        self.add_tile(TabPane::LogFile(LogFile::new(
+            path,
+            Vec::new(),
+            self.default_display.clone(),
+        )))
+    }
+
+    /// Shows what's going on with opening files, in the menu bar.
+    fn status_ui(&mut self, ui: &mut egui::Ui) {
+        if self.file_dialog_open.load(Ordering::Relaxed) {
+            ui.add_space(16.0);
+            ui.spinner();
+            ui.label("Waiting for the file dialog…");
+        }
+
+        match self.opened_files {
+            Some((count, opened)) if opened.elapsed() < STATUS_DURATION => {
+                ui.add_space(16.0);
+                ui.weak(format!("Opening {count} files…"));
+                ui.ctx().request_repaint_after(STATUS_DURATION - opened.elapsed());
+            }
+            Some(_) => self.opened_files = None,
+            None => (),
+        }
+    }
+
+    /// Asks what to do about the files opened which were already open, one at a time.
+    fn reopen_window(&mut self, ctx: &egui::Context) {
+        let Some((path, id)) = self.pending_reopen.first().cloned() else {
+            return;
+        };
+
+        let mut choice = None;
+
+        egui::Window::new("File already open")
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "{} is already open, the existing view keeps its own filters and highlights.",
+                    path.display()
+                ));
+
+                ui.horizontal(|ui| {
+                    let existing = ui.button("Go to existing view");
+
+                    // Keeps the previous behaviour when simply pressing enter.
+                    if existing.clicked() || ui.input(|i| i.key_pressed(Key::Enter)) {
+                        choice = Some(ReopenBehaviour::GoToExisting);
+                    }
+
+                    if ui.button("Open a new view").clicked() {
+                        choice = Some(ReopenBehaviour::OpenNew);
+                    }
+                });
+
+                ui.checkbox(&mut self.remember_reopen_choice, "Remember my choice");
+            });
+
+        let Some(choice) = choice else {
+            return;
+        };
+
+        self.pending_reopen.remove(0);
+
+        if self.remember_reopen_choice {
+            self.settings.reopen = choice;
+            self.remember_reopen_choice = false;
+        }
+
+        match choice {
+            ReopenBehaviour::OpenNew => {
+                self.open_new_view(path);
+            }
+            _ => {
+                self.tree.make_active(|t_id, _t| id == t_id);
+            }
+        }
+    }
+
+    /// Lets the user pick which file within an opened archive to open, one archive at a time.
+    fn archive_window(&mut self, ctx: &egui::Context) {
+        let Some(pending) = self.pending_archives.first_mut() else {
+            return;
+        };
+
+        let path = pending.path.clone();
+        let mut open = true;
+        let mut chosen = None;
+
+        egui::Window::new("Open from archive")
+            .open(&mut open)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                ui.label(format!("Pick a file within {}", path.display()));
+
+                match &pending.members {
+                    None => {
+                        ui.horizontal(|ui| {
+                            ui.spinner();
+                            ui.label("Listing the files within the archive...");
+                        });
+                        // The listing arrives as a message, which doesn't repaint by itself.
+                        ui.ctx().request_repaint_after(Duration::from_millis(100));
+                    }
+                    Some(Err(e)) => {
+                        ui.colored_label(ui.visuals().error_fg_color, e);
+                    }
+                    Some(Ok(members)) if members.is_empty() => {
+                        ui.label("The archive doesn't contain any files.");
+                    }
+                    Some(Ok(members)) => {
+                        egui::ScrollArea::vertical()
+                            .max_height(320.0)
+                            .show(ui, |ui| {
+                                for (i, member) in members.iter().enumerate() {
+                                    let response =
+                                        ui.selectable_label(pending.selected == Some(i), member);
+
+                                    if response.clicked() {
+                                        pending.selected = Some(i);
+                                    }
+
+                                    if response.double_clicked() {
+                                        chosen = Some(member.clone());
+                                    }
+                                }
+                            });
+
+                        if ui
+                            .add_enabled(pending.selected.is_some(), egui::Button::new("Open"))
+                            .on_hover_text("Archived files are read once, changes to the archive aren't followed")
+                            .clicked()
+                        {
+                            chosen = pending.selected.map(|i| members[i].clone());
+                        }
+                    }
+                }
+            });
+
+        if let Some(member) = chosen {
+            self.pending_archives.remove(0);
+            self.add_tile(TabPane::LogFile(
+                LogFile::new(path, Vec::new(), self.default_display.clone())
+                    .with_archive_member(member),
+            ));
+        } else if !open {
+            self.pending_archives.remove(0);
+        }
+    }
+
+    /// Opens the file of a location such as `app.log:14233`, scrolling to the line if given. The
+    /// path should be absolute, to find the tab it's opened in.
+    pub fn open_location(&mut self, path: PathBuf, line_number: Option<u64>) {
+        self.open_files(vec![path.clone()]);
+
+        if let Some(line_number) = line_number {
+            for tile in self.tree.tiles.tiles_mut() {
+                if let Tile::Pane(TabPane::LogFile(file)) = tile {
+                    if file.path == path {
+                        file.scroll_to_line(line_number);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Lets the user edit the alias of the tab being renamed, if any.
+    fn rename_window(&mut self, ctx: &egui::Context) {
+        let Some((tile_id, alias)) = self.behaviour.renaming.as_mut() else {
+            return;
+        };
+
+        let mut open = true;
+        let mut done = false;
+        let mut new_alias = None;
+
+        egui::Window::new("Rename tab")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let response = ui.text_edit_singleline(alias);
+                response.request_focus();
+
+                ui.horizontal(|ui| {
+                    if ui.button("Rename").clicked()
+                        || (response.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter)))
+                    {
+                        let alias = alias.trim();
+                        new_alias = Some((!alias.is_empty()).then(|| alias.to_owned()));
+                        done = true;
+                    }
+
+                    if ui
+                        .button("Reset")
+                        .on_hover_text("Use the filename as the title")
+                        .clicked()
+                    {
+                        new_alias = Some(None);
+                        done = true;
+                    }
+
+                    done |= ui.button("Cancel").clicked();
+                });
+            });
+
+        if let Some(alias) = new_alias {
+            if let Some(Tile::Pane(TabPane::LogFile(f))) = self.tree.tiles.get_mut(*tile_id) {
+                f.alias = alias;
+            }
+        }
+
+        if done || !open {
+            self.behaviour.renaming = None;
+        }
+    }
+
+    fn error_window(&mut self, ctx: &egui::Context) {
+        let Some(message) = self.behaviour.error.as_ref() else {
+            return;
+        };
+
+        let mut open = true;
+        let mut done = false;
+
+        egui::Window::new("Error")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(message);
+                done = ui.button("Ok").clicked();
+            });
+
+        if done || !open {
+            self.behaviour.error = None;
+        }
+    }
+
+    fn open_file_count(&self) -> usize {
+        self.tree
+            .tiles
+            .tiles()
+            .filter(|tile| matches!(tile, Tile::Pane(TabPane::LogFile(_))))
+            .count()
+    }
+
+    /// Closes the least recently viewed files, or warns about it, if opening files went beyond
+    /// the configured maximum.
+    fn limit_open_files(&mut self, previously_open: usize) {
+        let max = self.settings.max_open_files;
+
+        if self.open_file_count() <= max {
+            return;
+        }
+
+        if self.settings.close_least_recently_viewed {
+            self.close_least_recently_viewed();
+        } else if previously_open <= max {
+            self.too_many_files = true;
+        }
+    }
+
+    /// Closes the files viewed the longest time ago until no more than the configured maximum
+    /// are open. Restored files which haven't been viewed yet are closed first.
+    ///
+    /// Snapshots and files only kept in memory are left open, as they can't be opened again.
+    fn close_least_recently_viewed(&mut self) {
+        let excess = self
+            .open_file_count()
+            .saturating_sub(self.settings.max_open_files);
+        let mut files: Vec<_> = self
+            .tree
+            .tiles
+            .iter()
+            .filter_map(|(id, tile)| match tile {
+                Tile::Pane(TabPane::LogFile(file))
+                    if file.snapshot.is_none() && !file.is_in_memory() =>
+                {
+                    Some((*id, file.last_viewed))
+                }
+                _ => None,
+            })
+            .collect();
+        files.sort_by_key(|(_, last_viewed)| *last_viewed);
+
+        for (id, _) in files.into_iter().take(excess) {
+            if let Some(Tile::Pane(TabPane::LogFile(file))) = self.tree.tiles.get(id) {
+                debug!("Closing least recently viewed {}", file.filename);
+
+                if let Some(thread) = file.thread.as_ref() {
+                    thread.abort();
+                }
+            }
+
+            self.tree.remove_recursively(id);
+        }
+    }
+
+    fn too_many_files_window(&mut self, ctx: &egui::Context) {
+        if !self.too_many_files {
+            return;
+        }
+
+        let mut open = true;
+        let mut done = false;
+
+        egui::Window::new("Many files open")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "{} files are open, more than the max of {}.",
+                    self.open_file_count(),
+                    self.settings.max_open_files
+                ));
+                ui.label(
+                    "Each open file is read and watched for changes, which uses up memory and \
+                    file handles.",
+                );
+
+                ui.horizontal(|ui| {
+                    if ui.button("Close least recently viewed").clicked() {
+                        self.close_least_recently_viewed();
+                        done = true;
+                    }
+
+                    if ui.button("Keep them open").clicked() {
+                        done = true;
+                    }
+                });
+            });
+
+        if done || !open {
+            self.too_many_files = false;
+        }
+    }
+
+    /// Shows a file dialog, starting in the log root if there is one and only listing the files
+    /// matching `filter` if given, without blocking the UI. What was picked is sent as the message
+    /// made by `message`, unless the dialog was cancelled.
+    fn pick_file(
+        &self,
+        frame: &eframe::Frame,
+        ctx: &egui::Context,
+        pick: FilePick,
+        filter: Option<(&str, &[&str])>,
+        message: impl FnOnce(Vec<PathBuf>) -> Message + Send + 'static,
+    ) {
+        let sender = self.messages.sender.clone();
+        let dialog_open = self.file_dialog_open.clone();
+        let ctx = ctx.clone();
+
+        let mut dialog = rfd::AsyncFileDialog::new().set_parent(frame);
+
+        if let Some(root) = self.settings.log_root.as_ref() {
+            dialog = dialog.set_directory(root);
+        }
+
+        if let Some((name, extensions)) = filter {
+            dialog = dialog.add_filter(name, extensions);
+        }
+
+        dialog_open.store(true, Ordering::Relaxed);
+
+        tokio::spawn(async move {
+            // In the order the dialog returns them, which is the order they were selected in
+            // where the platform keeps track of it.
+            let files: Vec<PathBuf> = match pick {
+                FilePick::Many => dialog.pick_files().await.unwrap_or_default(),
+                FilePick::One => dialog.pick_file().await.into_iter().collect(),
+                FilePick::Save(name) => {
+                    dialog.set_file_name(name).save_file().await.into_iter().collect()
+                }
+            }
+            .iter()
+            .map(|file| file.path().to_owned())
+            .collect();
+
+            dialog_open.store(false, Ordering::Relaxed);
+            ctx.request_repaint();
+
+            if !files.is_empty() {
+                if let Err(e) = sender.send(message(files)) {
+                    error!("Unable to send to message channel: {e:?}");
+                }
+            }
+        });
+    }
+
+    /// Writes the open files, in the order of their tabs, to the workspace file at `path`.
+    fn save_workspace(&mut self, path: &Path) {
+        let files = self
+            .ordered_panes()
+            .into_iter()
+            .filter_map(|id| match self.tree.tiles.get(id) {
+                Some(Tile::Pane(TabPane::LogFile(file))) => Some(file),
+                _ => None,
+            })
+            .collect();
+
+        if let Err(e) = workspace::save(path, files, self.settings.log_root.as_deref()) {
+            self.behaviour.error = Some(format!("Unable to save the workspace: {e}"));
+        }
+    }
+
+    /// Replaces the open files with those of the workspace file at `path`, which start reading
+    /// once shown like files restored on startup.
+    fn open_workspace(&mut self, path: &Path) {
+        let files = match workspace::load(path, self.settings.log_root.as_deref()) {
+            Ok(files) => files,
+            Err(e) => {
+                self.behaviour.error = Some(format!("Unable to open the workspace: {e}"));
+                return;
+            }
+        };
+
+        for tile in self.tree.tiles.tiles() {
+            if let Tile::Pane(TabPane::LogFile(file)) = tile {
+                if let Some(thread) = file.thread.as_ref() {
+                    thread.abort();
+                }
+            }
+        }
+
+        self.tree = Self::create_tree();
+        self.behaviour.keyboard_pane = None;
+
+        let mut first_tile = None;
+
+        for file in files {
+            let id = self.add_tile(TabPane::LogFile(file));
+            first_tile.get_or_insert(id);
+        }
+
+        if let Some(id) = first_tile {
+            self.tree.make_active(|t_id, _t| id == t_id);
+        }
+    }
+
+    /// Reads every open file again from scratch, e.g. after they've all been rotated at once.
+    pub fn reload_all(&mut self) {
+        for tile in self.tree.tiles.tiles_mut() {
+            if let Tile::Pane(TabPane::LogFile(file)) = tile {
+                file.reload();
+            }
+        }
+    }
+
+    /// Remembers the restricted mode choices made in open files, and makes files which haven't
+    /// been opened yet use the choice previously made for their path.
+    fn sync_restrict_decisions(&mut self) {
+        for tile in self.tree.tiles.tiles_mut() {
+            let Tile::Pane(TabPane::LogFile(file)) = tile else {
+                continue;
+            };
+
+            if file.archive_member.is_some() {
+                continue;
+            }
+
+            match file.restrict_decision {
+                Some(decision) => {
+                    if self.restrict_decisions.get(&file.path) != Some(&decision) {
+                        self.restrict_decisions.insert(file.path.clone(), decision);
+                    }
+                }
+                None => file.restrict_decision = self.restrict_decisions.get(&file.path).copied(),
+            }
+        }
+    }
+
+    /// All panes in the order they're shown, e.g. the order of the tabs.
+    fn ordered_panes(&self) -> Vec<TileId> {
+        fn collect(tiles: &Tiles<TabPane>, id: TileId, panes: &mut Vec<TileId>) {
+            match tiles.get(id) {
+                Some(Tile::Pane(_)) => panes.push(id),
+                Some(Tile::Container(container)) => {
+                    for child in container.children() {
+                        collect(tiles, *child, panes);
+                    }
+                }
+                None => (),
+            }
+        }
+
+        let mut panes = Vec::new();
+
+        if let Some(root) = self.tree.root() {
+            collect(&self.tree.tiles, root, &mut panes);
+        }
+
+        panes
+    }
+
+    /// Ctrl+Tab/Ctrl+Shift+Tab cycles through the tabs, Ctrl+1..9 selects a specific tab.
+    fn handle_tab_navigation(&mut self, ctx: &egui::Context) {
+        let (next, previous, number) = ctx.input_mut(|i| {
+            // Shift is ignored when matching Ctrl+Tab, so check the more specific shortcut first.
+            let previous = i.consume_key(Modifiers::CTRL | Modifiers::SHIFT, Key::Tab);
+            let next = !previous && i.consume_key(Modifiers::CTRL, Key::Tab);
+            let number = TAB_NUMBER_KEYS
+                .iter()
+                .position(|k| i.consume_key(Modifiers::CTRL, *k));
+
+            (next, previous, number)
+        });
+
+        if !next && !previous && number.is_none() {
+            return;
+        }
+
+        let panes = self.ordered_panes();
+
+        if panes.is_empty() {
+            return;
+        }
+
+        let active = self.tree.active_tiles();
+        let current = panes.iter().position(|p| active.contains(p));
+
+        let target = match (number, current) {
+            (Some(n), _) => panes.get(n).copied(),
+            (None, None) => panes.first().copied(),
+            (None, Some(c)) if next => panes.get((c + 1) % panes.len()).copied(),
+            (None, Some(c)) => panes.get((c + panes.len() - 1) % panes.len()).copied(),
+        };
+
+        if let Some(target) = target {
+            self.tree.make_active(|id, _| id == target);
+        }
     }
 }
 
@@ -266,7 +1354,25 @@ impl Default for LogTool {
             tree: Self::create_tree(),
             messages: MessageChannel::default(),
             recent_files: VecDeque::new(),
-            behaviour: TabBehaviour {},
+            selected_recent_files: Vec::new(),
+            settings: Settings::default(),
+            default_display: DisplayOptions::default(),
+            pending_reopen: Vec::new(),
+            remember_reopen_choice: false,
+            pending_archives: Vec::new(),
+            #[cfg(feature = "control-socket")]
+            pending_commands: Vec::new(),
+            pending_offset: None,
+            too_many_files: false,
+            file_dialog_open: Arc::default(),
+            opened_files: None,
+            restrict_decisions: HashMap::new(),
+            show_settings: false,
+            show_diagnostics: false,
+            show_raw: false,
+            show_about: false,
+            diagnostics: DiagnosticsPanel::default(),
+            behaviour: TabBehaviour::default(),
         }
     }
 }
@@ -280,64 +1386,59 @@ impl eframe::App for LogTool {
         // Put your widgets into a `SidePanel`, `TopBottomPanel`, `CentralPanel`, `Window` or `Area`.
         // For inspiration and more examples, go to https://emilk.github.io/egui
 
-        if let Ok(msg) = self.messages.receiver.try_recv() {
+        self.handle_tab_navigation(ctx);
+        let previously_open = self.open_file_count();
+
+        while let Ok(msg) = self.messages.receiver.try_recv() {
             debug!("Got message! {msg:?}");
 
             match msg {
                 Message::FilesPicked(files) => {
-                    debug!("{files:?}");
-                    for path in files {
-                        let mut matching_tile = None;
-
-                        for (id, tile) in self.tree.tiles.iter() {
-                            match tile {
-                                Tile::Pane(pane) => match pane {
-                                    TabPane::LogFile(file) => {
-                                        if file.path == path {
-                                            matching_tile = Some(id.clone());
-                                        }
-                                    }
-                                },
-                                Tile::Container(_) => (),
-                            }
-                        }
-
-                        match matching_tile {
-                            Some(id) => {
-                                self.tree.make_active(|t_id, _t| id == t_id);
-                            }
-                            None => {
-                                self.add_tile(TabPane::LogFile(LogFile::new(
-                                    path.clone(),
-                                    Vec::new(),
-                                )));
-                            }
-                        }
+                    if files.len() > 1 {
+                        self.opened_files = Some((files.len(), Instant::now()));
+                    }
 
-                        // TODO: Move from whatever position to front
-                        if !self.recent_files.contains(&path) {
-                            self.recent_files.push_front(path);
-                        } else {
-                            let filtered = self
-                                .recent_files
-                                .iter()
-                                .filter(|p| p != &&path)
-                                .map(|p| p.to_owned());
-                            self.recent_files = VecDeque::from_iter(filtered);
-                            self.recent_files.push_front(path);
-                        }
+                    self.open_files(files);
 
-                        if self.recent_files.len() > MAX_RECENT_FILES {
-                            self.recent_files.pop_back();
-                        }
+                    debug!("{:?}", self.tree.tiles);
+                    ctx.request_repaint();
+                }
+                Message::OffsetFilePicked(path) => {
+                    self.pending_offset = Some(PendingOffset {
+                        size: std::fs::metadata(&path)
+                            .map(|meta| meta.len())
+                            .map_err(|e| format!("Unable to read the size of the file: {e}")),
+                        path,
+                        offset: 0,
+                    });
+                    ctx.request_repaint();
+                }
+                Message::WorkspacePicked(path) => {
+                    self.open_workspace(&path);
+                    ctx.request_repaint();
+                }
+                Message::WorkspaceSavePicked(path) => self.save_workspace(&path),
+                Message::ArchiveListed(path, members) => {
+                    if let Some(pending) = self
+                        .pending_archives
+                        .iter_mut()
+                        .find(|p| p.path == path && p.members.is_none())
+                    {
+                        pending.members = Some(members);
                     }
 
-                    debug!("{:?}", self.tree.tiles);
                     ctx.request_repaint();
                 }
+                #[cfg(feature = "control-socket")]
+                Message::Control(command, responder) => {
+                    self.pending_commands.push((command, responder));
+                }
             }
         }
 
+        #[cfg(feature = "control-socket")]
+        self.handle_pending_commands(ctx);
+
         egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
             // The top panel is often a good place for a menu bar:
 
@@ -348,27 +1449,55 @@ impl eframe::App for LogTool {
                         // TODO: Add "Open File", maybe even a list of X recent files?
 
                         if ui.button("Open File").clicked() {
-                            let file_sender = self.messages.sender.clone();
-
-                            let dialog = rfd::AsyncFileDialog::new().set_parent(_frame);
-
-                            tokio::spawn(async move {
-                                if let Some(files) = dialog.pick_files().await {
-                                    if let Err(e) = file_sender.send(Message::FilesPicked(
-                                        files
-                                            .into_iter()
-                                            .map(|f| f.path().to_owned())
-                                            .collect::<Vec<PathBuf>>(),
-                                    )) {
-                                        // TODO: Error handling
-                                        error!("Unable to send to message channel: {e:?}")
-                                    }
-                                }
+                            self.pick_file(_frame, ctx, FilePick::Many, None, Message::FilesPicked);
+                            ui.close_menu();
+                        }
+
+                        if ui
+                            .button("Open from offset")
+                            .on_hover_text("Start reading a large file somewhere in the middle")
+                            .clicked()
+                        {
+                            self.pick_file(_frame, ctx, FilePick::One, None, |mut files| {
+                                Message::OffsetFilePicked(files.remove(0))
                             });
+                            ui.close_menu();
+                        }
+
+                        ui.separator();
+
+                        if ui
+                            .button("Open workspace…")
+                            .on_hover_text("Replace the open files with those of a workspace file")
+                            .clicked()
+                        {
+                            let pick = FilePick::One;
+                            self.pick_file(_frame, ctx, pick, WORKSPACE_FILTER, |mut files| {
+                                Message::WorkspacePicked(files.remove(0))
+                            });
+                            ui.close_menu();
+                        }
 
+                        if ui
+                            .add_enabled(
+                                self.open_file_count() > 0,
+                                egui::Button::new("Save workspace…"),
+                            )
+                            .on_hover_text(
+                                "Save the open files with their filters, highlights and display \
+                                options to a file",
+                            )
+                            .clicked()
+                        {
+                            let pick = FilePick::Save("workspace.json");
+                            self.pick_file(_frame, ctx, pick, WORKSPACE_FILTER, |mut files| {
+                                Message::WorkspaceSavePicked(files.remove(0))
+                            });
                             ui.close_menu();
                         }
 
+                        ui.separator();
+
                         if self.recent_files.is_empty() {
                             // Extra spaces at end to add padding to ensure it keeps style when
                             // using it as a submenu button.
@@ -376,40 +1505,258 @@ impl eframe::App for LogTool {
                             ui.label("Recent files  ");
                         } else {
                             ui.menu_button("Recent files", |ui| {
+                                let mut files_to_open = Vec::new();
+
                                 for file in &self.recent_files {
-                                    if ui.button(file.to_string_lossy().to_string()).clicked() {
-                                        if let Err(e) = self
-                                            .messages
-                                            .sender
-                                            .send(Message::FilesPicked(vec![file.to_owned()]))
-                                        {
-                                            // TODO: Error handling
-                                            error!("Unable to send message to channel: {e:?}");
+                                    let selected = self.selected_recent_files.contains(file);
+
+                                    let response = ui
+                                        .selectable_label(selected, file.to_string_lossy().to_string())
+                                        .on_hover_text("Ctrl-click to select several files");
+
+                                    response.context_menu(|ui| {
+                                        if ui.button("Show in folder").clicked() {
+                                            let path = self.settings.resolve_in_log_root(file);
+
+                                            if let Err(e) = reveal::reveal(&path) {
+                                                self.behaviour.error = Some(format!(
+                                                    "Unable to show {} in folder: {e}",
+                                                    file.display()
+                                                ));
+                                            }
+
+                                            ui.close_menu();
                                         }
 
-                                        ui.close_menu()
+                                        copy_path_buttons(ui, &self.settings.resolve_in_log_root(file));
+                                    });
+
+                                    if response.clicked() {
+                                        if ui.input(|i| i.modifiers.command) {
+                                            if selected {
+                                                self.selected_recent_files.retain(|f| f != file);
+                                            } else {
+                                                self.selected_recent_files.push(file.to_owned());
+                                            }
+                                        } else {
+                                            files_to_open = vec![file.to_owned()];
+                                        }
                                     }
                                 }
+
+                                ui.separator();
+
+                                if ui
+                                    .add_enabled(
+                                        !self.selected_recent_files.is_empty(),
+                                        egui::Button::new(format!(
+                                            "Open selected ({})",
+                                            self.selected_recent_files.len()
+                                        )),
+                                    )
+                                    .clicked()
+                                {
+                                    files_to_open = self.selected_recent_files.clone();
+                                }
+
+                                if ui.button("Open all recent").clicked() {
+                                    files_to_open = self.recent_files.iter().cloned().collect();
+                                }
+
+                                if !files_to_open.is_empty() {
+                                    // Recent files within the log root are relative to it.
+                                    let files_to_open = files_to_open
+                                        .iter()
+                                        .map(|path| self.settings.resolve_in_log_root(path))
+                                        .collect();
+
+                                    if let Err(e) = self
+                                        .messages
+                                        .sender
+                                        .send(Message::FilesPicked(files_to_open))
+                                    {
+                                        // TODO: Error handling
+                                        error!("Unable to send message to channel: {e:?}");
+                                    }
+
+                                    self.selected_recent_files.clear();
+                                    ui.close_menu()
+                                }
                             });
                         }
 
+                        if ui
+                            .button("Reload all files")
+                            .on_hover_text("Read every open file again from the start")
+                            .clicked()
+                        {
+                            self.reload_all();
+                            ui.close_menu();
+                        }
+
+                        if ui.button("Settings").clicked() {
+                            self.show_settings = true;
+                            ui.close_menu();
+                        }
+
                         if ui.button("Quit").clicked() {
                             ctx.send_viewport_cmd(egui::ViewportCommand::Close);
                         }
                     });
 
+                    ui.menu_button("View", |ui| {
+                        ui.checkbox(&mut self.show_diagnostics, "Diagnostics")
+                            .on_hover_text("Show the application's log messages");
+                        ui.checkbox(&mut self.show_raw, "Raw content").on_hover_text(
+                            "Show every file's lines as they are, without filters, highlights or display options, until unchecked",
+                        );
+                    });
+
+                    if self.show_raw
+                        && ui
+                            .selectable_label(true, "Raw")
+                            .on_hover_text("Filters and highlights are suspended, click to show them again")
+                            .clicked()
+                    {
+                        self.show_raw = false;
+                    }
+
+                    ui.menu_button("Help", |ui| {
+                        if ui.button("About").clicked() {
+                            self.show_about = true;
+                            ui.close_menu();
+                        }
+                    });
+
                     ui.add_space(16.0);
                 }
 
                 egui::widgets::global_dark_light_mode_buttons(ui);
+
+                self.status_ui(ui);
             });
         });
 
-        TopBottomPanel::bottom("bottom_panel").show(ctx, powered_by_egui_and_eframe);
+        if self.settings.show_footer {
+            TopBottomPanel::bottom("bottom_panel").show(ctx, powered_by_egui_and_eframe);
+        }
+
+        egui::Window::new(format!("About {APPLICATION_NAME}"))
+            .open(&mut self.show_about)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(format!("{APPLICATION_NAME} {}", env!("CARGO_PKG_VERSION")));
+                ui.add_space(8.0);
+                powered_by_egui_and_eframe(ui);
+            });
+
+        let mut apply_default_display = false;
+
+        egui::Window::new("Settings")
+            .open(&mut self.show_settings)
+            .show(ctx, |ui| {
+                self.settings.ui(ui);
+
+                if ui
+                    .add_enabled(
+                        !self.restrict_decisions.is_empty(),
+                        egui::Button::new(format!(
+                            "Forget restricted mode choices ({})",
+                            self.restrict_decisions.len()
+                        )),
+                    )
+                    .clicked()
+                {
+                    self.restrict_decisions.clear();
+                }
+
+                ui.separator();
+                ui.label("Display options for newly opened files");
+                self.default_display.ui(ui);
+
+                apply_default_display = ui
+                    .button("Apply to open files")
+                    .on_hover_text("Replace the display options of all open files with these")
+                    .clicked();
+            });
+
+        if apply_default_display {
+            for tile in self.tree.tiles.tiles_mut() {
+                if let Tile::Pane(TabPane::LogFile(file)) = tile {
+                    file.display = self.default_display.clone();
+                }
+            }
+        }
+
+        egui::Window::new("Diagnostics")
+            .open(&mut self.show_diagnostics)
+            .default_size([640.0, 320.0])
+            .show(ctx, |ui| {
+                self.diagnostics.ui(ui, &self.behaviour.watcher);
+            });
+
+        self.rename_window(ctx);
+        self.reopen_window(ctx);
+        self.archive_window(ctx);
+        self.offset_window(ctx);
+        self.error_window(ctx);
+        self.too_many_files_window(ctx);
+
+        self.behaviour.settings = self.settings.clone();
+        self.behaviour.show_raw = self.show_raw;
+
+        // Passed on to another shown pane if the tab was switched away from or closed.
+        let active = self.tree.active_tiles();
+        self.behaviour.keyboard_pane = self.behaviour.keyboard_pane.filter(|id| active.contains(id));
+
+        if self.settings.remember_restrict_decisions {
+            self.sync_restrict_decisions();
+        }
 
         CentralPanel::default().show(ctx, |ui| {
             self.tree.ui(&mut self.behaviour, ui);
         });
+
+        for id in std::mem::take(&mut self.behaviour.snapshot_requests) {
+            if let Some(Tile::Pane(TabPane::LogFile(file))) = self.tree.tiles.get(id) {
+                let snapshot = file.snapshot();
+                self.add_tile(TabPane::LogFile(snapshot));
+            }
+        }
+
+        if let Some((path, offset)) = self.behaviour.earlier_requests.pop() {
+            self.behaviour.earlier_requests.clear();
+            self.pending_offset = Some(PendingOffset {
+                size: std::fs::metadata(&path)
+                    .map(|meta| meta.len())
+                    .map_err(|e| format!("Unable to read the size of the file: {e}")),
+                path,
+                offset,
+            });
+        }
+
+        // However the files were opened this frame, e.g. picked, restored from a workspace or
+        // snapshotted.
+        if self.open_file_count() > previously_open {
+            self.limit_open_files(previously_open);
+            ctx.request_repaint();
+        }
+    }
+}
+
+/// Context menu buttons copying the full path or the file name of `path` to the clipboard.
+fn copy_path_buttons(ui: &mut egui::Ui, path: &Path) {
+    if ui.button("Copy full path").clicked() {
+        let full_path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+        ui.output_mut(|o| o.copied_text = full_path.to_string_lossy().into_owned());
+        ui.close_menu();
+    }
+
+    if ui.button("Copy file name").clicked() {
+        let name = path.file_name().unwrap_or(path.as_os_str());
+        ui.output_mut(|o| o.copied_text = name.to_string_lossy().into_owned());
+        ui.close_menu();
     }
 }
 
@@ -426,3 +1773,95 @@ fn powered_by_egui_and_eframe(ui: &mut egui::Ui) {
         ui.label(".");
     });
 }
+
+#[cfg(test)]
+mod test {
+    use super::{parse_location, DisplayOptions, Error, LogFile, LogTool, TabPane, Tile};
+    use std::error::Error as _;
+    use std::path::PathBuf;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    pub fn test_error_display() {
+        let error = Error::from(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "app.log is missing",
+        ));
+        assert_eq!(error.to_string(), "I/O error: app.log is missing");
+        assert!(error
+            .source()
+            .and_then(|e| e.downcast_ref::<std::io::Error>())
+            .is_some());
+
+        let error = Error::from("Unable to read the file");
+        assert_eq!(error.to_string(), "Unable to read the file");
+        assert_eq!(
+            error.source().map(ToString::to_string),
+            Some(String::from("Unable to read the file"))
+        );
+    }
+
+    #[test]
+    pub fn test_close_least_recently_viewed() {
+        let mut app = LogTool::default();
+        app.settings.max_open_files = 2;
+        let viewed = Instant::now();
+
+        let file = LogFile::new(PathBuf::from("app.log"), Vec::new(), DisplayOptions::default());
+        let mut snapshot = file.snapshot();
+        snapshot.last_viewed = Some(viewed);
+        app.add_tile(TabPane::LogFile(snapshot));
+
+        for (index, name) in ["older.log", "newer.log"].into_iter().enumerate() {
+            let mut file = LogFile::new(PathBuf::from(name), Vec::new(), DisplayOptions::default());
+            file.last_viewed = Some(viewed + Duration::from_secs(index as u64 + 1));
+            app.add_tile(TabPane::LogFile(file));
+        }
+
+        app.close_least_recently_viewed();
+
+        // The snapshot was viewed the longest time ago, but can't be opened again.
+        let mut titles: Vec<_> = app
+            .tree
+            .tiles
+            .iter()
+            .filter_map(|(_, tile)| match tile {
+                Tile::Pane(TabPane::LogFile(file)) => Some(file.title().to_owned()),
+                _ => None,
+            })
+            .collect();
+        titles.sort();
+        assert_eq!(titles.len(), 2);
+        assert!(titles[0].starts_with("app.log (snapshot @ "));
+        assert_eq!(titles[1], "newer.log");
+    }
+
+    #[test]
+    pub fn test_parse_location() {
+        assert_eq!(
+            parse_location("app.log:14233".into()),
+            (PathBuf::from("app.log"), Some(14233))
+        );
+        assert_eq!(
+            parse_location("/var/log/app.log".into()),
+            (PathBuf::from("/var/log/app.log"), None)
+        );
+        assert_eq!(
+            parse_location(r"C:\logs\app.log".into()),
+            (PathBuf::from(r"C:\logs\app.log"), None)
+        );
+        assert_eq!(
+            parse_location("12:30.log".into()),
+            (PathBuf::from("12:30.log"), None)
+        );
+
+        // A file whose name ends like a line number is taken as is. Windows doesn't allow them.
+        if cfg!(unix) {
+            let path = std::env::temp_dir().join("logglance_test_location.log:2");
+            std::fs::write(&path, "").expect("Should create file");
+            let location = parse_location(path.clone().into_os_string());
+            let _ = std::fs::remove_file(&path);
+            assert_eq!(location, (path, None));
+        }
+    }
+}
@@ -1,24 +1,41 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
 use encoding_rs::Encoding;
 use rayon::prelude::*;
 
-use std::collections::VecDeque;
-use std::ffi::OsStr;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashSet, VecDeque};
 use std::fmt::Debug;
+use std::ops::{Range, RangeInclusive};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::mpsc::{channel, Receiver, Sender, TryRecvError};
-use std::time::Instant;
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant, SystemTime};
 
 use eframe::egui::{
-    self, text::LayoutJob, Color32, Label, ScrollArea, TextFormat, TextStyle, Vec2, Widget,
+    self, text::LayoutJob, Color32, DragValue, FontId, Grid, Key, Label, Modifiers, Rect, RichText,
+    ScrollArea, Stroke, TextEdit, TextFormat, TextStyle, Vec2, Widget,
 };
 
+use crate::ansi::{parse_ansi, strip_ansi, AnsiEscapes};
+use crate::diff::{diff_lines, Change};
+use crate::format::{FormatPatterns, FormatProfile};
+use crate::logfmt;
+use crate::settings::Settings;
+use crate::table::{split_fields, TableOptions, TableSort};
+use crate::severity::{level_pattern, max_severity, max_severity_of_rows, Severity};
+use crate::watcher::{FileWatcher, WatchRegistration};
 use crate::Error;
 use egui_extras::{Size, StripBuilder};
-use notify::event::{MetadataKind, ModifyKind};
-use notify::{EventKind, RecursiveMode, Watcher};
+use notify::event::{DataChange, MetadataKind, ModifyKind, RenameMode};
+use notify::EventKind;
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
 use rayon::iter::IntoParallelRefIterator;
 use regex::{Regex, RegexBuilder};
 use serde::{Deserialize, Serialize};
+use tokio::sync::Notify;
 use tokio::task::JoinHandle;
 
 use tokio::fs::File;
@@ -29,7 +46,7 @@ use log::{debug, error};
 const SPACING_FOR_SCROLLBAR: f32 = 8.0;
 
 // TODO: Is there a way to make this dynamic?
-static AVAILABLE_ENCODINGS: [&'static Encoding; 34] = [
+pub static AVAILABLE_ENCODINGS: [&Encoding; 34] = [
     encoding_rs::UTF_8,
     encoding_rs::UTF_16BE,
     encoding_rs::UTF_16LE,
@@ -67,6 +84,27 @@ static AVAILABLE_ENCODINGS: [&'static Encoding; 34] = [
 ];
 
 const MAX_FILE_SIZE: u64 = (2u64.pow(30)) * 4; // 4GiB
+const ENCODING_SAMPLE_SIZE: u64 = 64 * 1024; // 64KiB
+const ENCODING_PREVIEW_LINES: usize = 50;
+/// Roughly what fits in a GitHub issue or comment.
+const MAX_MARKDOWN_COPY_BYTES: usize = 64 * 1024; // 64KiB
+/// A file is shown as actively written to while lines have arrived this recently.
+const ACTIVE_DURATION: Duration = Duration::from_secs(3);
+/// How long being caught up is shown for, fading out, once the file is no longer active.
+const CAUGHT_UP_FADE: Duration = Duration::from_millis(1500);
+/// Regexes compiling for longer than this show that they're being compiled, most compile well
+/// within it and would only make the indicator flicker.
+const COMPILING_INDICATOR_DELAY: Duration = Duration::from_millis(150);
+/// Number of the most recently read lines looked at to tell whether a file's encoding changed.
+const REDETECT_WINDOW_LINES: usize = 32;
+/// Number of lines within the window which have to fail to decode before detecting the encoding
+/// again.
+const REDETECT_THRESHOLD: usize = 8;
+/// The rate lines arrive at is averaged over this long.
+const THROUGHPUT_WINDOW: Duration = Duration::from_secs(10);
+/// Bytes read around the position scrubbed to, see [`LogFile::scrub_ui`].
+const SCRUB_WINDOW: u64 = 4 * 1024 * 1024; // 4MiB
+
 const MAX_ROWS: u64 = (10u64.pow(6)) * 120; // 120 million, filtering perfromance and general memory usage
                                             // takes a big hit around here. Better stop before.
 
@@ -74,6 +112,155 @@ pub fn humanreadable_bytes(bytes: u64) -> String {
     humansize::format_size(bytes, humansize::BINARY)
 }
 
+/// E.g. "1,203,114".
+pub fn humanreadable_count(count: u64) -> String {
+    let digits = count.to_string();
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3);
+
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+
+        grouped.push(digit);
+    }
+
+    grouped
+}
+
+/// E.g. "45s", "12m 5s" or "3h 20m".
+pub fn humanreadable_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+
+    match secs {
+        0..=59 => format!("{secs}s"),
+        60..=3599 => format!("{}m {}s", secs / 60, secs % 60),
+        _ => format!("{}h {}m", secs / 3600, secs % 3600 / 60),
+    }
+}
+
+/// Truncates lines longer than `max_length` bytes, returning the original length if it was. A
+/// `max_length` of 0 disables truncation.
+pub fn truncate_line(line: &mut String, max_length: usize) -> Option<usize> {
+    if max_length == 0 || line.len() <= max_length {
+        return None;
+    }
+
+    let length = line.len();
+    let mut end = max_length;
+    while !line.is_char_boundary(end) {
+        end -= 1;
+    }
+
+    line.truncate(end);
+    Some(length)
+}
+
+/// Wraps `lines` in a fenced Markdown code block, e.g. to paste into an issue, with `language` as
+/// its info string. The fence is longer than any run of backticks within the lines, so they can't
+/// end the block early. Returns the block and how many of the lines fit within `max_bytes`.
+pub fn markdown_code_block<'a>(lines: impl IntoIterator<Item = &'a str>, language: &str, max_bytes: usize) -> (String, usize) {
+    let mut body = String::new();
+    let mut count = 0;
+
+    for line in lines {
+        let line = line.trim_end_matches(['\n', '\r']);
+
+        if body.len() + line.len() + 1 > max_bytes {
+            break;
+        }
+
+        body.push_str(line);
+        body.push('\n');
+        count += 1;
+    }
+
+    let mut longest_run = 0;
+    let mut run = 0;
+
+    for c in body.chars() {
+        run = if c == '`' { run + 1 } else { 0 };
+        longest_run = longest_run.max(run);
+    }
+
+    let fence = "`".repeat((longest_run + 1).max(3));
+
+    (format!("{fence}{}\n{body}{fence}\n", language.trim()), count)
+}
+
+/// Byte offsets of the tabs expanded by [`expand_tabs`] and the spaces inserted for each of them.
+#[derive(Debug, Default)]
+pub struct TabStops(Vec<(usize, usize)>);
+
+impl TabStops {
+    /// The offset within the expanded text of `offset` within the original text.
+    pub fn map(&self, offset: usize) -> usize {
+        offset
+            + self
+                .0
+                .iter()
+                .take_while(|(tab, _)| *tab < offset)
+                .map(|(_, extra)| extra)
+                .sum::<usize>()
+    }
+}
+
+/// Background of the whitespace shown by [`RowModifier::generate_line_with_format`], faint in both
+/// the dark and the light theme.
+const WHITESPACE_BACKGROUND: Color32 = Color32::from_rgba_premultiplied(64, 64, 64, 64);
+
+/// The byte ranges of `text` which are trailing whitespace or tabs, including the spaces the tabs
+/// were expanded to going by `tab_stops`.
+fn whitespace_ranges(text: &str, tab_stops: &TabStops) -> Vec<Range<usize>> {
+    let mut ranges: Vec<Range<usize>> = if tab_stops.0.is_empty() {
+        text.match_indices('\t').map(|(i, _)| i..i + 1).collect()
+    } else {
+        tab_stops
+            .0
+            .iter()
+            .map(|&(tab, extra)| {
+                let start = tab_stops.map(tab);
+                start..start + extra + 1
+            })
+            .collect()
+    };
+
+    let trimmed = text.trim_end_matches([' ', '\t']).len();
+
+    if trimmed < text.len() {
+        ranges.retain(|r| r.end <= trimmed);
+        ranges.push(trimmed..text.len());
+    }
+
+    ranges
+}
+
+/// Replaces tabs with spaces up to the next multiple of `width` characters, so tab separated
+/// columns line up. A `width` of 0 keeps the tabs.
+pub fn expand_tabs(text: &str, width: usize) -> (Cow<'_, str>, TabStops) {
+    if width == 0 || !text.contains('\t') {
+        return (Cow::Borrowed(text), TabStops::default());
+    }
+
+    let mut expanded = String::with_capacity(text.len() + width);
+    let mut stops = Vec::new();
+    let mut column = 0;
+
+    for (offset, c) in text.char_indices() {
+        if c == '\t' {
+            let spaces = width - column % width;
+            expanded.push_str(&" ".repeat(spaces));
+            stops.push((offset, spaces - 1));
+            column += spaces;
+        } else {
+            expanded.push(c);
+            column += 1;
+        }
+    }
+
+    (Cow::Owned(expanded), TabStops(stops))
+}
+
 pub fn send_err_to_error(e: std::sync::mpsc::SendError<LogFileMessage>) -> crate::Error {
     crate::Error::Other(e.into())
 }
@@ -83,6 +270,33 @@ pub struct Line {
     pub full: String,
     pub chunks: Option<Vec<TextChunk>>,
     pub default_format: TextFormat,
+    #[serde(default)]
+    pub truncated_length: Option<usize>,
+    #[serde(default)]
+    pub row_number: Option<u64>,
+    #[serde(default)]
+    pub byte_offset: Option<u64>,
+    #[serde(default)]
+    pub fold: Option<Fold>,
+}
+
+/// Folding state of a row starting a multi-line entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Fold {
+    pub expanded: bool,
+    /// Number of lines in the entry after the first one.
+    pub continuation_lines: usize,
+}
+
+/// How rows longer than the view are laid out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Wrap {
+    /// On a single row, scrolled to horizontally.
+    None,
+    /// Wrapped at the width of the view.
+    Width,
+    /// Wrapped after this many characters, see [`DisplayOptions::wrap_column`].
+    Column(usize),
 }
 
 impl Line {
@@ -91,15 +305,60 @@ impl Line {
             full: txt,
             chunks: None,
             default_format: format,
+            truncated_length: None,
+            row_number: None,
+            byte_offset: None,
+            fold: None,
         }
     }
 
-    pub fn ui(&mut self, ui: &mut egui::Ui) {
+    pub fn ui(&mut self, ui: &mut egui::Ui, wrap: Wrap) -> egui::Response {
         let mut layout_job = LayoutJob::default();
 
+        if let Some(row_number) = self.row_number {
+            layout_job.append(
+                &format!("{row_number} "),
+                0.0,
+                TextFormat {
+                    color: ui.visuals().weak_text_color(),
+                    ..self.default_format.clone()
+                },
+            );
+        }
+
+        if let Some(byte_offset) = self.byte_offset {
+            layout_job.append(
+                &format!("@{byte_offset} "),
+                0.0,
+                TextFormat {
+                    color: ui.visuals().weak_text_color(),
+                    ..self.default_format.clone()
+                },
+            );
+        }
+
+        if let Some(fold) = self.fold {
+            layout_job.append(
+                if fold.expanded { "▼ " } else { "▶ " },
+                0.0,
+                TextFormat {
+                    color: ui.visuals().weak_text_color(),
+                    ..self.default_format.clone()
+                },
+            );
+        }
+
+        // Character ranges of the boxed chunks within the laid out text.
+        let mut boxed = Vec::new();
+
         match self.chunks.as_ref() {
             Some(chunks) => {
                 for chunk in chunks {
+                    if chunk.boxed {
+                        let start = layout_job.text.chars().count();
+                        boxed.push(start..start + chunk.text.chars().count());
+                    }
+
                     layout_job.append(
                         &chunk.text,
                         0.0,
@@ -110,8 +369,97 @@ impl Line {
             None => layout_job.append(&self.full, 0.0, self.default_format.clone()),
         }
 
-        Label::new(layout_job).extend().ui(ui);
+        if self.truncated_length.is_some() {
+            layout_job.append(
+                " …",
+                0.0,
+                TextFormat {
+                    color: ui.visuals().weak_text_color(),
+                    ..self.default_format.clone()
+                },
+            );
+        }
+
+        if let Some(fold) = self.fold.filter(|f| !f.expanded) {
+            layout_job.append(
+                &format!("  (+{} lines)", fold.continuation_lines),
+                0.0,
+                TextFormat {
+                    color: ui.visuals().weak_text_color(),
+                    ..self.default_format.clone()
+                },
+            );
+        }
+
+        // Lines wrapped at a column are in a monospace font, where every character is as wide. Half
+        // a character to spare so rounding doesn't wrap before the column.
+        let column_width = match wrap {
+            Wrap::Column(column) => {
+                Some((column as f32 + 0.5) * ui.fonts(|f| f.glyph_width(&self.default_format.font_id, '0')))
+            }
+            _ => None,
+        };
+
+        if let Some(width) = column_width {
+            layout_job.wrap.max_width = width;
+        } else if !boxed.is_empty() && wrap == Wrap::Width {
+            layout_job.wrap.max_width = ui.available_width();
+        }
+
+        // Laid out here to find where the boxed chunks ended up, the label doesn't expose it, and
+        // to wrap at the column, the label would wrap at the width of the view.
+        let galley = (!boxed.is_empty() || column_width.is_some()).then(|| ui.fonts(|f| f.layout_job(layout_job.clone())));
+
+        let label = match (wrap, &galley) {
+            (Wrap::Column(_), Some(galley)) => Label::new(galley.clone()),
+            (Wrap::Width, _) => Label::new(layout_job).wrap(),
+            _ => Label::new(layout_job).extend(),
+        };
+        let response = label.ui(ui);
+
+        if let Some(galley) = galley {
+            let stroke = Stroke::new(1.0, ui.visuals().strong_text_color());
+
+            for rect in boxed_rects(&galley, &boxed) {
+                ui.painter().rect_stroke(rect.translate(response.rect.min.to_vec2()).expand(1.0), 2.0, stroke);
+            }
+        }
+
+        if let Some(length) = self.truncated_length {
+            response.on_hover_text(format!(
+                "Line truncated, full length is {}",
+                humanreadable_bytes(length as u64)
+            ))
+        } else {
+            response
+        }
+    }
+}
+
+/// The rects of the character ranges `boxed` within `galley`, one per row they're on.
+fn boxed_rects(galley: &egui::Galley, boxed: &[Range<usize>]) -> Vec<Rect> {
+    let mut rects = Vec::new();
+    let mut row_start = 0;
+
+    for row in &galley.rows {
+        let row_end = row_start + row.char_count_excluding_newline();
+
+        for range in boxed {
+            let start = range.start.max(row_start);
+            let end = range.end.min(row_end);
+
+            if start < end {
+                rects.push(Rect::from_x_y_ranges(
+                    row.x_offset(start - row_start)..=row.x_offset(end - row_start),
+                    row.rect.y_range(),
+                ));
+            }
+        }
+
+        row_start += row.char_count_including_newline();
     }
+
+    rects
 }
 
 impl From<String> for Line {
@@ -130,83 +478,360 @@ impl From<&str> for Line {
 pub struct TextChunk {
     pub text: String,
     pub format: Option<TextFormat>,
+    /// Framed by a box drawn around the text, see [`MatchStyle::Box`].
+    #[serde(default)]
+    pub boxed: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SearchMode {
+    /// Matches the text as is.
+    #[default]
+    Substring,
+    Regex,
+    /// Matches lines containing the characters of the text in order, with anything in between.
+    Fuzzy,
+}
+
+impl SearchMode {
+    pub fn label(&self) -> &'static str {
+        match self {
+            SearchMode::Substring => "Text",
+            SearchMode::Regex => "Regex",
+            SearchMode::Fuzzy => "Fuzzy",
+        }
+    }
+}
+
+fn fuzzy_matcher(case_insensitive: bool) -> &'static SkimMatcherV2 {
+    static IGNORE_CASE: OnceLock<SkimMatcherV2> = OnceLock::new();
+    static RESPECT_CASE: OnceLock<SkimMatcherV2> = OnceLock::new();
+
+    if case_insensitive {
+        IGNORE_CASE.get_or_init(|| SkimMatcherV2::default().ignore_case())
+    } else {
+        RESPECT_CASE.get_or_init(|| SkimMatcherV2::default().respect_case())
+    }
 }
 
+/// Set once a regex compiled in the background is done.
+type CompiledRegex = Arc<OnceLock<Result<Regex, regex::Error>>>;
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct Search {
     pub string: String,
-    pub is_regex: bool,
+    #[serde(default)]
+    pub mode: SearchMode,
     pub case_insensitive: bool,
+    /// `^` and `$` match at the start and end of each line of a multi-line entry, rather than only
+    /// at the start and end of the entry. Regex mode only.
+    #[serde(default)]
+    pub multi_line: bool,
+    /// `.` also matches `\n`, to match across the lines of a multi-line entry. Regex mode only.
+    #[serde(default)]
+    pub dot_matches_new_line: bool,
+    /// Compiled for the substring and regex modes.
     #[serde(skip)]
     pub regex: Option<Regex>,
+    /// The regex being compiled in the background after the search was edited, and since when.
+    #[serde(skip)]
+    compiling: Option<(Instant, CompiledRegex)>,
+    /// Why the search couldn't be compiled.
+    #[serde(skip)]
+    error: Option<String>,
     #[serde(skip)]
     changed: bool,
+    /// Superseded by `mode`, only read from previously saved state.
+    #[serde(default, rename = "is_regex", skip_serializing)]
+    legacy_is_regex: bool,
 }
 
 impl Search {
+    pub fn new(string: impl Into<String>, mode: SearchMode, case_insensitive: bool) -> Self {
+        let mut search = Self {
+            string: string.into(),
+            mode,
+            case_insensitive,
+            ..Default::default()
+        };
+        search.rebuild_regex();
+        search
+    }
+
     pub fn is_empty(&self) -> bool {
         self.string.is_empty()
     }
 
+    /// Whether there's anything to match with, e.g. not an empty or invalid search.
+    pub fn is_ready(&self) -> bool {
+        match self.mode {
+            SearchMode::Fuzzy => !self.is_empty(),
+            SearchMode::Substring | SearchMode::Regex => self.regex.is_some(),
+        }
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        match self.mode {
+            SearchMode::Fuzzy => fuzzy_matcher(self.case_insensitive)
+                .fuzzy_match(text, &self.string)
+                .is_some(),
+            SearchMode::Substring | SearchMode::Regex => {
+                self.regex.as_ref().is_some_and(|r| r.is_match(text))
+            }
+        }
+    }
+
+    /// The byte ranges of `text` matched by the search, the matched characters in fuzzy mode.
+    pub fn find_ranges(&self, text: &str) -> Vec<Range<usize>> {
+        match self.mode {
+            SearchMode::Fuzzy => {
+                let Some((_score, indices)) =
+                    fuzzy_matcher(self.case_insensitive).fuzzy_indices(text, &self.string)
+                else {
+                    return Vec::new();
+                };
+
+                // The indices are of chars, merge adjacent ones into byte ranges.
+                let mut ranges: Vec<Range<usize>> = Vec::new();
+                let mut indices = indices.into_iter().peekable();
+
+                for (char_index, (offset, c)) in text.char_indices().enumerate() {
+                    if indices.peek() != Some(&char_index) {
+                        continue;
+                    }
+
+                    indices.next();
+                    let range = offset..offset + c.len_utf8();
+
+                    match ranges.last_mut() {
+                        Some(last) if last.end == range.start => last.end = range.end,
+                        _ => ranges.push(range),
+                    }
+                }
+
+                ranges
+            }
+            SearchMode::Substring | SearchMode::Regex => self
+                .regex
+                .as_ref()
+                .map(|r| r.find_iter(text).map(|m| m.range()).collect())
+                .unwrap_or_default(),
+        }
+    }
+
     // TODO: I'm not very fond of this way of doing it. See if we can find a rustier way to do it.
     fn create_regex(&self) -> Result<Regex, regex::Error> {
-        let regex_pattern = if self.is_regex {
+        let regex_pattern = if self.mode == SearchMode::Regex {
             &self.string
         } else {
             &regex::escape(&self.string)
         };
 
-        RegexBuilder::new(&regex_pattern)
+        let is_regex = self.mode == SearchMode::Regex;
+
+        RegexBuilder::new(regex_pattern)
             .unicode(true)
             .case_insensitive(self.case_insensitive)
+            .multi_line(is_regex && self.multi_line)
+            .dot_matches_new_line(is_regex && self.dot_matches_new_line)
             .build()
     }
 
     pub fn ui(&mut self, ui: &mut egui::Ui, additional_content: impl FnOnce(&mut egui::Ui)) {
+        self.ui_labeled(ui, "Search text", additional_content);
+    }
+
+    /// Like [`Self::ui`], with `label` in front of the search text.
+    pub fn ui_labeled(
+        &mut self,
+        ui: &mut egui::Ui,
+        label: &str,
+        additional_content: impl FnOnce(&mut egui::Ui),
+    ) {
         self.changed = false;
 
         let mut data_changed = false;
 
         ui.vertical(|ui| {
             ui.horizontal(|ui| {
-                ui.label("Search text");
+                ui.label(label);
 
                 let txt_changed = ui.text_edit_singleline(&mut self.string).changed();
                 data_changed = data_changed || txt_changed;
             });
 
             ui.horizontal(|ui| {
-                let regex_checkbox_changed = ui.checkbox(&mut self.is_regex, "Regex?").changed();
+                let mode = self.mode;
+
+                for mode in [SearchMode::Substring, SearchMode::Regex, SearchMode::Fuzzy] {
+                    ui.selectable_value(&mut self.mode, mode, mode.label());
+                }
 
                 let case_checkbox_changed = ui
                     .checkbox(&mut self.case_insensitive, "Case Insensitive?")
                     .changed();
 
-                data_changed = data_changed || regex_checkbox_changed || case_checkbox_changed;
+                let mut flags_changed = false;
+
+                if self.mode == SearchMode::Regex {
+                    flags_changed |= ui
+                        .checkbox(&mut self.multi_line, "Multi-line")
+                        .on_hover_text(
+                            "^ and $ match at the start and end of each line of a multi-line \
+                            entry, (?m).\nLines are matched one at a time, so this only makes a \
+                            difference when grouping lines into entries.",
+                        )
+                        .changed();
+                    flags_changed |= ui
+                        .checkbox(&mut self.dot_matches_new_line, ". matches newline")
+                        .on_hover_text(
+                            ". also matches line breaks, to match across the lines of a \
+                            multi-line entry, (?s).",
+                        )
+                        .changed();
+                }
+
+                data_changed =
+                    data_changed || mode != self.mode || case_checkbox_changed || flags_changed;
 
                 additional_content(ui);
             });
         });
 
-        self.changed = (!self.string.is_empty() && self.regex.is_none()) || data_changed;
+        self.apply_changes(ui, data_changed);
+    }
+
+    /// Only the search text, for when there's little room. The other options keep their values.
+    pub fn ui_compact(&mut self, ui: &mut egui::Ui) {
+        self.changed = false;
+
+        let data_changed = TextEdit::singleline(&mut self.string)
+            .hint_text("Search text")
+            .ui(ui)
+            .changed();
+
+        self.apply_changes(ui, data_changed);
+    }
+
+    /// Compiles the search again if `data_changed` or not yet compiled, and shows the progress of
+    /// the compilation or its error.
+    fn apply_changes(&mut self, ui: &mut egui::Ui, data_changed: bool) {
+        let needs_regex = !self.string.is_empty() && self.mode != SearchMode::Fuzzy;
+        let missing_regex = needs_regex
+            && self.regex.is_none()
+            && self.error.is_none()
+            && self.compiling.is_none();
 
         // TODO: Ugly to have in UI function, can we move this to a better place?
-        if self.changed {
-            match self.create_regex() {
-                Ok(r) => {
-                    self.regex = Some(r);
+        if data_changed || missing_regex {
+            self.regex = None;
+            self.error = None;
+            self.compiling = None;
+
+            if needs_regex {
+                self.compile_in_background(ui.ctx().clone());
+            } else {
+                self.changed = true;
+            }
+        }
+
+        if let Some((started, result)) = self.compiling.clone() {
+            match result.get() {
+                Some(Ok(regex)) => {
+                    self.regex = Some(regex.clone());
+                    self.compiling = None;
+                    self.changed = true;
                 }
-                Err(e) => {
-                    self.regex = None;
-                    ui.colored_label(Color32::RED, format!("Invalid regex supplied: {e:?}"));
+                Some(Err(e)) => {
+                    self.error = Some(format!("Invalid regex supplied: {e:?}"));
+                    self.compiling = None;
+                    self.changed = true;
                 }
+                None => match COMPILING_INDICATOR_DELAY.checked_sub(started.elapsed()) {
+                    Some(remaining) => ui.ctx().request_repaint_after(remaining),
+                    None => {
+                        ui.horizontal(|ui| {
+                            ui.spinner();
+                            ui.label("Compiling…");
+                        });
+                    }
+                },
             }
         }
+
+        if let Some(error) = &self.error {
+            ui.colored_label(Color32::RED, error);
+        }
+    }
+
+    /// Compiles the regex on another thread, complex patterns can take a while. The result is
+    /// picked up by [`Self::ui_labeled`] once `ctx` is repainted.
+    fn compile_in_background(&mut self, ctx: egui::Context) {
+        let result = Arc::new(OnceLock::new());
+        self.compiling = Some((Instant::now(), result.clone()));
+
+        let search = Self {
+            string: self.string.clone(),
+            mode: self.mode,
+            case_insensitive: self.case_insensitive,
+            multi_line: self.multi_line,
+            dot_matches_new_line: self.dot_matches_new_line,
+            ..Default::default()
+        };
+
+        rayon::spawn(move || {
+            let _ = result.set(search.create_regex());
+            ctx.request_repaint();
+        });
     }
 
     pub fn changed(&self) -> bool {
         self.changed
     }
+
+    /// The compiled regex isn't persisted, rebuilds it from the serialized search fields.
+    pub fn rebuild_regex(&mut self) {
+        if std::mem::take(&mut self.legacy_is_regex) {
+            self.mode = SearchMode::Regex;
+        }
+
+        self.compiling = None;
+        self.error = None;
+        self.regex = if self.is_empty() || self.mode == SearchMode::Fuzzy {
+            None
+        } else {
+            self.create_regex().ok()
+        };
+    }
+}
+
+/// The characters `start..end` of a line, e.g. a field of a fixed-width log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ColumnRange {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Default for ColumnRange {
+    fn default() -> Self {
+        Self { start: 0, end: 80 }
+    }
+}
+
+impl ColumnRange {
+    /// The byte range of `line` covered by the columns, None if the line is shorter than that.
+    pub fn byte_range(&self, line: &str) -> Option<Range<usize>> {
+        let line = line.trim_end_matches(['\n', '\r']);
+        let mut boundaries = line
+            .char_indices()
+            .map(|(offset, _)| offset)
+            .chain(std::iter::once(line.len()));
+
+        let start = boundaries.nth(self.start)?;
+        let end = boundaries.nth(self.end.saturating_sub(self.start + 1))?;
+
+        (self.start < self.end).then_some(start..end)
+    }
 }
 
 // TODO: Change color of the matching text?
@@ -214,40 +839,203 @@ impl Search {
 pub struct Filter {
     pub search: Search,
     pub filter: bool,
+    /// Only match against these columns of each line.
+    #[serde(default)]
+    pub columns: Option<ColumnRange>,
+    /// Lines matched by this aren't matched by the filter, even if matched by `search`. Does
+    /// nothing while empty.
+    #[serde(default)]
+    pub exclude: Search,
     #[serde(skip)]
     changed: bool,
+    /// Set by the navigation buttons, handled by [`LogFile`].
+    #[serde(skip)]
+    pub(crate) jump: Option<Jump>,
+    /// The number of the match last navigated to, if still matched, and the number of matches.
+    #[serde(skip)]
+    pub(crate) position: Option<(Option<usize>, usize)>,
 }
 
 impl Filter {
-    pub fn ui(&mut self, ui: &mut egui::Ui) {
+    pub fn new(search: Search) -> Self {
+        Self {
+            search,
+            filter: true,
+            columns: None,
+            exclude: Search::default(),
+            changed: false,
+            jump: None,
+            position: None,
+        }
+    }
+
+    /// Shows buttons navigating between the matching lines if `navigable`, as refinements only
+    /// narrow down the rows matched by the filter.
+    pub fn ui(&mut self, ui: &mut egui::Ui, navigable: bool) {
         let mut checkbox_changed = false;
+        let mut columns_changed = false;
         self.search.ui(ui, |ui| {
             // TODO: Better label?
-            checkbox_changed = ui.checkbox(&mut self.filter, "Filter?").changed();
+            checkbox_changed = ui
+                .checkbox(&mut self.filter, "Filter?")
+                .on_hover_text("Ctrl+Shift+F toggles this for the active file")
+                .changed();
+
+            let mut restrict_columns = self.columns.is_some();
+
+            if ui
+                .checkbox(&mut restrict_columns, "Columns")
+                .on_hover_text("Only match against these characters of each line, lines shorter than that don't match.")
+                .changed()
+            {
+                self.columns = restrict_columns.then(ColumnRange::default);
+                columns_changed = true;
+            }
+
+            if let Some(columns) = self.columns.as_mut() {
+                columns_changed |= ui
+                    .add(DragValue::new(&mut columns.start).range(0..=columns.end))
+                    .changed();
+                ui.label("to");
+                columns_changed |= ui
+                    .add(DragValue::new(&mut columns.end).range(columns.start..=usize::MAX))
+                    .changed();
+            }
         });
 
-        // TODO: Buttons to scroll up/down to search results?
+        self.exclude.ui_labeled(ui, "Exclude", |_| {});
+
+        if navigable {
+            ui.add_enabled_ui(self.search.is_ready(), |ui| {
+                if ui
+                    .button("⏶")
+                    .on_hover_text("Previous line matching the filter")
+                    .clicked()
+                {
+                    self.jump = Some(Jump::Previous);
+                }
+
+                ui.label(match self.position {
+                    Some((Some(current), total)) => format!("{current} / {total}"),
+                    Some((None, total)) => format!("– / {total}"),
+                    None => String::from("– / –"),
+                });
+
+                if ui
+                    .button("⏷")
+                    .on_hover_text("Next line matching the filter")
+                    .clicked()
+                {
+                    self.jump = Some(Jump::Next);
+                }
+            });
+        }
+
+        self.changed =
+            checkbox_changed || columns_changed || self.search.changed() || self.exclude.changed();
+    }
+
+    /// Switches between filtering and only searching, keeping the search.
+    pub fn toggle(&mut self) {
+        self.filter = !self.filter;
+        self.changed = true;
+    }
+
+    /// Only the search text, see [`RowModifier::collapsed`].
+    pub fn compact_ui(&mut self, ui: &mut egui::Ui) {
+        self.search.ui_compact(ui);
+        self.changed = self.search.changed();
+    }
+
+    /// Filters by `search` from now on, e.g. for a preset.
+    pub fn set_search(&mut self, search: Search) {
+        self.search = search;
+        self.filter = true;
+        self.changed = true;
+    }
+
+    /// The part of `line` to match against.
+    fn haystack<'a>(&self, line: &'a str) -> Option<&'a str> {
+        match self.columns {
+            Some(columns) => columns.byte_range(line).map(|r| &line[r]),
+            None => Some(line),
+        }
+    }
+
+    fn is_excluded(&self, haystack: &str) -> bool {
+        self.exclude.is_ready() && self.exclude.is_match(haystack)
+    }
+
+    pub fn is_match(&self, line: &str) -> bool {
+        self.haystack(line)
+            .is_some_and(|h| self.search.is_match(h) && !self.is_excluded(h))
+    }
+
+    /// The byte ranges of `line` matched by the search, within the columns if restricted. Excluded
+    /// lines have none.
+    pub fn find_ranges(&self, line: &str) -> Vec<Range<usize>> {
+        let Some(haystack) = self.haystack(line).filter(|h| !self.is_excluded(h)) else {
+            return Vec::new();
+        };
+
+        let offset = haystack.as_ptr() as usize - line.as_ptr() as usize;
+
+        self.search
+            .find_ranges(haystack)
+            .into_iter()
+            .map(|r| r.start + offset..r.end + offset)
+            .collect()
+    }
 
-        self.changed = checkbox_changed || self.search.changed();
+    /// The text of each match in `line`, or of capture group `group` if non-zero. Only regex
+    /// searches have groups beyond the whole match.
+    pub fn matched_texts<'a>(&self, line: &'a str, group: usize) -> Vec<&'a str> {
+        let Some(haystack) = self
+            .haystack(line.trim_end_matches(['\n', '\r']))
+            .filter(|h| !self.is_excluded(h))
+        else {
+            return Vec::new();
+        };
+
+        match self.search.regex.as_ref() {
+            Some(regex) if self.search.mode == SearchMode::Regex => regex
+                .captures_iter(haystack)
+                .filter_map(|c| c.get(group))
+                .map(|m| m.as_str())
+                .collect(),
+            _ if group == 0 => self
+                .search
+                .find_ranges(haystack)
+                .into_iter()
+                .map(|r| &haystack[r])
+                .collect(),
+            _ => Vec::new(),
+        }
     }
 
     /// Will return None if there is nothing to filter on
-    pub fn filter<'a>(&self, it: &'a Vec<String>) -> Option<Vec<String>> {
-        if let Some(r) = self.search.regex.as_ref() {
-            Some(
-                it.par_iter()
-                    .filter(|l| r.is_match(l))
-                    .map(String::to_owned)
-                    .collect::<Vec<String>>(),
-            )
-        } else {
-            None
+    pub fn filter(&self, it: &[String]) -> Option<Vec<String>> {
+        if !self.search.is_ready() {
+            return None;
         }
+
+        Some(
+            it.par_iter()
+                .filter(|l| self.is_match(l))
+                .map(String::to_owned)
+                .collect::<Vec<String>>(),
+        )
     }
 
     pub fn changed(&self) -> bool {
         self.changed
     }
+
+    /// The compiled regexes aren't persisted, rebuilds them from the serialized search fields.
+    pub fn rebuild_regexes(&mut self) {
+        self.search.rebuild_regex();
+        self.exclude.rebuild_regex();
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -255,13 +1043,116 @@ pub struct RowHighlight {
     pub search: Search,
     pub bg_color: Color32,
     pub fg_color: Color32,
+    /// Left out when highlighting, e.g. to save the time it takes to match.
+    #[serde(default)]
+    pub disabled: bool,
     #[serde(skip)]
     pub(crate) should_delete: bool,
-}
-
+    /// Set by the navigation buttons, handled by [`LogFile`].
+    #[serde(skip)]
+    pub(crate) jump: Option<Jump>,
+    #[serde(skip)]
+    changed: bool,
+}
+
+/// Direction to navigate in between the lines matched by a highlight or a quick search.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Jump {
+    Previous,
+    Next,
+}
+
+impl Jump {
+    fn reverse(self) -> Self {
+        match self {
+            Jump::Previous => Jump::Next,
+            Jump::Next => Jump::Previous,
+        }
+    }
+}
+
+/// What the lines navigated in between are matched by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Matcher {
+    /// The highlight at the index.
+    Highlight(usize),
+    QuickSearch,
+    /// The lines matched by the filter, or which would be if filtering.
+    Filter,
+}
+
+/// A search typed after pressing `/`, or `?` to search backwards, as in less.
+#[derive(Debug, Clone)]
+struct QuickSearch {
+    search: Search,
+    /// Direction `n` navigates in, `N` navigates in the other one.
+    direction: Jump,
+}
+
+/// A window of a file read around a position rather than all of it, see [`LogFile::scrub_ui`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Scrub {
+    /// Position within the file, from 0 to 1.
+    fraction: f32,
+    /// Byte offset the window starts at, read from the first line starting at or after it.
+    offset: u64,
+}
+
+/// An entry of the encoding switcher or the encoding menu, see [`LogFile::encoding_switcher_ui`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EncodingChoice {
+    Redetect,
+    Encoding(&'static Encoding),
+}
+
+/// The input of a quick search while it's being typed.
+#[derive(Debug, Clone)]
+struct QuickSearchInput {
+    text: String,
+    direction: Jump,
+    /// Line number the search starts from, matches are searched for from here as the text changes.
+    origin: u64,
+    /// Whether the last search found anything.
+    found: bool,
+    focused: bool,
+}
+
 impl RowHighlight {
+    /// A new highlight in colors readable with `visuals`, the default ones are meant for the dark
+    /// theme.
+    pub fn for_visuals(visuals: &egui::Visuals) -> Self {
+        if visuals.dark_mode {
+            Self::default()
+        } else {
+            Self {
+                bg_color: Color32::from_rgb(0xc8, 0xf0, 0xc8),
+                fg_color: Color32::from_rgb(0x00, 0x5a, 0x00),
+                ..Self::default()
+            }
+        }
+    }
+
+    pub fn new(search: Search, bg_color: Color32, fg_color: Color32) -> Self {
+        Self {
+            search,
+            bg_color,
+            fg_color,
+            disabled: false,
+            should_delete: false,
+            jump: None,
+            changed: false,
+        }
+    }
+
     pub fn ui(&mut self, ui: &mut egui::Ui) {
         ui.horizontal(|ui| {
+            let mut enabled = !self.disabled;
+            self.changed = ui
+                .checkbox(&mut enabled, "")
+                .on_hover_text("Enable row highlight")
+                .changed();
+            self.disabled = !enabled;
+
             self.search.ui(ui, |ui| {
                 ui.label("Bg color");
                 ui.color_edit_button_srgba(&mut self.bg_color);
@@ -270,6 +1161,24 @@ impl RowHighlight {
                 ui.color_edit_button_srgba(&mut self.fg_color);
             });
 
+            ui.add_enabled_ui(self.search.is_ready(), |ui| {
+                if ui
+                    .button("⏶")
+                    .on_hover_text("Previous line matching this highlight")
+                    .clicked()
+                {
+                    self.jump = Some(Jump::Previous);
+                }
+
+                if ui
+                    .button("⏷")
+                    .on_hover_text("Next line matching this highlight")
+                    .clicked()
+                {
+                    self.jump = Some(Jump::Next);
+                }
+            });
+
             self.should_delete = ui
                 .button("X")
                 .on_hover_ui(|ui| {
@@ -286,7 +1195,83 @@ impl Default for RowHighlight {
             bg_color: Color32::DARK_GREEN,
             fg_color: Color32::LIGHT_GREEN,
             search: Search::default(),
+            disabled: false,
+            should_delete: false,
+            jump: None,
+            changed: false,
+        }
+    }
+}
+
+/// Display only rewrite of the rows matching the search, the loaded lines are left untouched.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RewriteRule {
+    pub search: Search,
+    /// Replacement for each match, may refer to capture groups (e.g. `$1`) when using a regex.
+    pub replacement: String,
+    pub enabled: bool,
+    #[serde(skip)]
+    pub(crate) should_delete: bool,
+    #[serde(skip)]
+    pub(crate) should_move_up: bool,
+    #[serde(skip)]
+    changed: bool,
+}
+
+impl RewriteRule {
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        let mut changed = false;
+
+        ui.horizontal(|ui| {
+            changed |= ui
+                .checkbox(&mut self.enabled, "")
+                .on_hover_text("Enable rewrite rule")
+                .changed();
+
+            self.search.ui(ui, |ui| {
+                ui.label("Replace with");
+                changed |= ui.text_edit_singleline(&mut self.replacement).changed();
+            });
+
+            self.should_move_up = ui
+                .button("⬆")
+                .on_hover_text("Apply this rule before the previous one")
+                .clicked();
+
+            self.should_delete = ui
+                .button("X")
+                .on_hover_ui(|ui| {
+                    ui.label("Remove rewrite rule");
+                })
+                .clicked();
+        });
+
+        self.changed = changed || self.search.changed() || self.should_move_up || self.should_delete;
+    }
+
+    pub fn rewrite<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        match self.search.regex.as_ref() {
+            Some(re) if self.enabled && !self.search.is_empty() => {
+                if self.search.mode == SearchMode::Regex {
+                    re.replace_all(text, self.replacement.as_str())
+                } else {
+                    re.replace_all(text, regex::NoExpand(&self.replacement))
+                }
+            }
+            _ => Cow::Borrowed(text),
+        }
+    }
+}
+
+impl Default for RewriteRule {
+    fn default() -> Self {
+        Self {
+            search: Search::default(),
+            replacement: String::new(),
+            enabled: true,
             should_delete: false,
+            should_move_up: false,
+            changed: false,
         }
     }
 }
@@ -295,10 +1280,187 @@ impl Default for RowHighlight {
 pub struct RowModifier {
     pub filter: Filter,
     pub row_highlights: Vec<RowHighlight>,
+    #[serde(default)]
+    pub rewrite_rules: Vec<RewriteRule>,
+    /// Whether the filter matches against the rewritten rows rather than the original lines.
+    #[serde(default)]
+    pub filter_rewritten: bool,
+    #[serde(skip)]
+    rewrites_changed: bool,
+    /// Filters applied one after another on the results of the filter, narrowing them down.
+    #[serde(default)]
+    pub refinements: Vec<Filter>,
+    /// Number of rows remaining after the filter and after each active refinement.
+    #[serde(skip)]
+    pub stage_counts: Vec<usize>,
+    #[serde(skip)]
+    refinements_changed: bool,
+    /// Only highlight lines matched by the filter, e.g. while searching without filtering, rather
+    /// than all lines.
+    #[serde(default)]
+    pub highlight_filter_matches_only: bool,
+    /// Only the first this many enabled highlights are matched, 0 matches all of them.
+    #[serde(default)]
+    pub max_highlights: usize,
+    /// Average time each highlight took to match a line of the last sample, see
+    /// [`Self::measure_highlights`].
+    #[serde(skip)]
+    highlight_costs: Vec<Option<Duration>>,
+    /// Number of lines the costs were measured over.
+    #[serde(skip)]
+    highlight_sample_size: usize,
+    /// When to measure the costs again, once the highlights haven't been edited for a moment.
+    #[serde(skip)]
+    highlight_costs_due: Option<Instant>,
+    /// Number of rows in view when the costs were measured, which are matched every frame.
+    #[serde(skip)]
+    rows_in_view: usize,
+    /// Whether a highlight was changed or removed, this frame.
+    #[serde(skip)]
+    highlights_changed: bool,
+    /// The highlight whose navigation buttons were clicked this frame, and which one.
+    #[serde(skip)]
+    highlight_jump: Option<(usize, Jump)>,
+    /// Only the filter's search text is shown, leaving more room for the rows.
+    #[serde(default)]
+    pub collapsed: bool,
+    /// How filter matches are emphasized, from the settings.
+    #[serde(skip)]
+    pub match_style: MatchStyle,
+}
+
+/// Number of items filtered between checking whether the filtering has been cancelled.
+const FILTER_BLOCK_SIZE: usize = 64 * 1024;
+/// New lines beyond this many are filtered in the background rather than as they're received.
+const MAX_INCREMENTAL_FILTER_LINES: usize = 100_000;
+
+/// Lets filtering running in the background report its progress and be cancelled.
+#[derive(Debug, Default)]
+pub struct FilterControl {
+    cancelled: AtomicBool,
+    /// Number of items the filter has gone through.
+    progress: AtomicUsize,
+}
+
+impl FilterControl {
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    pub fn progress(&self) -> usize {
+        self.progress.load(Ordering::Relaxed)
+    }
+}
+
+/// The result of filtering all lines in the background.
+struct FilterOutcome {
+    /// The rows to show, or the entries matching the filter if grouping entries. None if the
+    /// filter isn't active.
+    rows: Option<Vec<usize>>,
+    grouped: bool,
+    stage_counts: Vec<usize>,
+    max_severity: Option<Severity>,
+}
+
+/// The lines before the last reload compared to the lines since, see [`LogFile::start_diffing`].
+struct Diff {
+    /// How many lines there were when diffed.
+    lines: usize,
+    changes: Vec<Change>,
+    added: usize,
+    removed: usize,
+}
+
+/// Filtering of all lines running in the background, see [`LogFile::start_filtering`].
+struct FilterJob {
+    control: Arc<FilterControl>,
+    /// Number of lines or entries being filtered.
+    total: usize,
+    receiver: Receiver<FilterOutcome>,
+}
+
+impl FilterJob {
+    fn percent(&self) -> usize {
+        (self.control.progress() * 100).checked_div(self.total).unwrap_or(0)
+    }
 }
 
+impl Drop for FilterJob {
+    /// The result of a replaced job isn't needed anymore.
+    fn drop(&mut self) {
+        self.control.cancel();
+    }
+}
+
+/// Color of the lines added since the last reload, see [`LogFile::diff_ui`].
+const DIFF_ADDED_COLOR: Color32 = Color32::from_rgb(0x2e, 0xa0, 0x43);
+/// Color of the lines removed since the last reload.
+const DIFF_REMOVED_COLOR: Color32 = Color32::from_rgb(0xd0, 0x3a, 0x3a);
+
+/// Pinned lines beyond this many are scrolled through rather than taking up more of the view.
+const MAX_PINNED_ROWS: usize = 5;
+
+/// Number of lines the time each highlight takes to match is measured over.
+const HIGHLIGHT_SAMPLE_LINES: usize = 200;
+/// Time matching the highlights against the rows in view may take each frame before warning about
+/// it.
+const HIGHLIGHT_FRAME_BUDGET: Duration = Duration::from_millis(4);
+/// How long the highlights must go unedited before their costs are measured again, rather than
+/// measuring them on every keystroke.
+const HIGHLIGHT_MEASURE_DELAY: Duration = Duration::from_millis(300);
+
+/// Filters for the levels commonly of interest, matching the levels the way the format extracts
+/// them, see [`level_pattern`].
+const SEVERITY_PRESETS: [(&str, &str, &[Severity]); 3] = [
+    ("Errors", "Only lines at error level", &[Severity::Error]),
+    ("Warnings+", "Only lines at warning level or above", &[Severity::Warn, Severity::Error]),
+    (
+        "Exclude debug",
+        "Only lines at info level or above",
+        &[Severity::Info, Severity::Warn, Severity::Error],
+    ),
+];
+
+/// Start of the config links of [`RowModifier::to_config_link`], to tell them apart from other
+/// text.
+const CONFIG_LINK_PREFIX: &str = "logglance-config:";
+
 impl RowModifier {
-    pub fn ui(&mut self, ui: &mut egui::Ui) {
+    /// The filter, highlights and rewrite rules as a single URL-safe string, e.g. to share them in
+    /// a chat. Applied with [`Self::from_config_link`].
+    pub fn to_config_link(&self) -> Result<String, Error> {
+        let json = serde_json::to_vec(self).map_err(|e| Error::Other(e.into()))?;
+        Ok(format!("{CONFIG_LINK_PREFIX}{}", URL_SAFE_NO_PAD.encode(json)))
+    }
+
+    /// The row modifier of a config link made by [`Self::to_config_link`], with its regexes
+    /// rebuilt.
+    pub fn from_config_link(link: &str) -> Result<Self, String> {
+        let encoded = link
+            .trim()
+            .strip_prefix(CONFIG_LINK_PREFIX)
+            .ok_or_else(|| format!("Not a config link, those start with {CONFIG_LINK_PREFIX}"))?;
+        let json = URL_SAFE_NO_PAD
+            .decode(encoded)
+            .map_err(|e| format!("The config link is damaged, it might have been cut off: {e}"))?;
+        let mut row_modifier: Self = serde_json::from_slice(&json)
+            .map_err(|e| format!("The config link isn't compatible with this version: {e}"))?;
+
+        row_modifier.rebuild_regexes();
+        Ok(row_modifier)
+    }
+
+    /// `format_level` is the level pattern of the file's format, for the severity presets.
+    pub fn ui(&mut self, ui: &mut egui::Ui, format_level: &str) {
+        if self.collapsed {
+            self.compact_ui(ui);
+            return;
+        }
+
         ScrollArea::horizontal()
             .auto_shrink([false, false])
             .show(ui, |ui| {
@@ -307,13 +1469,38 @@ impl RowModifier {
                     .size(Size::relative(0.59))
                     .horizontal(|mut strip| {
                         strip.cell(|ui| {
-                            ui.vertical(|ui| {
-                                ui.label("Filter/Search rows");
+                            ScrollArea::vertical()
+                                .id_source("filter_scroll")
+                                .auto_shrink([false, true])
+                                .show(ui, |ui| {
+                                    ui.vertical(|ui| {
+                                        ui.horizontal(|ui| {
+                                            if ui.small_button("⏶").on_hover_text("Collapse into a single line").clicked() {
+                                                self.collapsed = true;
+                                            }
+
+                                            ui.label("Filter/Search rows");
+                                        });
+
+                                        ui.horizontal(|ui| {
+                                            self.filter.ui(ui, true);
+                                        });
+
+                                        ui.horizontal(|ui| {
+                                            for (label, description, severities) in SEVERITY_PRESETS {
+                                                if ui.small_button(label).on_hover_text(description).clicked() {
+                                                    self.filter.set_search(Search::new(
+                                                        level_pattern(severities, format_level),
+                                                        SearchMode::Regex,
+                                                        false,
+                                                    ));
+                                                }
+                                            }
+                                        });
 
-                                ui.horizontal(|ui| {
-                                    self.filter.ui(ui);
+                                        self.refinements_ui(ui);
+                                    });
                                 });
-                            });
                         });
 
                         strip.cell(|ui| {
@@ -330,7 +1517,7 @@ impl RowModifier {
                                             })
                                             .clicked()
                                         {
-                                            self.row_highlights.push(RowHighlight::default());
+                                            self.row_highlights.push(RowHighlight::for_visuals(ui.visuals()));
                                         }
 
                                         ui.add_space(4.0);
@@ -339,6 +1526,7 @@ impl RowModifier {
                                             ui.spacing_mut().item_spacing = Vec2::new(8.0, 8.0);
 
                                             let mut highlights_to_remove: Vec<usize> = Vec::new();
+                                            self.highlights_changed = false;
 
                                             for (index, row_highlight) in
                                                 self.row_highlights.iter_mut().enumerate()
@@ -348,14 +1536,45 @@ impl RowModifier {
                                                 if row_highlight.should_delete {
                                                     highlights_to_remove.push(index);
                                                 }
+
+                                                if let Some(jump) = row_highlight.jump.take() {
+                                                    self.highlight_jump = Some((index, jump));
+                                                }
+
+                                                self.highlights_changed |= row_highlight.search.changed() || row_highlight.changed;
+
+                                                if let Some(cost) = self.highlight_costs.get(index).copied().flatten() {
+                                                    ui.weak(format!("About {cost:.1?} per line"));
+                                                }
                                             }
 
+                                            self.highlights_changed |= !highlights_to_remove.is_empty();
+
                                             for index in highlights_to_remove {
                                                 self.row_highlights.remove(index);
                                             }
                                         });
                                     });
 
+                                    self.highlights_changed |= ui
+                                        .checkbox(&mut self.highlight_filter_matches_only, "Only highlight filter matches")
+                                        .on_hover_text("Leave lines not matched by the filter unhighlighted, e.g. while searching without filtering")
+                                        .changed();
+
+                                    ui.horizontal(|ui| {
+                                        ui.label("Match at most");
+                                        self.highlights_changed |= ui
+                                            .add(DragValue::new(&mut self.max_highlights).range(0..=usize::MAX))
+                                            .on_hover_text("Only the first this many enabled highlights are matched, 0 matches all of them")
+                                            .changed();
+                                        ui.label("highlights");
+                                    });
+
+                                    self.highlight_cost_ui(ui);
+
+                                    ui.separator();
+                                    self.rewrite_rules_ui(ui);
+
                                     ui.add_space(SPACING_FOR_SCROLLBAR);
                                 });
                         });
@@ -363,398 +1582,4252 @@ impl RowModifier {
             });
     }
 
-    pub fn generate_line(&self, text: &str) -> Line {
-        let mut l: Line = text.into();
+    /// A single line with the filter's search text, the rest being hidden until expanded again.
+    fn compact_ui(&mut self, ui: &mut egui::Ui) {
+        self.highlights_changed = false;
+        self.rewrites_changed = false;
+        self.refinements_changed = false;
 
-        for row_highlight in &self.row_highlights {
-            if row_highlight.search.is_empty() {
-                continue;
+        ui.horizontal(|ui| {
+            ui.label("Filter/Search rows");
+            self.filter.compact_ui(ui);
+
+            if ui.button("…").on_hover_text("Show all filter and highlight options").clicked() {
+                self.collapsed = false;
             }
+        });
+    }
 
-            if let Some(re) = row_highlight.search.regex.as_ref() {
-                if re.is_match(text) {
-                    let mut format = TextFormat::default();
-                    format.background = row_highlight.bg_color.clone();
-                    format.color = row_highlight.fg_color.clone();
+    fn refinements_ui(&mut self, ui: &mut egui::Ui) {
+        let mut changed = false;
+        let mut refinement_to_remove = None;
 
-                    l.default_format = format;
-                    break;
+        for (index, refinement) in self.refinements.iter_mut().enumerate() {
+            ui.separator();
+
+            ui.horizontal(|ui| {
+                ui.label("Within results");
+                refinement.ui(ui, false);
+
+                if ui
+                    .button("X")
+                    .on_hover_ui(|ui| {
+                        ui.label("Remove search within results");
+                    })
+                    .clicked()
+                {
+                    refinement_to_remove = Some(index);
                 }
-            }
+            });
+
+            changed |= refinement.changed();
         }
 
-        if let Some(re) = self.filter.search.regex.as_ref() {
-            let mut chunks: Vec<TextChunk> = Vec::new();
+        if let Some(index) = refinement_to_remove {
+            self.refinements.remove(index);
+            changed = true;
+        }
 
-            let mut last_end = 0;
+        let filter_active = self.filter.filter && !self.filter.search.is_empty();
 
-            for m in re.find_iter(&text) {
-                if m.start() > 0 {
-                    chunks.push(TextChunk {
-                        text: text[last_end..m.start()].to_string(),
-                        format: None,
-                    });
-                }
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(filter_active, egui::Button::new("Search within results"))
+                .on_hover_text("Narrow down the filtered rows further")
+                .clicked()
+            {
+                self.refinements.push(Filter::new(Search::default()));
+            }
 
-                chunks.push(TextChunk {
-                    text: m.as_str().to_owned(),
-                    format: Some(TextFormat {
-                        color: Color32::RED,
-                        ..Default::default()
-                    }),
-                });
+            if filter_active && !self.stage_counts.is_empty() {
+                let counts = self
+                    .stage_counts
+                    .iter()
+                    .map(usize::to_string)
+                    .collect::<Vec<_>>()
+                    .join(" → ");
 
-                last_end = m.end()
+                ui.label(format!("{counts} rows"));
             }
+        });
 
-            chunks.push(TextChunk {
-                text: text[last_end..].to_string(),
-                format: None,
-            });
+        self.refinements_changed = changed;
+    }
 
-            l.chunks = Some(chunks);
+    /// The highlights which are matched, the enabled ones up to [`Self::max_highlights`], with their
+    /// indices.
+    fn evaluated_highlights(&self) -> impl Iterator<Item = (usize, &RowHighlight)> {
+        let max = if self.max_highlights == 0 { usize::MAX } else { self.max_highlights };
+
+        self.row_highlights
+            .iter()
+            .enumerate()
+            .filter(|(_, h)| !h.disabled)
+            .take(max)
+    }
+
+    /// Whether the costs of the highlights need to be measured again, having been measured over
+    /// fewer than `sample_size` lines or before highlights were added or removed.
+    pub fn highlight_costs_outdated(&self, sample_size: usize) -> bool {
+        self.highlight_costs.len() != self.row_highlights.len() || self.highlight_sample_size < sample_size
+    }
+
+    /// Whether the costs of the highlights should be measured now, which waits for
+    /// [`HIGHLIGHT_MEASURE_DELAY`] after the last edit to the highlights.
+    pub fn highlight_costs_due(&mut self, ctx: &egui::Context, sample_size: usize) -> bool {
+        if self.highlights_changed {
+            self.highlight_costs_due = Some(Instant::now() + HIGHLIGHT_MEASURE_DELAY);
         }
 
-        l
+        match self.highlight_costs_due {
+            Some(due) => match due.checked_duration_since(Instant::now()) {
+                Some(remaining) if !remaining.is_zero() => {
+                    ctx.request_repaint_after(remaining);
+                    false
+                }
+                _ => {
+                    self.highlight_costs_due = None;
+                    true
+                }
+            },
+            None => self.highlight_costs_outdated(sample_size),
+        }
     }
-}
 
-#[derive(Debug)]
-pub enum LogFileMessage {
-    FileData(Vec<String>),
-    Error(crate::Error),
-    ShowRestrictFileSizeDialog(u64, Sender<bool>),
-    RestrictFileSize(bool),
-    SetEncoding(Option<&'static Encoding>),
-}
+    /// Measures how long each highlight takes to match the lines of `sample`, to tell how long
+    /// matching the `rows_in_view` takes each frame.
+    pub fn measure_highlights(&mut self, sample: &[String], rows_in_view: usize) {
+        self.highlight_costs = self
+            .row_highlights
+            .iter()
+            .map(|h| {
+                (h.search.is_ready() && !sample.is_empty()).then(|| {
+                    let start = Instant::now();
 
-#[derive(Clone, Debug, Default)]
-pub enum RestrictFileSize {
-    #[default]
-    Initializing,
-    ShowRestrictFileSizeDialog(u64, Sender<bool>),
-    RestrictedFileSize,
-    UnrestrictedFileSize,
-}
+                    for line in sample {
+                        std::hint::black_box(h.search.find_ranges(line));
+                    }
 
-// TODO: Some better state management?
-#[derive(Serialize, Deserialize)]
-pub struct LogFile {
-    pub filename: String,
-    pub path: PathBuf,
-    #[serde(default)]
-    pub encoding: Option<&'static Encoding>,
-    #[serde(skip, default)]
-    pub errors: Vec<crate::Error>,
-    #[serde(skip)]
-    pub restrict_filesize: RestrictFileSize,
-    #[serde(default)]
-    pub row_modifier: RowModifier,
-    #[serde(skip)]
-    pub lines: Vec<String>,
-    #[serde(skip)]
-    receiver: Option<Receiver<LogFileMessage>>,
-    #[serde(skip)]
-    sender: Option<Sender<LogFileMessage>>,
-    #[serde(skip, default)]
-    recalculate_filter_cache: bool,
-    #[serde(skip)]
-    filter_cache: Option<Vec<String>>,
-    #[serde(skip)]
-    pub thread: Option<JoinHandle<()>>,
-}
+                    start.elapsed() / sample.len() as u32
+                })
+            })
+            .collect();
+        self.highlight_sample_size = sample.len();
+        self.rows_in_view = rows_in_view;
+    }
 
-impl LogFile {
-    pub fn reload_with_encoding(&mut self, encoding: &'static Encoding) {
-        self.encoding = Some(encoding);
+    /// Estimated time matching the highlights takes each frame.
+    fn highlight_frame_cost(&self) -> Duration {
+        let per_line: Duration = self
+            .evaluated_highlights()
+            .filter_map(|(i, _)| self.highlight_costs.get(i).copied().flatten())
+            .sum();
 
-        if let Some(thread) = self.thread.as_ref() {
-            thread.abort();
-        }
+        per_line * self.rows_in_view as u32
+    }
 
-        self.thread = None;
-        self.receiver = None;
+    /// Disables the slowest highlights until matching them fits within the frame budget.
+    pub fn disable_slowest_highlights(&mut self) {
+        let mut by_cost: Vec<(usize, Duration)> = self
+            .evaluated_highlights()
+            .filter_map(|(i, _)| Some((i, self.highlight_costs.get(i).copied().flatten()?)))
+            .collect();
+        by_cost.sort_by_key(|(_, cost)| std::cmp::Reverse(*cost));
+
+        for (index, _) in by_cost {
+            if self.highlight_frame_cost() <= HIGHLIGHT_FRAME_BUDGET {
+                break;
+            }
+
+            self.row_highlights[index].disabled = true;
+            self.highlights_changed = true;
+        }
     }
 
-    // TODO: Change receiver type to Result<Vec<String>, ReadError>?
-    pub fn create_receiver(
-        &mut self,
-        ctx: egui::Context,
-    ) -> (JoinHandle<()>, Receiver<LogFileMessage>) {
-        let (sender, receiver) = channel();
-        let file_path = self.path.clone();
+    fn highlight_cost_ui(&mut self, ui: &mut egui::Ui) {
+        let cost = self.highlight_frame_cost();
 
-        self.sender = Some(sender.clone());
-        let encoding = self.encoding.clone();
+        if cost.is_zero() {
+            return;
+        }
 
-        // TODO: Let users choose encoding.
-        let handle = tokio::spawn(async move {
-            if let Err(e) = reader(file_path.as_path(), sender, ctx, encoding).await {
-                // TODO: Actual error handling
-                error!("LogFile reader thread failed: {e:?}");
+        ui.horizontal(|ui| {
+            if cost > HIGHLIGHT_FRAME_BUDGET {
+                ui.colored_label(ui.visuals().warn_fg_color, format!("⚠ Highlighting takes about {cost:.1?} per frame"))
+                    .on_hover_text("Which can make scrolling janky, disable the slowest highlights or match fewer of them");
+
+                if ui.button("Disable slowest").clicked() {
+                    self.disable_slowest_highlights();
+                }
+            } else {
+                ui.weak(format!("Highlighting takes about {cost:.1?} per frame"));
             }
         });
+    }
 
-        (handle, receiver)
+    /// Whether the highlights apply to `text`, see [`Self::highlight_filter_matches_only`].
+    pub fn highlights_apply(&self, text: &str) -> bool {
+        !self.highlight_filter_matches_only
+            || !self.filter.search.is_ready()
+            || self.filter.is_match(text)
     }
 
-    pub fn new(path: PathBuf, items: Vec<String>) -> Self {
+    /// Whether the result of [`Self::filter`] might've changed since the last frame.
+    pub fn filter_changed(&self) -> bool {
+        self.filter.changed() || self.rewrites_changed || self.refinements_changed
+    }
+
+    /// Whether a highlight was changed or removed since the last frame.
+    pub fn highlights_changed(&self) -> bool {
+        self.highlights_changed
+    }
+
+    /// The index of the highlight whose navigation buttons were clicked since the last call, and
+    /// which one.
+    pub fn take_highlight_jump(&mut self) -> Option<(usize, Jump)> {
+        self.highlight_jump.take()
+    }
+
+    /// Which of the filter's navigation buttons was clicked since the last call.
+    pub fn take_filter_jump(&mut self) -> Option<Jump> {
+        self.filter.jump.take()
+    }
+
+    fn rewrite_rules_ui(&mut self, ui: &mut egui::Ui) {
+        let mut rules_changed = false;
+
+        ui.horizontal(|ui| {
+            ui.label("Rewrite rows");
+
+            if ui
+                .button("+")
+                .on_hover_ui(|ui| {
+                    ui.label("Add new rewrite rule");
+                })
+                .clicked()
+            {
+                self.rewrite_rules.push(RewriteRule::default());
+            }
+
+            ui.add_space(4.0);
+
+            ui.vertical(|ui| {
+                ui.spacing_mut().item_spacing = Vec2::new(8.0, 8.0);
+
+                let mut rule_to_remove = None;
+                let mut rule_to_move_up = None;
+
+                for (index, rule) in self.rewrite_rules.iter_mut().enumerate() {
+                    rule.ui(ui);
+                    rules_changed |= rule.changed;
+
+                    if rule.should_delete {
+                        rule_to_remove = Some(index);
+                    } else if rule.should_move_up && index > 0 {
+                        rule_to_move_up = Some(index);
+                    }
+                }
+
+                if let Some(index) = rule_to_remove {
+                    self.rewrite_rules.remove(index);
+                }
+
+                if let Some(index) = rule_to_move_up {
+                    self.rewrite_rules.swap(index - 1, index);
+                }
+            });
+        });
+
+        let filter_rewritten_changed = ui
+            .checkbox(&mut self.filter_rewritten, "Filter on rewritten rows")
+            .on_hover_text("Otherwise the filter matches against the original lines")
+            .changed();
+
+        self.rewrites_changed = filter_rewritten_changed || (self.filter_rewritten && rules_changed);
+    }
+
+    /// Applies all enabled rewrite rules, in order.
+    pub fn rewrite<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        self.rewrite_rules
+            .iter()
+            .fold(Cow::Borrowed(text), |text, rule| match rule.rewrite(&text) {
+                Cow::Borrowed(_) => text,
+                Cow::Owned(rewritten) => Cow::Owned(rewritten),
+            })
+    }
+
+    fn is_match(&self, filter: &Filter, line: &str) -> bool {
+        if self.filter_rewritten && !self.rewrite_rules.is_empty() {
+            filter.is_match(&self.rewrite(line))
+        } else {
+            filter.is_match(line)
+        }
+    }
+
+    /// Filters `lines` using the filter and then each active refinement, matching against the
+    /// rewritten rows if configured to. Returns the indices of the remaining lines and the number
+    /// of rows remaining after each step.
+    /// Will return None if there is nothing to filter on.
+    pub fn filter_rows(&self, lines: &[String]) -> Option<(Vec<usize>, Vec<usize>)> {
+        self.filter_items(lines.len(), |i| Cow::Borrowed(&lines[i]))
+    }
+
+    /// Like [`Self::filter_rows`], for `count` items whose text is given by `text`, e.g. entries
+    /// made up of several lines.
+    pub fn filter_items<'a>(
+        &self,
+        count: usize,
+        text: impl Fn(usize) -> Cow<'a, str> + Sync,
+    ) -> Option<(Vec<usize>, Vec<usize>)> {
+        self.filter_items_controlled(count, text, &FilterControl::default())
+    }
+
+    /// Like [`Self::filter_items`], reporting the progress through `control` and giving up (with
+    /// None) once cancelled through it.
+    pub fn filter_items_controlled<'a>(
+        &self,
+        count: usize,
+        text: impl Fn(usize) -> Cow<'a, str> + Sync,
+        control: &FilterControl,
+    ) -> Option<(Vec<usize>, Vec<usize>)> {
+        if !self.filter.search.is_ready() {
+            return None;
+        }
+
+        let mut items: Vec<usize> = Vec::new();
+
+        // Most of the time is spent on the first step, which goes through every item.
+        for start in (0..count).step_by(FILTER_BLOCK_SIZE) {
+            if control.is_cancelled() {
+                return None;
+            }
+
+            let end = (start + FILTER_BLOCK_SIZE).min(count);
+            items.par_extend(
+                (start..end)
+                    .into_par_iter()
+                    .filter(|&i| self.is_match(&self.filter, &text(i))),
+            );
+            control.progress.store(end, Ordering::Relaxed);
+        }
+
+        let mut counts = vec![items.len()];
+
+        for refinement in &self.refinements {
+            if !refinement.filter || refinement.search.is_empty() {
+                continue;
+            }
+
+            if control.is_cancelled() {
+                return None;
+            }
+
+            if refinement.search.is_ready() {
+                items = items
+                    .into_par_iter()
+                    .filter(|&i| self.is_match(refinement, &text(i)))
+                    .collect();
+                counts.push(items.len());
+            }
+        }
+
+        Some((items, counts))
+    }
+
+    /// Like [`Self::filter_rows`], with the lines rather than their indices.
+    pub fn filter_with_counts(&self, lines: &[String]) -> Option<(Vec<String>, Vec<usize>)> {
+        self.filter_rows(lines).map(|(rows, counts)| {
+            (rows.into_iter().map(|i| lines[i].clone()).collect(), counts)
+        })
+    }
+
+    /// Like [`Self::filter_with_counts`], without the counts.
+    pub fn filter(&self, lines: &[String]) -> Option<Vec<String>> {
+        self.filter_with_counts(lines).map(|(filtered, _)| filtered)
+    }
+
+    /// Rebuilds the regexes of the filter, all highlights and rewrite rules, e.g. after being
+    /// deserialized.
+    pub fn rebuild_regexes(&mut self) {
+        self.filter.rebuild_regexes();
+
+        for row_highlight in &mut self.row_highlights {
+            row_highlight.search.rebuild_regex();
+        }
+
+        for rule in &mut self.rewrite_rules {
+            rule.search.rebuild_regex();
+        }
+
+        for refinement in &mut self.refinements {
+            refinement.rebuild_regexes();
+        }
+    }
+
+    pub fn generate_line(&self, text: &str) -> Line {
+        let options = RenderOptions {
+            ansi: AnsiEscapes::Keep,
+            ..Default::default()
+        };
+
+        self.generate_line_with_format(text, &TextFormat::default(), options)
+    }
+
+    /// Like [`Self::generate_line`], with `base` as the format the highlights are applied on top of
+    /// and the text rendered according to `options`.
+    ///
+    /// The formats are layered, from the bottom:
+    /// 1. The first matching row highlight colors the whole row, otherwise it's in `base` with
+    ///    interpreted ANSI colors, or the logfmt colors where there aren't any. ANSI colors are
+    ///    also dropped if a rewrite rule changed the line since they'd no longer line up.
+    /// 2. The text matched by each matching row highlight, in the order of the highlights so the
+    ///    later ones are on top where they overlap.
+    /// 3. Filter matches, colored red on top of whichever background they have.
+    /// 4. Shown whitespace, keeping the color of the text below it.
+    pub fn generate_line_with_format(
+        &self,
+        original: &str,
+        base: &TextFormat,
+        options: RenderOptions,
+    ) -> Line {
+        let RenderOptions {
+            ansi,
+            tab_width,
+            show_whitespace,
+            logfmt,
+        } = options;
+        // The line terminator would otherwise be laid out as an extra, empty row. Record separators
+        // end lines as well.
+        let original_text = original.trim_end_matches(['\n', '\r', '\0', '\x1e']);
+        let (plain, mut ansi_spans) = match ansi {
+            AnsiEscapes::Keep => (Cow::Borrowed(original_text), Vec::new()),
+            AnsiEscapes::Strip => (strip_ansi(original_text), Vec::new()),
+            AnsiEscapes::Interpret => parse_ansi(original_text),
+        };
+
+        // Highlights are matched against the rewritten text with its tabs expanded, so match
+        // offsets line up with what's displayed.
+        let rewritten = self.rewrite(&plain);
+
+        if rewritten.as_ref() != plain.as_ref() {
+            ansi_spans.clear();
+        }
+
+        let (expanded, tab_stops) = expand_tabs(&rewritten, tab_width);
+        let text = expanded.as_ref();
+
+        for span in &mut ansi_spans {
+            span.range = tab_stops.map(span.range.start)..tab_stops.map(span.range.end);
+        }
+
+        let whitespace = if show_whitespace { whitespace_ranges(text, &tab_stops) } else { Vec::new() };
+        let mut logfmt_colors = if logfmt { logfmt::colors(text, base.color) } else { Vec::new() };
+
+        let mut l = Line::new(text.to_owned(), base.clone());
+
+        let highlight_format = |row_highlight: &RowHighlight| TextFormat {
+            background: row_highlight.bg_color,
+            color: row_highlight.fg_color,
+            ..base.clone()
+        };
+
+        let matches = self
+            .filter
+            .search
+            .is_ready()
+            .then(|| self.filter.find_ranges(text));
+
+        // The text matched by each highlight, in the order of the highlights.
+        let mut highlight_spans: Vec<(Range<usize>, &RowHighlight)> = Vec::new();
+        let highlighted = !self.highlight_filter_matches_only || matches.as_ref().is_none_or(|m| !m.is_empty());
+
+        for (_, row_highlight) in self.evaluated_highlights().filter(|_| highlighted) {
+            if !row_highlight.search.is_ready() {
+                continue;
+            }
+
+            let ranges = row_highlight.search.find_ranges(text);
+
+            if !ranges.is_empty() && highlight_spans.is_empty() {
+                l.default_format = highlight_format(row_highlight);
+                ansi_spans.clear();
+                logfmt_colors.clear();
+            }
+
+            highlight_spans.extend(ranges.into_iter().map(|r| (r, row_highlight)));
+        }
+
+        if matches.is_some()
+            || !ansi_spans.is_empty()
+            || !highlight_spans.is_empty()
+            || !whitespace.is_empty()
+            || !logfmt_colors.is_empty()
+        {
+            let matches = matches.unwrap_or_default();
+
+            // Split the text wherever a match, highlight or ANSI styled span starts or ends.
+            let mut bounds: Vec<usize> = [0, text.len()]
+                .into_iter()
+                .chain(matches.iter().flat_map(|m| [m.start, m.end]))
+                .chain(highlight_spans.iter().flat_map(|(r, _)| [r.start, r.end]))
+                .chain(ansi_spans.iter().flat_map(|s| [s.range.start, s.range.end]))
+                .chain(whitespace.iter().flat_map(|r| [r.start, r.end]))
+                .chain(logfmt_colors.iter().flat_map(|(r, _)| [r.start, r.end]))
+                .collect();
+            bounds.sort_unstable();
+            bounds.dedup();
+
+            let chunks = bounds
+                .windows(2)
+                .map(|w| {
+                    let mut format = highlight_spans
+                        .iter()
+                        .rev()
+                        .find(|(r, _)| r.contains(&w[0]))
+                        .map(|(_, h)| highlight_format(h))
+                        .or_else(|| {
+                            ansi_spans
+                                .iter()
+                                .find(|s| s.range.contains(&w[0]))
+                                .map(|s| s.style.apply(base))
+                        })
+                        .or_else(|| {
+                            logfmt_colors
+                                .iter()
+                                .find(|(r, _)| r.contains(&w[0]))
+                                .map(|&(_, color)| TextFormat {
+                                    color,
+                                    ..base.clone()
+                                })
+                        });
+
+                    let matched = matches.iter().any(|m| m.contains(&w[0]));
+
+                    if matched {
+                        let format_below = format.unwrap_or_else(|| l.default_format.clone());
+
+                        format = Some(match self.match_style {
+                            MatchStyle::Color => TextFormat {
+                                color: Color32::RED,
+                                ..format_below
+                            },
+                            MatchStyle::Underline => TextFormat {
+                                underline: Stroke::new(1.5, format_below.color),
+                                ..format_below
+                            },
+                            MatchStyle::Box => format_below,
+                        });
+                    }
+
+                    if whitespace.iter().any(|r| r.contains(&w[0])) {
+                        format = Some(TextFormat {
+                            background: WHITESPACE_BACKGROUND,
+                            ..format.unwrap_or_else(|| l.default_format.clone())
+                        });
+                    }
+
+                    TextChunk {
+                        text: text[w[0]..w[1]].to_owned(),
+                        format,
+                        boxed: matched && self.match_style == MatchStyle::Box,
+                    }
+                })
+                .collect();
+
+            l.chunks = Some(chunks);
+        }
+
+        l
+    }
+}
+
+/// How filter matches are emphasized.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MatchStyle {
+    /// Colored red.
+    #[default]
+    Color,
+    Underline,
+    /// Framed by a box, e.g. for colorblind users who can't tell the red apart.
+    Box,
+}
+
+impl MatchStyle {
+    pub fn label(&self) -> &'static str {
+        match self {
+            MatchStyle::Color => "Color",
+            MatchStyle::Underline => "Underline",
+            MatchStyle::Box => "Box",
+        }
+    }
+}
+
+const DEFAULT_FONT_SIZE: f32 = 14.0;
+const DEFAULT_TAB_WIDTH: usize = 4;
+/// Column wrapped at once wrapping at a column is turned on, the width of a wide terminal.
+const DEFAULT_WRAP_COLUMN: usize = 120;
+
+/// How tightly rows are packed.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Density {
+    /// Rows directly below each other, showing as many as possible.
+    #[default]
+    Compact,
+    /// Space between rows, for readability.
+    Comfortable,
+}
+
+impl Density {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Density::Compact => "Compact",
+            Density::Comfortable => "Comfortable",
+        }
+    }
+
+    /// Space between rows `row_height` high.
+    pub fn row_spacing(&self, row_height: f32) -> f32 {
+        match self {
+            Density::Compact => 0.0,
+            Density::Comfortable => (row_height * 0.4).round(),
+        }
+    }
+}
+
+/// How [`RowModifier::generate_line_with_format`] renders the text of a line, besides its
+/// highlights.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RenderOptions {
+    /// What to do with ANSI escape sequences.
+    pub ansi: AnsiEscapes,
+    /// Columns tabs are expanded to, 0 keeps them.
+    pub tab_width: usize,
+    /// Give trailing whitespace and tabs a faint background, only changing how they're displayed.
+    pub show_whitespace: bool,
+    /// Color the keys and values of logfmt lines.
+    pub logfmt: bool,
+}
+
+/// How the lines of a file are displayed.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct DisplayOptions {
+    pub font_size: f32,
+    /// Additional space between rows, in points.
+    pub line_spacing: f32,
+    pub wrap: bool,
+    /// Wrap after this many characters, e.g. to match the width of a terminal, rather than at the
+    /// width of the view. The lines are shown in a monospace font so the columns line up. Takes
+    /// precedence over [`Self::wrap`].
+    pub wrap_column: Option<usize>,
+    pub line_numbers: bool,
+    /// Show the byte offset of each line within the file.
+    pub byte_offsets: bool,
+    /// Color lines by their detected level, lines matching a highlight keep the highlight colors.
+    pub level_colors: bool,
+    /// What to do with ANSI escape sequences, e.g. from colorized console output.
+    pub ansi: AnsiEscapes,
+    /// Tabs are expanded to spaces up to the next multiple of this many columns, 0 keeps them.
+    pub tab_width: usize,
+    /// Fold lines which don't start an entry (see [`FormatProfile::is_entry_start`]) under the
+    /// preceding entry, e.g. stack traces. The filter matches against whole entries.
+    pub group_entries: bool,
+    /// Scroll to the first matching row when the filter changes, unless following the end of the
+    /// file.
+    pub scroll_to_first_match: bool,
+    /// Overrides the density of the settings.
+    pub density: Option<Density>,
+    /// Show the lines as a table of their delimited fields, e.g. CSV, rather than as text.
+    pub table: Option<TableOptions>,
+    /// Give trailing whitespace and tabs a faint background, to make them visible.
+    pub show_whitespace: bool,
+    /// Dim the keys and emphasize the values of logfmt lines, e.g. `level=info msg="..."`.
+    pub logfmt: bool,
+}
+
+impl Default for DisplayOptions {
+    fn default() -> Self {
         Self {
-            filename: path.to_string_lossy().to_string(),
-            path,
-            row_modifier: RowModifier::default(),
-            lines: items,
-            restrict_filesize: RestrictFileSize::default(),
-            receiver: None,
-            sender: None,
-            recalculate_filter_cache: false,
-            filter_cache: None,
-            thread: None,
-            encoding: None,
-            errors: Vec::new(),
+            font_size: DEFAULT_FONT_SIZE,
+            line_spacing: 0.0,
+            wrap: false,
+            wrap_column: None,
+            line_numbers: false,
+            byte_offsets: false,
+            level_colors: false,
+            ansi: AnsiEscapes::default(),
+            tab_width: DEFAULT_TAB_WIDTH,
+            group_entries: false,
+            scroll_to_first_match: false,
+            density: None,
+            table: None,
+            show_whitespace: false,
+            logfmt: false,
+        }
+    }
+}
+
+impl DisplayOptions {
+    pub fn font_id(&self) -> FontId {
+        if self.wrap_column.is_some() {
+            FontId::monospace(self.font_size)
+        } else {
+            FontId::proportional(self.font_size)
+        }
+    }
+
+    pub fn render_options(&self) -> RenderOptions {
+        RenderOptions {
+            ansi: self.ansi,
+            tab_width: self.tab_width,
+            show_whitespace: self.show_whitespace,
+            logfmt: self.logfmt,
+        }
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        Grid::new(ui.next_auto_id())
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("Font size");
+                ui.add(DragValue::new(&mut self.font_size).range(6.0..=72.0).speed(0.5));
+                ui.end_row();
+
+                ui.label("Density");
+                ui.horizontal(|ui| {
+                    ui.selectable_value(&mut self.density, None, "Default")
+                        .on_hover_text("The density chosen in the settings");
+
+                    for density in [Density::Compact, Density::Comfortable] {
+                        ui.selectable_value(&mut self.density, Some(density), density.label());
+                    }
+                });
+                ui.end_row();
+
+                ui.label("Line spacing").on_hover_text("Added to the spacing of the density");
+                ui.add(DragValue::new(&mut self.line_spacing).range(0.0..=32.0).speed(0.5));
+                ui.end_row();
+
+                ui.label("Wrap lines");
+                ui.horizontal(|ui| {
+                    ui.add_enabled(self.wrap_column.is_none(), egui::Checkbox::new(&mut self.wrap, ""))
+                        .on_disabled_hover_text("Wrapped at the column instead");
+
+                    let mut at_column = self.wrap_column.is_some();
+
+                    if ui
+                        .checkbox(&mut at_column, "At column")
+                        .on_hover_text("Wrap after a number of characters, e.g. to match the width of a terminal, rather than at the width of the view")
+                        .changed()
+                    {
+                        self.wrap_column = at_column.then_some(DEFAULT_WRAP_COLUMN);
+                    }
+
+                    if let Some(column) = self.wrap_column.as_mut() {
+                        ui.add(DragValue::new(column).range(10..=1000));
+                    }
+                });
+                ui.end_row();
+
+                ui.label("Line numbers");
+                ui.checkbox(&mut self.line_numbers, "");
+                ui.end_row();
+
+                ui.label("Byte offsets");
+                ui.checkbox(&mut self.byte_offsets, "");
+                ui.end_row();
+
+                ui.label("Color by level");
+                ui.checkbox(&mut self.level_colors, "");
+                ui.end_row();
+
+                ui.label("Tab width").on_hover_text("Tabs are expanded to spaces, 0 keeps them");
+                ui.add(DragValue::new(&mut self.tab_width).range(0..=16));
+                ui.end_row();
+
+                ui.label("Show whitespace").on_hover_text("Give trailing whitespace and tabs a faint background");
+                ui.checkbox(&mut self.show_whitespace, "");
+                ui.end_row();
+
+                ui.label("Logfmt").on_hover_text(
+                    "Dim the keys and emphasize the values of key=value lines, e.g. level=info msg=\"Started\"",
+                );
+                ui.checkbox(&mut self.logfmt, "");
+                ui.end_row();
+
+                ui.label("Group multi-line entries").on_hover_text(
+                    "Fold lines not matching the format's entry start pattern under the preceding entry, click an entry to expand it",
+                );
+                ui.checkbox(&mut self.group_entries, "");
+                ui.end_row();
+
+                ui.label("Scroll to first match").on_hover_text(
+                    "Scroll to the first matching row when the filter changes, unless scrolled to the end",
+                );
+                ui.checkbox(&mut self.scroll_to_first_match, "");
+                ui.end_row();
+
+                ui.label("ANSI escapes");
+                ui.horizontal(|ui| {
+                    for ansi in [AnsiEscapes::Strip, AnsiEscapes::Interpret, AnsiEscapes::Keep] {
+                        ui.selectable_value(&mut self.ansi, ansi, ansi.label());
+                    }
+                });
+                ui.end_row();
+
+                ui.label("Table view").on_hover_text(
+                    "Split delimited lines, e.g. CSV, into columns, click a column's header to sort by it",
+                );
+                ui.vertical(|ui| {
+                    let mut table = self.table.is_some();
+
+                    if ui.checkbox(&mut table, "").changed() {
+                        self.table = table.then(TableOptions::default);
+                    }
+
+                    if let Some(options) = self.table.as_mut() {
+                        options.ui(ui);
+                    }
+                });
+                ui.end_row();
+            });
+    }
+}
+
+#[derive(Debug)]
+pub enum LogFileMessage {
+    /// New lines, the byte offset of each of them within the file, and the index and original
+    /// length of those truncated.
+    FileData(Vec<String>, Vec<u64>, Vec<(usize, usize)>),
+    Error(crate::Error),
+    ShowRestrictFileSizeDialog(u64, Sender<bool>),
+    RestrictFileSize(bool),
+    SetEncoding(&'static Encoding, EncodingDetection),
+    /// The file is a stream (e.g. a FIFO) rather than a regular file.
+    Streaming,
+    /// Raw bytes from the start of the file, used to preview other encodings.
+    EncodingSample(Vec<u8>),
+    /// The file has been deleted (or moved away), nothing more will be read until it reappears.
+    SourceDeleted,
+    /// A deleted file has been created again, tailing is resumed.
+    SourceRestored,
+    /// The file couldn't be watched for changes, with the reason why. It's read once rather than
+    /// tailed.
+    NotWatching(String),
+    /// When the file was last modified, sent once the existing lines have been read and whenever
+    /// new ones have.
+    Modified(SystemTime),
+    /// The lines from the byte offset on are decoded using another encoding, after the previous
+    /// one failed to decode them.
+    EncodingSwitched(u64, &'static Encoding),
+    /// Restricted mode started reading at the byte offset, of a file of the size, what's before it
+    /// isn't read.
+    SkippedTo(u64, u64),
+}
+
+/// Sends the messages of a reader stamped with the epoch of the receiver it was created for, see
+/// [`LogFile::epoch`]. A reader still sending after being replaced, e.g. by a reload, has its
+/// messages ignored rather than mixed in with those of the reader replacing it.
+#[derive(Debug, Clone)]
+pub struct ReaderOutput {
+    sender: Sender<(u64, LogFileMessage)>,
+    epoch: u64,
+}
+
+impl ReaderOutput {
+    pub fn send(&self, msg: LogFileMessage) -> Result<(), std::sync::mpsc::SendError<LogFileMessage>> {
+        self.sender
+            .send((self.epoch, msg))
+            .map_err(|std::sync::mpsc::SendError((_, msg))| std::sync::mpsc::SendError(msg))
+    }
+}
+
+/// Byte separating the records of a file, for tools writing records which can span several lines.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordSeparator {
+    Nul,
+    /// The ASCII record separator, 0x1E.
+    Rs,
+}
+
+impl RecordSeparator {
+    pub fn byte(&self) -> u8 {
+        match self {
+            RecordSeparator::Nul => 0x00,
+            RecordSeparator::Rs => 0x1e,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            RecordSeparator::Nul => "NUL",
+            RecordSeparator::Rs => "RS (0x1E)",
+        }
+    }
+}
+
+/// How the encoding a file is read with was arrived at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EncodingDetection {
+    /// Known beforehand, e.g. chosen by the user or restored from the previous session.
+    Given,
+    /// Given by the file's byte order mark.
+    Bom,
+    /// Guessed from the contents of the file, `confident` unless another encoding is about as
+    /// likely.
+    Guessed { confident: bool },
+    /// UTF-8 assumed without looking at the contents, e.g. for streams which can't be read ahead.
+    Assumed,
+    /// The guess wasn't confident, so the fallback encoding from the settings is used instead.
+    Fallback,
+}
+
+impl EncodingDetection {
+    /// Whether the encoding was found by looking at the contents of the file.
+    pub fn is_detected(&self) -> bool {
+        matches!(self, Self::Bom | Self::Guessed { .. })
+    }
+
+    pub fn is_confident(&self) -> bool {
+        match self {
+            Self::Given | Self::Bom => true,
+            Self::Guessed { confident } => *confident,
+            Self::Assumed | Self::Fallback => false,
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::Given => "Chosen encoding",
+            Self::Bom => "Detected by the byte order mark",
+            Self::Guessed { confident: true } => "Detected from the contents",
+            Self::Guessed { confident: false } => "Guessed from the contents, another encoding might be as likely",
+            Self::Assumed => "Assumed, the encoding of streams isn't detected",
+            Self::Fallback => "The fallback encoding from the settings, detection wasn't sure about its guess",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub enum RestrictFileSize {
+    #[default]
+    Initializing,
+    ShowRestrictFileSizeDialog(u64, Sender<bool>),
+    RestrictedFileSize,
+    UnrestrictedFileSize,
+}
+
+/// How fast lines are arriving, averaged over the batches received within [`THROUGHPUT_WINDOW`].
+#[derive(Debug, Default)]
+struct Throughput {
+    /// When each batch arrived, and its number of lines and bytes.
+    batches: VecDeque<(Instant, usize, usize)>,
+}
+
+impl Throughput {
+    fn record(&mut self, now: Instant, lines: usize, bytes: usize) {
+        self.batches.push_back((now, lines, bytes));
+        self.forget_before(now);
+    }
+
+    fn forget_before(&mut self, now: Instant) {
+        while self.batches.front().is_some_and(|(arrived, _, _)| now.duration_since(*arrived) > THROUGHPUT_WINDOW) {
+            self.batches.pop_front();
+        }
+    }
+
+    /// Lines and bytes per second, None if nothing arrived within the window. Averaged over the
+    /// time since the first of the batches, but at least a second to not exaggerate a single batch.
+    fn rate(&mut self, now: Instant) -> Option<(f64, f64)> {
+        self.forget_before(now);
+
+        let (first, _, _) = self.batches.front()?;
+        let seconds = now.duration_since(*first).as_secs_f64().max(1.0);
+        let (lines, bytes) = self
+            .batches
+            .iter()
+            .fold((0, 0), |(lines, bytes), (_, l, b)| (lines + l, bytes + b));
+
+        Some((lines as f64 / seconds, bytes as f64 / seconds))
+    }
+}
+
+/// At most this many bytes of the lines of a snapshot are persisted, the last ones, so large
+/// snapshots don't bloat the storage written on every save.
+const MAX_PERSISTED_SNAPSHOT_BYTES: usize = 4 * 1024 * 1024;
+
+/// The lines of a file frozen at some point, see [`LogFile::snapshot`].
+#[derive(Deserialize, Debug, Clone)]
+pub struct Snapshot {
+    pub taken: SystemTime,
+    /// Persisted unlike the lines of other files, as they can't be read again. Only the last
+    /// [`MAX_PERSISTED_SNAPSHOT_BYTES`] of them.
+    #[serde(deserialize_with = "deserialize_lines")]
+    pub lines: Arc<Vec<String>>,
+    /// Number of lines dropped before the first of `lines`, to keep the line numbers.
+    pub dropped_lines: u64,
+    /// Notes about the lines, by line number, marked by labeled dividers.
+    #[serde(default)]
+    pub notes: Vec<(u64, String)>,
+}
+
+impl Serialize for Snapshot {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        struct Persisted<'a> {
+            taken: SystemTime,
+            lines: &'a [String],
+            dropped_lines: u64,
+            notes: &'a [(u64, String)],
+        }
+
+        let mut bytes = 0;
+        let kept = self
+            .lines
+            .iter()
+            .rev()
+            .take_while(|line| {
+                bytes += line.len();
+                bytes <= MAX_PERSISTED_SNAPSHOT_BYTES
+            })
+            .count();
+        let dropped = self.lines.len() - kept;
+
+        // The lines left out count as dropped, so the line numbers stay the same.
+        Persisted {
+            taken: self.taken,
+            lines: &self.lines[dropped..],
+            dropped_lines: self.dropped_lines + dropped as u64,
+            notes: &self.notes,
         }
+        .serialize(serializer)
+    }
+}
+
+fn deserialize_lines<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Arc<Vec<String>>, D::Error> {
+    Vec::deserialize(deserializer).map(Arc::new)
+}
+
+/// E.g. "14:03:22", the local time of day of `time`.
+fn clock_time(time: SystemTime) -> String {
+    chrono::DateTime::<chrono::Local>::from(time).format("%H:%M:%S").to_string()
+}
+
+// TODO: Some better state management?
+#[derive(Serialize, Deserialize)]
+pub struct LogFile {
+    pub filename: String,
+    pub path: PathBuf,
+    /// Shown as the tab title instead of the filename.
+    #[serde(default)]
+    pub alias: Option<String>,
+    /// The file within the archive at `path` which is shown, if `path` is an archive.
+    #[serde(default)]
+    pub archive_member: Option<String>,
+    #[serde(default)]
+    pub encoding: Option<&'static Encoding>,
+    #[serde(skip)]
+    encoding_detection: Option<EncodingDetection>,
+    #[serde(skip, default)]
+    pub errors: Vec<crate::Error>,
+    #[serde(skip)]
+    pub restrict_filesize: RestrictFileSize,
+    /// Whether to open the file in restricted mode if it's large, asked if None.
+    #[serde(skip)]
+    pub restrict_decision: Option<bool>,
+    /// Byte offset to start reading the file at, rather than its start.
+    #[serde(default)]
+    pub start_offset: Option<u64>,
+    /// Read records separated by this rather than lines, each record shown as an entry.
+    #[serde(default)]
+    pub record_separator: Option<RecordSeparator>,
+    /// The lines of a snapshot, which are shown rather than reading the file.
+    #[serde(default)]
+    pub snapshot: Option<Snapshot>,
+    /// Lines given rather than read from anywhere, see [`LogFile::from_lines`]. Never persisted.
+    #[serde(skip)]
+    in_memory: Option<Arc<Vec<String>>>,
+    /// Line numbers of the lines kept in view above the rows, e.g. a header.
+    #[serde(default)]
+    pub pinned_lines: Vec<u64>,
+    /// The snapshot button was clicked, handled by [`crate::LogTool`].
+    #[serde(skip)]
+    snapshot_requested: bool,
+    /// Byte offset restricted mode started reading at, and the size of the file then. What's
+    /// before the offset isn't read.
+    #[serde(skip)]
+    skipped_to: Option<(u64, u64)>,
+    /// Scrubbed to a window of the file, which is read once rather than followed.
+    #[serde(skip)]
+    scrub: Option<Scrub>,
+    /// Position of the scrub slider while it's being dragged.
+    #[serde(skip)]
+    scrub_draft: Option<f32>,
+    /// Size of the file, or why it couldn't be had, read when the scrub menu was opened.
+    #[serde(skip)]
+    scrub_file_len: Option<Result<u64, String>>,
+    /// Offset suggested for reading from earlier in the file, handled by [`crate::LogTool`].
+    #[serde(skip)]
+    earlier_requested: Option<u64>,
+    /// Line number and text of the note being added to a snapshot.
+    #[serde(skip)]
+    note_draft: (u64, String),
+    /// Config link being pasted, and why the last one couldn't be applied.
+    #[serde(skip)]
+    config_link_draft: (String, Option<String>),
+    #[serde(default)]
+    pub row_modifier: RowModifier,
+    #[serde(default)]
+    pub display: DisplayOptions,
+    /// Format of this specific file, None uses the default format from the settings.
+    #[serde(default)]
+    pub format: Option<FormatProfile>,
+    /// Patterns of the format last used to extract the severities.
+    #[serde(skip)]
+    applied_format: Option<FormatPatterns>,
+    /// Shared with the filtering running in the background, which keeps the lines from being
+    /// modified until it's done.
+    #[serde(skip)]
+    pub lines: Arc<Vec<String>>,
+    #[serde(skip)]
+    filter_job: Option<FilterJob>,
+    /// Byte offsets within the file of the last `line_offsets.len()` lines, the lines before
+    /// those (e.g. given to [`Self::new`]) have no known offset.
+    #[serde(skip)]
+    line_offsets: Vec<u64>,
+    /// Original length of the lines cut short by the max line length, by line number.
+    #[serde(skip)]
+    truncated_lengths: BTreeMap<u64, usize>,
+    #[serde(skip)]
+    receiver: Option<Receiver<(u64, LogFileMessage)>>,
+    #[serde(skip)]
+    sender: Option<ReaderOutput>,
+    /// Incremented whenever a reader is created, messages stamped with an earlier epoch are from
+    /// a reader since replaced and are ignored.
+    #[serde(skip)]
+    epoch: u64,
+    #[serde(skip, default)]
+    recalculate_filter_cache: bool,
+    /// Indices of the lines matching the filter.
+    #[serde(skip)]
+    filter_cache: Option<Vec<usize>>,
+    /// Index of the first line of each entry, while grouping multi-line entries.
+    #[serde(skip)]
+    entry_starts: Vec<usize>,
+    /// Entries, by the index of their first line, whose continuation lines are shown.
+    #[serde(skip)]
+    expanded_entries: HashSet<usize>,
+    /// Entries matching the filter while grouping, the rows shown are derived from these.
+    #[serde(skip)]
+    matched_entries: Vec<usize>,
+    /// Whether the entries were found with grouping enabled.
+    #[serde(skip)]
+    applied_grouping: bool,
+    /// Line number to scroll to once it has been read.
+    #[serde(skip)]
+    scroll_to_line: Option<u64>,
+    /// Number of lines read, dropped ones included, the last time the tab was focused.
+    #[serde(skip)]
+    seen_lines: Option<u64>,
+    /// Line number of the first line read while the tab wasn't focused, marked by a divider.
+    #[serde(skip)]
+    new_lines_from: Option<u64>,
+    /// Line number of the first line read after each mark and when it was made, marked by labeled
+    /// dividers.
+    #[serde(skip)]
+    marks: Vec<(u64, Instant)>,
+    /// Line number of the first line decoded using each encoding switched to part way through the
+    /// file, marked by labeled dividers.
+    #[serde(skip)]
+    encoding_switches: Vec<(u64, &'static Encoding)>,
+    /// Frame number of the last frame the tab was shown in a focused window.
+    #[serde(skip)]
+    focused_frame: u64,
+    /// Capture group to copy with "Copy matches", 0 being the whole match.
+    #[serde(skip)]
+    copy_matches_group: usize,
+    #[serde(skip)]
+    copy_matches_deduplicate: bool,
+    /// Language of the code block copied by "Copy as Markdown", e.g. `log`.
+    #[serde(skip)]
+    markdown_language: String,
+    /// Number of lines copied by "Copy as Markdown" and the number of shown lines, if not all of
+    /// them fit.
+    #[serde(skip)]
+    markdown_truncated: Option<(usize, usize)>,
+    /// Whether the view is scrolled to the end, following new lines as they're read.
+    #[serde(skip)]
+    following: bool,
+    /// Scroll to the first matching row once the filter has been recalculated.
+    #[serde(skip)]
+    scroll_to_first_match: bool,
+    /// Indices of the shown lines matched by a highlight or the quick search, and which of them.
+    #[serde(skip)]
+    match_rows: Option<(Matcher, Vec<usize>)>,
+    /// Line number of the line last navigated to using a highlight's navigation buttons or the
+    /// quick search.
+    #[serde(skip)]
+    match_position: Option<u64>,
+    /// The last quick search, navigated using `n` and `N`.
+    #[serde(skip)]
+    quick_search: Option<QuickSearch>,
+    #[serde(skip)]
+    quick_search_input: Option<QuickSearchInput>,
+    /// Whether keyboard shortcuts such as `/` apply to this file, see [`crate::TabBehaviour`].
+    #[serde(skip)]
+    pub has_keyboard: bool,
+    /// Show the lines as they are, without filtering, highlighting or display options changing
+    /// them, see [`crate::TabBehaviour`]. Nothing is cleared, so it's all back once unset.
+    #[serde(skip)]
+    pub show_raw: bool,
+    /// Line numbers of the first and last row in view.
+    #[serde(skip)]
+    visible_lines: Option<RangeInclusive<u64>>,
+    /// Column the table view is sorted by.
+    #[serde(skip)]
+    table_sort: Option<TableSort>,
+    /// The shown lines in the order they're sorted in by the table view, what they were sorted
+    /// with, and how many lines there were. Lines read since are merged in.
+    #[serde(skip)]
+    table_order: Option<(TableOptions, TableSort, Vec<usize>, usize)>,
+    /// Line number of the row in the middle of the view, and the number of rows in view above it.
+    #[serde(skip)]
+    centered_line: Option<(u64, usize)>,
+    /// The centered line when the filter changed, kept in the middle of the view once the filter
+    /// has been recalculated.
+    #[serde(skip)]
+    keep_in_view: Option<(u64, usize)>,
+    /// Wraps lines regardless of the display options, until toggled off.
+    #[serde(skip)]
+    fit_to_width: bool,
+    /// How far the rows are scrolled horizontally, from 0 to 1. None if they fit in the view.
+    #[serde(skip)]
+    horizontal_scroll: Option<f32>,
+    #[serde(skip)]
+    scroll_to_line_start: bool,
+    #[serde(skip)]
+    encoding_sample: Vec<u8>,
+    /// Encoding currently being previewed before reloading the file with it.
+    #[serde(skip)]
+    preview_encoding: Option<&'static Encoding>,
+    /// Selected entry of the encoding switcher while it's open, 0 being re-detecting the encoding
+    /// and the rest [`AVAILABLE_ENCODINGS`].
+    #[serde(skip)]
+    encoding_switcher: Option<usize>,
+    /// Search being tried out against the first lines, see [`Self::regex_sandbox_ui`].
+    #[serde(skip)]
+    regex_sandbox: Option<RegexSandbox>,
+    #[serde(skip)]
+    source_deleted: bool,
+    /// Number of lines dropped to conserve memory in unrestricted mode.
+    #[serde(skip)]
+    dropped_lines: u64,
+    /// Set once the reader has stopped, it's not restarted until the user retries.
+    #[serde(skip)]
+    reader_stopped: bool,
+    /// Whether the file is read as a stream, e.g. a FIFO or character device.
+    #[serde(skip)]
+    streaming: bool,
+    /// Why the file isn't watched for changes, if it couldn't be.
+    #[serde(skip)]
+    watch_error: Option<String>,
+    /// When the file was last modified, None until its existing lines have been read.
+    #[serde(skip)]
+    modified: Option<SystemTime>,
+    /// When lines last arrived, not counting the ones which existed when the file was opened.
+    #[serde(skip)]
+    last_data: Option<Instant>,
+    /// Whether the lines which existed when the reader started are still being read.
+    #[serde(skip)]
+    loading: bool,
+    /// How fast lines have been arriving lately, not counting the existing ones.
+    #[serde(skip)]
+    throughput: Throughput,
+    /// Frame number of the last frame the tab was shown in.
+    #[serde(skip)]
+    shown_frame: u64,
+    /// Whether the lines being received were sent while the tab was hidden, which arrive all at
+    /// once when it's shown again rather than at the rate they were sent at.
+    #[serde(skip)]
+    receiving_backlog: bool,
+    /// Keep the lines when reloading, and show what changed compared to them.
+    #[serde(skip)]
+    diff_on_reload: bool,
+    /// The lines before the last reload, while `diff_on_reload`.
+    #[serde(skip)]
+    previous_lines: Option<Arc<Vec<String>>>,
+    /// The diff of the previous lines and the lines.
+    #[serde(skip)]
+    diff: Option<Diff>,
+    /// Diffing running in the background, see [`LogFile::start_diffing`].
+    #[serde(skip)]
+    diff_job: Option<Receiver<Diff>>,
+    /// Most severe level among the visible lines.
+    #[serde(skip)]
+    max_severity: Option<Severity>,
+    #[serde(skip)]
+    pub thread: Option<JoinHandle<()>>,
+    /// While paused, messages from the reader are left in the channel until resumed.
+    #[serde(skip)]
+    paused: Arc<AtomicBool>,
+    /// Wakes the reader to check the file for new data right away, rather than waiting for the
+    /// watcher to notice it changed.
+    #[serde(skip)]
+    poll_now: Arc<Notify>,
+    /// When the file was last shown, or opened if it hasn't been shown yet. None for files
+    /// restored from the previous session until they're shown.
+    #[serde(skip)]
+    pub last_viewed: Option<Instant>,
+}
+
+impl LogFile {
+    /// Reads the file again from scratch using `encoding`. Each reader has a channel of its own,
+    /// which is dropped along with the previous reader, so whatever it still sends after the switch
+    /// (aborting it isn't immediate) is discarded rather than mixed in with the newly decoded lines.
+    pub fn reload_with_encoding(&mut self, encoding: &'static Encoding) {
+        self.encoding = Some(encoding);
+        self.sender = None;
+        self.reload();
+    }
+
+    // TODO: Change receiver type to Result<Vec<String>, ReadError>?
+    pub fn create_receiver(
+        &mut self,
+        ctx: egui::Context,
+        watcher: FileWatcher,
+        settings: &Settings,
+    ) -> (JoinHandle<()>, Receiver<(u64, LogFileMessage)>) {
+        let (sender, receiver) = channel();
+        self.epoch += 1;
+        let sender = ReaderOutput { sender, epoch: self.epoch };
+        let file_path = self.path.clone();
+
+        self.sender = Some(sender.clone());
+        self.loading = true;
+        let options = ReadOptions {
+            // Files matching an encoding rule skip the detection, unless switched to another.
+            encoding: self.encoding.or_else(|| settings.encoding_for(&self.path)),
+            fallback_encoding: settings.fallback_encoding.encoding(),
+            redetect_encoding: settings.redetect_encoding,
+            record_separator: self.record_separator,
+            max_line_length: settings.max_line_length,
+            buffer_size: settings.read_buffer_size,
+            restrict_decision: self.restrict_decision,
+            start_offset: self.scrub.map(|scrub| scrub.offset).or(self.start_offset),
+            window: self.scrub.map(|_| SCRUB_WINDOW),
+        };
+        let repaint = Repainter {
+            ctx,
+            paused: self.paused.clone(),
+            interval: Duration::from_millis(settings.repaint_interval_ms),
+        };
+
+        let archive_member = self.archive_member.clone();
+        let poll_now = self.poll_now.clone();
+
+        // TODO: Let users choose encoding.
+        let handle = tokio::spawn(async move {
+            let result = match archive_member {
+                Some(member) => tokio::task::spawn_blocking(move || {
+                    archive_reader(&file_path, &member, sender, repaint, options)
+                })
+                .await
+                .unwrap_or_else(|e| Err(Error::Other(Box::new(e)))),
+                None => reader(file_path.as_path(), sender, repaint, watcher, poll_now, options).await,
+            };
+
+            if let Err(e) = result {
+                // TODO: Actual error handling
+                error!("LogFile reader thread failed: {e:?}");
+            }
+        });
+
+        (handle, receiver)
+    }
+
+    pub fn new(path: PathBuf, items: Vec<String>, display: DisplayOptions) -> Self {
+        Self {
+            filename: path.to_string_lossy().to_string(),
+            path,
+            alias: None,
+            archive_member: None,
+            snapshot: None,
+            in_memory: None,
+            pinned_lines: Vec::new(),
+            snapshot_requested: false,
+            skipped_to: None,
+            scrub: None,
+            scrub_draft: None,
+            scrub_file_len: None,
+            earlier_requested: None,
+            note_draft: (0, String::new()),
+            config_link_draft: (String::new(), None),
+            row_modifier: RowModifier::default(),
+            display,
+            format: None,
+            applied_format: None,
+            lines: Arc::new(items),
+            filter_job: None,
+            line_offsets: Vec::new(),
+            truncated_lengths: BTreeMap::new(),
+            restrict_filesize: RestrictFileSize::default(),
+            restrict_decision: None,
+            start_offset: None,
+            record_separator: None,
+            receiver: None,
+            sender: None,
+            epoch: 0,
+            recalculate_filter_cache: false,
+            filter_cache: None,
+            entry_starts: Vec::new(),
+            expanded_entries: HashSet::new(),
+            matched_entries: Vec::new(),
+            applied_grouping: false,
+            scroll_to_line: None,
+            seen_lines: None,
+            new_lines_from: None,
+            marks: Vec::new(),
+            encoding_switches: Vec::new(),
+            focused_frame: 0,
+            copy_matches_group: 0,
+            copy_matches_deduplicate: false,
+            markdown_language: String::new(),
+            markdown_truncated: None,
+            following: false,
+            scroll_to_first_match: false,
+            match_rows: None,
+            match_position: None,
+            quick_search: None,
+            quick_search_input: None,
+            has_keyboard: false,
+            show_raw: false,
+            visible_lines: None,
+            table_sort: None,
+            table_order: None,
+            centered_line: None,
+            keep_in_view: None,
+            fit_to_width: false,
+            horizontal_scroll: None,
+            scroll_to_line_start: false,
+            max_severity: None,
+            streaming: false,
+            watch_error: None,
+            modified: None,
+            last_data: None,
+            loading: false,
+            throughput: Throughput::default(),
+            shown_frame: 0,
+            receiving_backlog: false,
+            diff_on_reload: false,
+            previous_lines: None,
+            diff: None,
+            diff_job: None,
+            source_deleted: false,
+            dropped_lines: 0,
+            reader_stopped: false,
+            encoding_sample: Vec::new(),
+            preview_encoding: None,
+            encoding_switcher: None,
+            regex_sandbox: None,
+            thread: None,
+            encoding: None,
+            encoding_detection: None,
+            errors: Vec::new(),
+            paused: Arc::default(),
+            poll_now: Arc::default(),
+            last_viewed: Some(Instant::now()),
+        }
+    }
+
+    /// Whether the lines are grouped into entries, by the format's entry start pattern or by
+    /// records.
+    fn grouped(&self) -> bool {
+        self.display.group_entries || self.record_separator.is_some()
+    }
+
+    /// Drops the `count` oldest lines from memory, keeping the filter cache in line with them.
+    fn drop_oldest_lines(&mut self, count: usize) {
+        let count = count.min(self.lines.len());
+        let without_offset = self.lines.len() - self.line_offsets.len();
+        self.line_offsets.drain(..count.saturating_sub(without_offset));
+        Arc::make_mut(&mut self.lines).drain(..count);
+        self.dropped_lines += count as u64;
+        self.truncated_lengths = self.truncated_lengths.split_off(&(self.dropped_lines + 1));
+        self.rows_changed();
+
+        if let Some(rows) = self.filter_cache.as_mut() {
+            rows.retain(|&i| i >= count);
+
+            for row in rows {
+                *row -= count;
+            }
+        }
+
+        if self.grouped() {
+            self.entry_starts.retain(|&i| i >= count);
+
+            for start in &mut self.entry_starts {
+                *start -= count;
+            }
+
+            // The remains of an entry cut short make up an entry of their own.
+            if !self.lines.is_empty() && self.entry_starts.first() != Some(&0) {
+                self.entry_starts.insert(0, 0);
+            }
+
+            self.expanded_entries = self
+                .expanded_entries
+                .iter()
+                .filter(|&&i| i >= count)
+                .map(|i| i - count)
+                .collect();
+            self.recalculate_filter_cache = true;
+        }
+    }
+
+    /// Empties the lines read so far to free up their memory, e.g. of a long running tail, while
+    /// the reader keeps adding new ones. Line numbers keep counting the cleared lines, so they still
+    /// match the file.
+    pub fn clear_buffer(&mut self) {
+        self.dropped_lines += self.lines.len() as u64;
+        // Replaced rather than drained, to also give back the capacity.
+        self.lines = Arc::default();
+        self.line_offsets = Vec::new();
+        self.truncated_lengths.clear();
+        self.filter_job = None;
+        self.filter_cache = None;
+        self.recalculate_filter_cache = true;
+        self.rows_changed();
+        self.entry_starts = Vec::new();
+        self.expanded_entries.clear();
+        self.matched_entries = Vec::new();
+        self.max_severity = None;
+        self.keep_in_view = None;
+    }
+
+    /// Shows how far through the file the view is, next to the scrollbar at the bottom right of
+    /// `rect`, going by the row at the top of the view.
+    fn position_ui(&self, ui: &egui::Ui, rect: egui::Rect, top_row: usize, row_count: usize) {
+        let Some(index) = (match self.filter_cache.as_ref() {
+            Some(rows) => rows.get(top_row).copied(),
+            None => Some(top_row),
+        }) else {
+            return;
+        };
+
+        let percentage = (top_row + 1) as f64 / row_count as f64 * 100.0;
+        let text = format!(
+            "{percentage:.0}% • line {} / {}",
+            humanreadable_count(self.line_number(index)),
+            humanreadable_count(self.line_number(self.lines.len().saturating_sub(1))),
+        );
+        let color = ui.visuals().weak_text_color();
+
+        let background = ui.painter().add(egui::Shape::Noop);
+        let text_rect = ui.painter().text(
+            rect.right_bottom() - Vec2::new(ui.spacing().scroll.bar_width + 12.0, 12.0),
+            egui::Align2::RIGHT_BOTTOM,
+            text,
+            FontId::proportional(12.0),
+            color,
+        );
+        ui.painter().set(
+            background,
+            egui::Shape::rect_filled(text_rect.expand(3.0), 3.0, ui.visuals().extreme_bg_color.gamma_multiply(0.9)),
+        );
+    }
+
+    /// Receives what the reader has sent since the last frame, unless paused or filtering.
+    fn receive_messages(&mut self, format: &FormatProfile) {
+        let Some(receiver) = &self.receiver else {
+            return;
+        };
+
+        // The lines can't be modified while they're being filtered.
+        while !self.paused.load(Ordering::Relaxed) && self.filter_job.is_none() {
+            let res = receiver.try_recv();
+
+            match res {
+                Ok((epoch, _)) if epoch != self.epoch => {
+                    debug!("Ignoring a message from a replaced reader of {}", self.filename);
+                }
+                Ok((_, msg)) => match msg {
+                    LogFileMessage::FileData(v, offsets, truncated) => {
+                        let offset = self.lines.len();
+                        let first_line_number = self.line_number(offset);
+                        self.truncated_lengths
+                            .extend(truncated.into_iter().map(|(i, length)| (first_line_number + i as u64, length)));
+
+                        if self.modified.is_some() {
+                            let now = Instant::now();
+                            self.last_data = Some(now);
+
+                            if !self.receiving_backlog {
+                                self.throughput.record(now, v.len(), v.iter().map(String::len).sum());
+                            }
+                        }
+
+                        if self.grouped() {
+                            // The last entry might continue among the new lines, so the
+                            // entries can't be filtered incrementally.
+                            self.recalculate_filter_cache = true;
+                        } else if self.filter_cache.is_none() && !self.filter_active() {
+                            // Every line is shown, there's nothing to filter.
+                            self.max_severity = self.max_severity.max(max_severity(&v, format));
+                        } else if v.len() > MAX_INCREMENTAL_FILTER_LINES {
+                            // Too many to filter without holding up the frame.
+                            self.recalculate_filter_cache = true;
+                        } else if let Some(cache) = self.filter_cache.as_mut() {
+                            if !self.row_modifier.filter.search.is_empty()
+                                && self.row_modifier.filter.filter
+                                && self.row_modifier.filter.search.is_ready()
+                            {
+                                if let Some((rows, counts)) = self.row_modifier.filter_rows(&v) {
+                                    for (total, count) in self.row_modifier.stage_counts.iter_mut().zip(counts) {
+                                        *total += count;
+                                    }
+
+                                    self.max_severity = self.max_severity.max(max_severity_of_rows(&v, &rows, format));
+                                    cache.extend(rows.into_iter().map(|i| i + offset));
+                                } else {
+                                    // Unable to incrementally fill the filter cache.
+                                    self.recalculate_filter_cache = true;
+                                }
+                            }
+                        } else {
+                            // Filtered, but the filtering hasn't finished yet.
+                            self.recalculate_filter_cache = true;
+                        }
+
+                        Arc::make_mut(&mut self.lines).extend(v);
+                        self.line_offsets.extend(offsets);
+
+                        // The matches among the new rows are added, unless all the rows are about
+                        // to change. The table order has the new lines merged in when shown.
+                        if let Some((matcher, mut rows)) = self.match_rows.take().filter(|_| !self.recalculate_filter_cache) {
+                            if let Some(new_rows) = self.rows_matching(matcher, offset) {
+                                rows.extend(new_rows);
+                                self.match_rows = Some((matcher, rows));
+                            }
+                        }
+
+                        if self.grouped() {
+                            self.entry_starts.extend(find_entry_starts(&self.lines, offset, format, self.record_separator));
+                        }
+                    },
+                    LogFileMessage::ShowRestrictFileSizeDialog(size, sender) => {
+                        self.restrict_filesize = RestrictFileSize::ShowRestrictFileSizeDialog(size, sender);
+                    },
+                    LogFileMessage::RestrictFileSize(response) => {
+                        self.restrict_filesize = if response {
+                            RestrictFileSize::RestrictedFileSize
+                        } else {
+                            RestrictFileSize::UnrestrictedFileSize
+                        };
+                    },
+                    LogFileMessage::Error(e) => {
+                        error!("Error when handling file: {e:?}");
+                        self.errors.push(e);
+                        self.loading = false;
+                    },
+                    LogFileMessage::SetEncoding(encoding, detection) => {
+                        self.encoding = Some(encoding);
+                        self.encoding_detection = Some(detection);
+                    },
+                    LogFileMessage::Streaming => {
+                        // Streams have no existing lines, they're read as they arrive.
+                        self.streaming = true;
+                        self.loading = false;
+                    },
+                    LogFileMessage::NotWatching(reason) => {
+                        self.watch_error = Some(reason);
+                    },
+                    LogFileMessage::Modified(modified) => {
+                        // Also sent for empty files, once there's nothing to read.
+                        self.modified = Some(modified);
+                        self.loading = false;
+                    },
+                    LogFileMessage::EncodingSwitched(offset, encoding) => {
+                        let without_offset = self.lines.len() - self.line_offsets.len();
+
+                        if let Ok(index) = self.line_offsets.binary_search(&offset) {
+                            let line_number = self.line_number(without_offset + index);
+                            self.encoding_switches.push((line_number, encoding));
+                        }
+                    },
+                    LogFileMessage::EncodingSample(sample) => {
+                        self.encoding_sample = sample;
+                    },
+                    LogFileMessage::SourceDeleted => {
+                        self.source_deleted = true;
+                    },
+                    LogFileMessage::SourceRestored => {
+                        self.source_deleted = false;
+                        // The new file is read from its start.
+                        self.skipped_to = None;
+                    },
+                    LogFileMessage::SkippedTo(offset, size) => {
+                        self.skipped_to = Some((offset, size));
+                    },
+                },
+                Err(e) => {
+                    match e {
+                        TryRecvError::Empty => (),
+                        TryRecvError::Disconnected => {
+                            // The reader has stopped, e.g. due to the file not existing.
+                            // Don't restart it every frame, let the user retry instead.
+                            self.receiver = None;
+                            self.reader_stopped = true;
+                        }
+                    };
+
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Filters all lines in the background, replacing the filtering already running if any. New
+    /// lines wait to be received until it's done.
+    fn start_filtering(&mut self, ctx: egui::Context, format: FormatProfile) {
+        let control = Arc::new(FilterControl::default());
+        let (sender, receiver) = channel();
+        let grouped = self.grouped();
+        let starts = grouped.then(|| self.entry_starts.clone());
+        let lines = self.lines.clone();
+        let row_modifier = self.row_modifier.clone();
+        let filter_active = self.filter_active();
+
+        self.filter_job = Some(FilterJob {
+            control: control.clone(),
+            total: if grouped { self.entry_starts.len() } else { self.lines.len() },
+            receiver,
+        });
+
+        rayon::spawn(move || {
+            let outcome = match starts {
+                Some(starts) => {
+                    let (entries, stage_counts) = filter_entries(&row_modifier, &lines, &starts, &control);
+                    let first_lines: Vec<usize> = entries.iter().map(|&e| starts[e]).collect();
+
+                    FilterOutcome {
+                        max_severity: max_severity_of_rows(&lines, &first_lines, &format),
+                        rows: Some(entries),
+                        grouped,
+                        stage_counts,
+                    }
+                }
+                None => {
+                    let (rows, stage_counts) = if filter_active {
+                        row_modifier
+                            .filter_items_controlled(lines.len(), |i| Cow::Borrowed(&lines[i]), &control)
+                            .unzip()
+                    } else {
+                        (None, None)
+                    };
+
+                    FilterOutcome {
+                        max_severity: match rows.as_ref() {
+                            Some(rows) => max_severity_of_rows(&lines, rows, &format),
+                            None => max_severity(&lines, &format),
+                        },
+                        rows,
+                        grouped,
+                        stage_counts: stage_counts.unwrap_or_default(),
+                    }
+                }
+            };
+
+            // Released before the outcome is received, otherwise adding the next lines would copy
+            // all of them.
+            drop(lines);
+
+            // A cancelled job's receiver is gone along with the job.
+            if !control.is_cancelled() && sender.send(outcome).is_ok() {
+                ctx.request_repaint();
+            }
+        });
+    }
+
+    /// Starts filtering if anything changed and applies the outcome of the filtering once it's
+    /// done, also while the tab isn't shown. Returns true once the rows are up to date.
+    pub fn update_filtering(&mut self, ctx: &egui::Context, default_format: &FormatProfile) -> bool {
+        if self.recalculate_filter_cache {
+            self.recalculate_filter_cache = false;
+            let format = self.format.clone().unwrap_or_else(|| default_format.clone());
+            self.start_filtering(ctx.clone(), format);
+        }
+
+        self.finish_filtering();
+        self.filter_job.is_none()
+    }
+
+    /// Applies the outcome of the filtering running in the background, once it's done.
+    fn finish_filtering(&mut self) {
+        let outcome = match self.filter_job.as_ref().map(|job| job.receiver.try_recv()) {
+            Some(Ok(outcome)) => outcome,
+            Some(Err(TryRecvError::Disconnected)) => {
+                error!("Filtering {} stopped without a result", self.filename);
+                self.filter_job = None;
+                return;
+            }
+            _ => return,
+        };
+
+        self.apply_filter_outcome(outcome);
+    }
+
+    /// Shows the rows filtered in the background.
+    fn apply_filter_outcome(&mut self, outcome: FilterOutcome) {
+        self.filter_job = None;
+        self.rows_changed();
+
+        self.filter_cache = if outcome.grouped {
+            let entries = outcome.rows.unwrap_or_default();
+            let rows = self.entry_rows(&entries);
+            self.matched_entries = entries;
+            Some(rows)
+        } else {
+            outcome.rows
+        };
+        self.row_modifier.stage_counts = outcome.stage_counts;
+        self.max_severity = outcome.max_severity;
+
+        let keep_in_view = self.keep_in_view.take();
+
+        if std::mem::take(&mut self.scroll_to_first_match) {
+            if let Some(&first) = self.filter_cache.as_ref().and_then(|rows| rows.first()) {
+                self.scroll_to_line = Some(self.line_number(first));
+            }
+        } else if let Some((line_number, rows_above)) = keep_in_view {
+            if let Some(row) = self.nearest_row(line_number) {
+                let top = row.saturating_sub(rows_above);
+                let index = match self.filter_cache.as_ref() {
+                    Some(rows) => rows[top],
+                    None => top,
+                };
+
+                self.scroll_to_line = Some(self.line_number(index));
+            }
+        }
+    }
+
+    /// The row showing the line with the given line number, or the closest shown line if it's
+    /// filtered out. None if no lines are shown.
+    fn nearest_row(&self, line_number: u64) -> Option<usize> {
+        let index = line_number.saturating_sub(self.dropped_lines + 1) as usize;
+
+        match self.filter_cache.as_ref() {
+            Some(rows) => {
+                let after = rows.partition_point(|&i| i < index);
+
+                match (after.checked_sub(1), rows.get(after)) {
+                    (Some(before), Some(&next)) if index - rows[before] > next - index => Some(after),
+                    (Some(before), _) => Some(before),
+                    (None, Some(_)) => Some(after),
+                    (None, None) => None,
+                }
+            }
+            None => self.lines.len().checked_sub(1).map(|last| index.min(last)),
+        }
+    }
+
+    fn filter_active(&self) -> bool {
+        !self.row_modifier.filter.search.is_empty() && self.row_modifier.filter.filter
+    }
+
+    /// The lines making up an entry.
+    fn entry_range(&self, entry: usize) -> Range<usize> {
+        let end = self
+            .entry_starts
+            .get(entry + 1)
+            .copied()
+            .unwrap_or(self.lines.len());
+
+        self.entry_starts[entry]..end
+    }
+
+    /// Shows the rows as a table of the delimited fields of each line, rather than as text.
+    fn table_ui(&mut self, ui: &mut egui::Ui, options: &TableOptions, font_id: &FontId, row_height: f32, scroll_to_row: Option<usize>) {
+        use egui_extras::{Column, TableBuilder};
+
+        // The header is left out of the rows while it's in memory.
+        let header = (options.header && self.dropped_lines == 0)
+            .then(|| self.lines.first())
+            .flatten()
+            .filter(|_| self.filter_cache.as_ref().is_none_or(|rows| rows.first() == Some(&0)));
+        let skip = usize::from(header.is_some());
+        let row_count = self.filter_cache.as_ref().map_or(self.lines.len(), Vec::len) - skip;
+
+        let stale = match (&self.table_order, self.table_sort) {
+            (Some((sorted_options, sorted_by, _, _)), Some(sort)) => sorted_options != options || *sorted_by != sort,
+            (order, sort) => order.is_some() != sort.is_some(),
+        };
+
+        // The rows of the lines from `from` on, the header aside.
+        let rows_from = |from: usize| -> Vec<usize> {
+            match self.filter_cache.as_ref() {
+                Some(rows) => rows[skip.max(rows.partition_point(|&i| i < from))..].to_vec(),
+                None => (skip.max(from)..self.lines.len()).collect(),
+            }
+        };
+
+        if stale {
+            self.table_order = self.table_sort.map(|sort| {
+                let mut rows = rows_from(0);
+                sort.sort(&self.lines, &mut rows, options.delimiter);
+
+                (options.clone(), sort, rows, self.lines.len())
+            });
+        } else if let Some((_, sort, rows, sorted_lines)) = self.table_order.as_mut() {
+            if *sorted_lines < self.lines.len() {
+                sort.merge(&self.lines, rows, rows_from(*sorted_lines), options.delimiter);
+                *sorted_lines = self.lines.len();
+            }
+        }
+
+        let index_at = |row: usize| match (&self.table_order, self.filter_cache.as_ref()) {
+            (Some((_, _, order, _)), _) => order[row],
+            (None, Some(rows)) => rows[row + skip],
+            (None, None) => row + skip,
+        };
+
+        // Going by the header, or the widest of the first rows.
+        let header_fields = header.map(|line| split_fields(line, options.delimiter));
+        let columns = match header_fields.as_ref() {
+            Some(fields) => fields.len(),
+            None => (0..row_count.min(100))
+                .map(|row| split_fields(&self.lines[index_at(row)], options.delimiter).len())
+                .max()
+                .unwrap_or(1),
+        };
+
+        let mut sort_clicked = None;
+        let mut builder = TableBuilder::new(ui)
+            .striped(true)
+            .resizable(true)
+            .auto_shrink([false, true])
+            .stick_to_bottom(self.table_sort.is_none() && scroll_to_row.is_none());
+
+        if let Some(row) = scroll_to_row.filter(|_| self.table_sort.is_none()) {
+            builder = builder.scroll_to_row(row.saturating_sub(skip), Some(egui::Align::TOP));
+        }
+
+        if self.display.line_numbers {
+            builder = builder.column(Column::auto());
+        }
+
+        builder
+            .columns(Column::auto().at_least(32.0).clip(true), columns)
+            .header(row_height, |mut row| {
+                if self.display.line_numbers {
+                    row.col(|ui| {
+                        ui.label("#");
+                    });
+                }
+
+                for column in 0..columns {
+                    row.col(|ui| {
+                        let name = match header_fields.as_ref() {
+                            Some(fields) => fields[column].to_string(),
+                            None => format!("{}", column + 1),
+                        };
+                        let arrow = match self.table_sort {
+                            Some(sort) if sort.column == column && sort.descending => " ⏷",
+                            Some(sort) if sort.column == column => " ⏶",
+                            _ => "",
+                        };
+
+                        if ui.button(format!("{name}{arrow}")).clicked() {
+                            sort_clicked = Some(column);
+                        }
+                    });
+                }
+            })
+            .body(|body| {
+                body.rows(row_height, row_count, |mut row| {
+                    let index = index_at(row.index());
+                    let fields = split_fields(&self.lines[index], options.delimiter);
+
+                    if self.display.line_numbers {
+                        row.col(|ui| {
+                            ui.label(RichText::new(self.line_number(index).to_string()).font(font_id.clone()).weak());
+                        });
+                    }
+
+                    for column in 0..columns {
+                        row.col(|ui| {
+                            if let Some(field) = fields.get(column) {
+                                ui.add(Label::new(RichText::new(field.as_ref()).font(font_id.clone())).truncate());
+                            }
+                        });
+                    }
+                });
+            });
+
+        if let Some(column) = sort_clicked {
+            self.table_sort = TableSort::toggle(self.table_sort, column);
+        }
+    }
+
+    /// Diffs the lines before the last reload and the lines since in the background.
+    fn start_diffing(&mut self, ctx: egui::Context, previous: Arc<Vec<String>>) {
+        let (sender, receiver) = channel();
+        let lines = self.lines.clone();
+        self.diff_job = Some(receiver);
+
+        rayon::spawn(move || {
+            let changes = diff_lines(&previous, &lines);
+            let diff = Diff {
+                lines: lines.len(),
+                added: changes.iter().filter(|c| matches!(c, Change::Added(_))).count(),
+                removed: changes.iter().filter(|c| matches!(c, Change::Removed(_))).count(),
+                changes,
+            };
+            // Released before the diff is received, see `start_filtering`.
+            drop(lines);
+
+            // A replaced job's receiver is gone.
+            if sender.send(diff).is_ok() {
+                ctx.request_repaint();
+            }
+        });
+    }
+
+    /// The lines before the last reload compared to the lines since, the added ones in green and
+    /// the removed ones in red. Diffed once the file has been read, and again as lines arrive.
+    fn diff_ui(&mut self, ui: &mut egui::Ui, font_id: &FontId, row_height: f32) {
+        let Some(previous) = self.previous_lines.clone() else {
+            return;
+        };
+
+        if let Some(Ok(diff)) = self.diff_job.as_ref().map(Receiver::try_recv) {
+            self.diff = Some(diff);
+            self.diff_job = None;
+        }
+
+        if !self.loading
+            && self.diff_job.is_none()
+            && self.diff.as_ref().is_none_or(|diff| diff.lines != self.lines.len())
+        {
+            self.start_diffing(ui.ctx().clone(), previous.clone());
+        }
+
+        let Some(changes) = self.diff.as_ref().map(|diff| &diff.changes) else {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label("Diffing…");
+            });
+            return;
+        };
+
+        ScrollArea::both()
+            .id_source("diff_scroll")
+            .auto_shrink([false, false])
+            .show_rows(ui, row_height, changes.len(), |ui, row_range| {
+                for change in &changes[row_range] {
+                    let (prefix, line, color) = match *change {
+                        Change::Kept(i) => ("  ", &self.lines[i], ui.visuals().text_color()),
+                        Change::Added(i) => ("+ ", &self.lines[i], DIFF_ADDED_COLOR),
+                        Change::Removed(i) => ("- ", &previous[i], DIFF_REMOVED_COLOR),
+                    };
+                    let text = format!("{prefix}{}", line.trim_end_matches(['\n', '\r']));
+
+                    Label::new(RichText::new(text).font(font_id.clone()).color(color))
+                        .extend()
+                        .ui(ui);
+                }
+            });
+    }
+
+    /// All the lines without any styling, bypassing the filter.
+    fn raw_ui(&mut self, ui: &mut egui::Ui, font_id: &FontId, row_height: f32) {
+        let base = TextFormat {
+            font_id: font_id.clone(),
+            ..Default::default()
+        };
+
+        ScrollArea::both()
+            .id_source("raw_scroll")
+            .auto_shrink([false, false])
+            .stick_to_bottom(true)
+            .show_rows(ui, row_height, self.lines.len(), |ui, row_range| {
+                for line in &self.lines[row_range] {
+                    let text = line.trim_end_matches(['\n', '\r']).to_owned();
+                    Line::new(text, base.clone()).ui(ui, Wrap::None);
+                }
+            });
+    }
+
+    /// Forgets what was derived from the shown rows, after they've changed.
+    fn rows_changed(&mut self) {
+        self.match_rows = None;
+        self.table_order = None;
+    }
+
+    /// The rows shown for `entries`, the first line of each and the rest of the expanded ones.
+    fn entry_rows(&self, entries: &[usize]) -> Vec<usize> {
+        let mut rows = Vec::with_capacity(entries.len());
+
+        for &entry in entries {
+            let range = self.entry_range(entry);
+
+            if self.expanded_entries.contains(&range.start) {
+                rows.extend(range);
+            } else {
+                rows.push(range.start);
+            }
+        }
+
+        rows
+    }
+
+    /// Reads the file again from scratch after the reader has stopped.
+    pub fn retry(&mut self) {
+        self.filter_job = None;
+        self.lines = Arc::default();
+        self.line_offsets.clear();
+        self.truncated_lengths.clear();
+        self.errors.clear();
+        self.filter_cache = None;
+        self.rows_changed();
+        self.entry_starts.clear();
+        self.expanded_entries.clear();
+        self.dropped_lines = 0;
+        self.seen_lines = None;
+        self.new_lines_from = None;
+        self.marks.clear();
+        self.encoding_switches.clear();
+        self.keep_in_view = None;
+        self.reader_stopped = false;
+        self.watch_error = None;
+        self.modified = None;
+        self.last_data = None;
+        self.throughput = Throughput::default();
+        self.skipped_to = None;
+    }
+
+    /// Stops the reader and reads the file again from scratch, keeping the filters, highlights and
+    /// encoding.
+    pub fn reload(&mut self) {
+        if let Some(thread) = self.thread.take() {
+            thread.abort();
+        }
+
+        if self.diff_on_reload {
+            self.previous_lines = Some(self.lines.clone());
+            self.diff = None;
+            self.diff_job = None;
+        }
+
+        self.receiver = None;
+        self.retry();
+    }
+
+    /// Shows `member` of the archive at `path` rather than `path` itself.
+    pub fn with_archive_member(mut self, member: String) -> Self {
+        self.filename = format!("{}/{member}", self.path.display());
+        self.archive_member = Some(member);
+        self
+    }
+
+    /// Moves the file to `path`, e.g. once resolved within the log root. Takes effect when the
+    /// file is next read.
+    pub fn set_path(&mut self, path: PathBuf) {
+        self.filename = match self.archive_member.as_ref() {
+            Some(member) => format!("{}/{member}", path.display()),
+            None => path.to_string_lossy().to_string(),
+        };
+        self.path = path;
+    }
+
+    /// Starts reading the file at the first line starting at or after `offset`.
+    pub fn with_start_offset(mut self, offset: u64) -> Self {
+        self.start_offset = Some(offset).filter(|&offset| offset > 0);
+        self
+    }
+
+    pub fn with_filter(mut self, filter: Filter) -> Self {
+        self.set_filter(filter);
+        self
+    }
+
+    pub fn set_filter(&mut self, filter: Filter) {
+        self.row_modifier.filter = filter;
+        self.row_modifier.filter.rebuild_regexes();
+        self.recalculate_filter_cache = true;
+    }
+
+    pub fn add_highlight(&mut self, mut highlight: RowHighlight) {
+        highlight.search.rebuild_regex();
+        self.row_modifier.row_highlights.push(highlight);
+    }
+
+    pub fn set_highlights(&mut self, highlights: Vec<RowHighlight>) {
+        self.row_modifier.row_highlights = highlights;
+        self.row_modifier.rebuild_regexes();
+    }
+
+    /// The lines matching the filter, the whole of each matching entry when grouped. Goes by the
+    /// last filtering, see [`Self::update_filtering`].
+    pub fn filtered_lines(&self) -> Vec<String> {
+        if self.grouped() {
+            return self
+                .matched_entries
+                .iter()
+                .flat_map(|&entry| self.entry_range(entry))
+                .map(|i| self.lines[i].clone())
+                .collect();
+        }
+
+        match self.filter_cache.as_ref() {
+            Some(rows) => rows.iter().map(|&i| self.lines[i].clone()).collect(),
+            None => self.lines.to_vec(),
+        }
+    }
+
+    /// Number of lines (or entries, when grouped) matching the filter, all of them while it isn't
+    /// active, and number of lines read. Goes by the last filtering, see
+    /// [`Self::update_filtering`].
+    pub fn count_matches(&self) -> (usize, usize) {
+        let matches = if self.grouped() {
+            self.matched_entries.len()
+        } else {
+            self.filter_cache.as_ref().map_or(self.lines.len(), Vec::len)
+        };
+
+        (matches, self.lines.len())
+    }
+
+    /// Pins the line with `line_number` above the rows, or unpins it if it's pinned.
+    pub fn toggle_pin(&mut self, line_number: u64) {
+        if let Some(index) = self.pinned_lines.iter().position(|&pinned| pinned == line_number) {
+            self.pinned_lines.remove(index);
+        } else {
+            self.pinned_lines.push(line_number);
+            self.pinned_lines.sort_unstable();
+        }
+    }
+
+    /// Marks the current end of the file, e.g. right before reproducing an issue, with a divider
+    /// shown above the lines read after it.
+    pub fn mark_now(&mut self) {
+        let next_line = self.dropped_lines + self.lines.len() as u64 + 1;
+
+        if self.marks.last().map(|(line, _)| *line) != Some(next_line) {
+            self.marks.push((next_line, Instant::now()));
+        }
+    }
+
+    pub fn clear_marks(&mut self) {
+        self.marks.clear();
+    }
+
+    /// The line number within the file of `self.lines[index]`, counting lines dropped from memory.
+    pub fn line_number(&self, index: usize) -> u64 {
+        self.dropped_lines + index as u64 + 1
+    }
+
+    /// The byte offset within the file of `self.lines[index]`, if known.
+    pub fn byte_offset(&self, index: usize) -> Option<u64> {
+        let without_offset = self.lines.len() - self.line_offsets.len();
+        self.line_offsets
+            .get(index.checked_sub(without_offset)?)
+            .copied()
+    }
+
+    /// Scrolls to the next or previous shown line matched by the highlight at `index`, wrapping
+    /// around at the ends. Navigates from the line last navigated to while it's in view, from
+    /// the top of the view otherwise.
+    fn jump_to_highlight(&mut self, index: usize, jump: Jump) {
+        self.jump_to_match(Matcher::Highlight(index), jump);
+    }
+
+    fn jump_to_match(&mut self, matcher: Matcher, jump: Jump) -> bool {
+        let Some(visible) = self.visible_lines.clone() else {
+            return false;
+        };
+
+        let current = self
+            .match_position
+            .filter(|p| visible.contains(p))
+            .unwrap_or(*visible.start());
+
+        self.jump_to_match_from(matcher, jump, current)
+    }
+
+    /// The number of the line last navigated to among the lines matched by the filter, if it's
+    /// one of them, and the number of such lines. None until navigating between them.
+    fn filter_match_position(&self) -> Option<(Option<usize>, usize)> {
+        let Some((Matcher::Filter, rows)) = self.match_rows.as_ref() else {
+            return None;
+        };
+
+        // The rows are in the order of the lines.
+        let current = self
+            .match_position
+            .and_then(|position| position.checked_sub(self.dropped_lines + 1))
+            .and_then(|index| rows.binary_search(&(index as usize)).ok())
+            .map(|p| p + 1);
+
+        Some((current, rows.len()))
+    }
+
+    /// The shown rows of the lines from `self.lines[from]` on matched by `matcher`. None if there's
+    /// nothing to match with.
+    fn rows_matching(&self, matcher: Matcher, from: usize) -> Option<Vec<usize>> {
+        let search = match matcher {
+            Matcher::Highlight(index) => self.row_modifier.row_highlights.get(index).filter(|h| !h.disabled).map(|h| &h.search),
+            Matcher::QuickSearch => self.quick_search.as_ref().map(|q| &q.search),
+            Matcher::Filter => Some(&self.row_modifier.filter.search),
+        };
+        let search = search.filter(|s| s.is_ready())?;
+
+        let lines = &self.lines;
+        let row_modifier = &self.row_modifier;
+        let is_highlight = matches!(matcher, Matcher::Highlight(_));
+        let matches = |&i: &usize| {
+            if matcher == Matcher::Filter {
+                return row_modifier.is_match(&row_modifier.filter, &lines[i]);
+            }
+
+            let text = row_modifier.rewrite(&lines[i]);
+            search.is_match(&text) && (!is_highlight || row_modifier.highlights_apply(&text))
+        };
+
+        Some(match self.filter_cache.as_ref() {
+            Some(rows) => rows[rows.partition_point(|&i| i < from)..].par_iter().copied().filter(matches).collect(),
+            None => (from..lines.len()).into_par_iter().filter(matches).collect(),
+        })
+    }
+
+    /// Scrolls to the next or previous shown line matched by `matcher` after or before the line
+    /// number `current`, wrapping around at the ends. False if no shown line is matched.
+    fn jump_to_match_from(&mut self, matcher: Matcher, jump: Jump, current: u64) -> bool {
+        // The rows are about to change.
+        if self.filter_job.is_some() {
+            return false;
+        }
+
+        if self.match_rows.as_ref().map(|(m, _)| *m) != Some(matcher) {
+            let Some(rows) = self.rows_matching(matcher, 0) else {
+                return false;
+            };
+
+            self.match_rows = Some((matcher, rows));
+        }
+
+        let Some((_, rows)) = self.match_rows.as_ref() else {
+            return false;
+        };
+
+        let target = match jump {
+            Jump::Next => rows
+                .get(rows.partition_point(|&i| self.line_number(i) <= current))
+                .or(rows.first()),
+            Jump::Previous => rows
+                .partition_point(|&i| self.line_number(i) < current)
+                .checked_sub(1)
+                .map(|p| &rows[p])
+                .or(rows.last()),
+        };
+
+        match target {
+            Some(&i) => {
+                let line_number = self.line_number(i);
+                self.match_position = Some(line_number);
+                self.scroll_to_line(line_number);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Handles Ctrl+Shift+F toggling the filter on and off, without clearing its search.
+    fn filter_toggle_key(&mut self, ui: &mut egui::Ui) {
+        if self.has_keyboard && ui.input_mut(|i| i.consume_key(Modifiers::CTRL | Modifiers::SHIFT, Key::F)) {
+            self.row_modifier.filter.toggle();
+        }
+    }
+
+    /// Handles `/` and `?` opening the quick search, and `n` and `N` navigating its matches, while
+    /// no text field has focus.
+    fn quick_search_keys(&mut self, ui: &mut egui::Ui) {
+        if !self.has_keyboard || self.quick_search_input.is_some() || ui.memory(|m| m.focused().is_some()) {
+            return;
+        }
+
+        // Going by the text typed rather than the keys pressed, which depend on the layout.
+        let typed = ui.input_mut(|i| {
+            let index = i.events.iter().position(|e| matches!(e, egui::Event::Text(t) if ["/", "?", "n", "N"].contains(&t.as_str())))?;
+
+            match i.events.remove(index) {
+                egui::Event::Text(text) => Some(text),
+                _ => None,
+            }
+        });
+
+        match typed.as_deref() {
+            Some(key @ ("/" | "?")) => {
+                let direction = if key == "/" { Jump::Next } else { Jump::Previous };
+                let origin = self.visible_lines.as_ref().map_or(0, |visible| *visible.start());
+
+                self.quick_search_input = Some(QuickSearchInput {
+                    text: String::new(),
+                    direction,
+                    origin,
+                    found: true,
+                    focused: false,
+                });
+            }
+            Some(key @ ("n" | "N")) => {
+                if let Some(quick_search) = self.quick_search.as_ref() {
+                    let jump = if key == "n" { quick_search.direction } else { quick_search.direction.reverse() };
+                    self.jump_to_match(Matcher::QuickSearch, jump);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// The input of the quick search, searching as the text changes.
+    fn quick_search_ui(&mut self, ui: &mut egui::Ui, rect: Rect) {
+        let Some(mut input) = self.quick_search_input.take() else {
+            return;
+        };
+
+        let mut close = false;
+        let mut changed = false;
+
+        egui::Area::new(ui.id().with("quick_search"))
+            .pivot(egui::Align2::RIGHT_TOP)
+            .fixed_pos(rect.right_top() + Vec2::new(-16.0, 8.0))
+            .show(ui.ctx(), |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label(if input.direction == Jump::Next { "/" } else { "?" });
+
+                        let response = ui.add(
+                            egui::TextEdit::singleline(&mut input.text)
+                                .hint_text("Regex, Enter to close")
+                                .desired_width(240.0),
+                        );
+
+                        if !std::mem::replace(&mut input.focused, true) {
+                            response.request_focus();
+                        }
+
+                        changed = response.changed();
+                        close = response.lost_focus();
+
+                        if !input.found && !input.text.is_empty() {
+                            ui.colored_label(ui.visuals().error_fg_color, "Pattern not found");
+                        }
+                    });
+
+                    ui.label("n and N go to the next and previous match");
+                });
+            });
+
+        if changed {
+            let search = Search::new(input.text.clone(), SearchMode::Regex, false);
+
+            self.quick_search = (!search.is_empty()).then_some(QuickSearch {
+                search,
+                direction: input.direction,
+            });
+            self.match_rows = None;
+            input.found = self.jump_to_match_from(Matcher::QuickSearch, input.direction, input.origin);
+        }
+
+        if !close {
+            self.quick_search_input = Some(input);
+        }
+    }
+
+    /// Scrolls to the line with the given line number once it has been read. Shows the closest
+    /// line after it if it's filtered out, or the last line if the file is shorter.
+    pub fn scroll_to_line(&mut self, line_number: u64) {
+        self.scroll_to_line = Some(line_number);
+    }
+
+    /// The shown lines as a Markdown code block, see [`markdown_code_block`], with the number of
+    /// lines which fit in it and the number of shown lines.
+    pub fn shown_lines_markdown(&self, language: &str) -> (String, usize, usize) {
+        let (block, copied) = match self.filter_cache.as_ref() {
+            Some(rows) => markdown_code_block(rows.iter().filter_map(|&i| self.lines.get(i)).map(String::as_str), language, MAX_MARKDOWN_COPY_BYTES),
+            None => markdown_code_block(self.lines.iter().map(String::as_str), language, MAX_MARKDOWN_COPY_BYTES),
+        };
+        let shown = self.filter_cache.as_ref().map_or(self.lines.len(), Vec::len);
+
+        (block, copied, shown)
+    }
+
+    /// The text matched by the filter in each shown line, one match per line, see
+    /// [`Filter::matched_texts`].
+    pub fn matched_texts(&self, group: usize, deduplicate: bool) -> String {
+        let filter = &self.row_modifier.filter;
+        let rows: Box<dyn Iterator<Item = &String>> = match self.filter_cache.as_ref() {
+            Some(rows) => Box::new(rows.iter().filter_map(|&i| self.lines.get(i))),
+            None => Box::new(self.lines.iter()),
+        };
+
+        let mut seen = HashSet::new();
+        let mut output = String::new();
+
+        for text in rows.flat_map(|line| filter.matched_texts(line, group)) {
+            if deduplicate && !seen.insert(text) {
+                continue;
+            }
+
+            output.push_str(text);
+            output.push('\n');
+        }
+
+        output
+    }
+
+    /// A reference to the line at `index`, e.g. `app.log:14233`, which can be given as an
+    /// argument to open the file at that line.
+    pub fn line_reference(&self, index: usize) -> String {
+        format!("{}:{}", self.path.display(), self.line_number(index))
+    }
+
+    /// The encoding the file is read with, if it was detected rather than given.
+    pub fn detected_encoding(&self) -> Option<&'static Encoding> {
+        self.encoding
+            .filter(|_| self.encoding_detection.is_some_and(|d| d.is_detected()))
+    }
+
+    /// How the encoding the file is read with was arrived at, None until the file is opened.
+    pub fn encoding_detection(&self) -> Option<EncodingDetection> {
+        self.encoding_detection
+    }
+
+    /// Whether the existing lines of the file are still being read.
+    pub fn is_loading(&self) -> bool {
+        self.loading && !self.reader_stopped
+    }
+
+    /// The alias if there is one, otherwise the filename.
+    pub fn title(&self) -> &str {
+        self.alias.as_deref().unwrap_or(&self.filename)
+    }
+
+    /// A tab of its own with the lines read so far, which doesn't follow the file and can be
+    /// annotated. Keeps the filter, highlights and display options.
+    pub fn snapshot(&self) -> Self {
+        let taken = SystemTime::now();
+        let mut snapshot = Self::new(self.path.clone(), Vec::new(), self.display.clone());
+
+        snapshot.filename = self.filename.clone();
+        snapshot.alias = Some(format!("{} (snapshot @ {})", self.title(), clock_time(taken)));
+        snapshot.archive_member = self.archive_member.clone();
+        snapshot.encoding = self.encoding;
+        snapshot.record_separator = self.record_separator;
+        snapshot.row_modifier = self.row_modifier.clone();
+        snapshot.format = self.format.clone();
+        snapshot.snapshot = Some(Snapshot {
+            taken,
+            lines: self.lines.clone(),
+            dropped_lines: self.dropped_lines,
+            notes: Vec::new(),
+        });
+
+        snapshot
+    }
+
+    /// A file of `lines` which aren't read from anywhere, e.g. to render and filter a fixed set of
+    /// lines in tests or when embedding. Retrying shows the lines again rather than reading `name`,
+    /// and it's left out of saved workspaces.
+    pub fn from_lines(name: impl Into<String>, lines: Vec<String>) -> Self {
+        let lines = Arc::new(lines);
+        let mut file = Self::new(PathBuf::from(name.into()), Vec::new(), DisplayOptions::default());
+
+        file.lines = lines.clone();
+        file.in_memory = Some(lines);
+        file.reader_stopped = true;
+
+        file
+    }
+
+    /// Whether the lines were given rather than read, see [`Self::from_lines`].
+    pub fn is_in_memory(&self) -> bool {
+        self.in_memory.is_some()
+    }
+
+    /// Whether the snapshot button was clicked since the last call.
+    pub fn take_snapshot_request(&mut self) -> bool {
+        std::mem::take(&mut self.snapshot_requested)
+    }
+
+    /// The offset to suggest reading from, if the button to read from earlier in the file was
+    /// clicked since the last call.
+    pub fn take_earlier_request(&mut self) -> Option<u64> {
+        self.earlier_requested.take()
+    }
+
+    pub fn max_severity(&self) -> Option<Severity> {
+        self.max_severity
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    /// Makes the reader check the file for new data now, e.g. if the watcher misses changes to
+    /// files on network shares.
+    pub fn poll_now(&self) {
+        self.poll_now.notify_one();
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused.store(paused, Ordering::Relaxed);
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui, watcher: &FileWatcher, settings: &Settings) {
+        self.last_viewed = Some(Instant::now());
+        self.row_modifier.match_style = settings.match_style;
+        let frame = ui.ctx().frame_nr();
+        self.receiving_backlog = self.shown_frame + 1 < frame;
+        self.shown_frame = frame;
+        let rect = ui.max_rect();
+        self.quick_search_keys(ui);
+
+        let format = self
+            .format
+            .clone()
+            .unwrap_or_else(|| settings.default_format.clone());
+
+        if self.applied_format.as_ref() != Some(&format.patterns)
+            || self.applied_grouping != self.grouped()
+        {
+            self.applied_format = Some(format.patterns.clone());
+            self.applied_grouping = self.grouped();
+
+            self.entry_starts.clear();
+            self.expanded_entries.clear();
+
+            if self.grouped() {
+                self.entry_starts = find_entry_starts(&self.lines, 0, &format, self.record_separator);
+            }
+
+            self.recalculate_filter_cache = true;
+        }
+
+        if self.receiver.is_some() {
+            self.receive_messages(&format);
+
+            // Archive members are read once without being followed, so they're never modified.
+            if self.thread.as_ref().is_none_or(JoinHandle::is_finished) {
+                self.loading = false;
+            }
+        } else if let Some(snapshot) = self.snapshot.as_ref().filter(|_| !self.reader_stopped) {
+            // Shown as is rather than read, as if the reader had stopped.
+            self.lines = snapshot.lines.clone();
+            self.dropped_lines = snapshot.dropped_lines;
+            self.reader_stopped = true;
+            self.row_modifier.rebuild_regexes();
+            self.recalculate_filter_cache = true;
+        } else if let Some(lines) = self.in_memory.as_ref().filter(|_| !self.reader_stopped) {
+            // Never read, shown again after retrying.
+            self.lines = lines.clone();
+            self.reader_stopped = true;
+            self.recalculate_filter_cache = true;
+        } else if !self.reader_stopped {
+            let (thread, receiver) = self.create_receiver(ui.ctx().clone(), watcher.clone(), settings);
+            self.thread = Some(thread);
+            self.receiver = Some(receiver);
+            // Restored filters and highlights only have their strings, not their regexes.
+            self.row_modifier.rebuild_regexes();
+            self.recalculate_filter_cache = true;
+        }
+
+        // Divides the lines read while the tab was hidden or the window unfocused from the rest.
+        // Lines are only received while the tab is shown, the ones sent while it was hidden have
+        // been received above.
+        if ui.input(|i| i.focused) {
+            let frame = ui.ctx().frame_nr();
+            let read_lines = self.dropped_lines + self.lines.len() as u64;
+
+            if self.focused_frame + 1 < frame {
+                self.new_lines_from = self
+                    .seen_lines
+                    .filter(|&seen| seen < read_lines)
+                    .map(|seen| seen + 1);
+            }
+
+            self.focused_frame = frame;
+            self.seen_lines = Some(read_lines);
+        }
+
+        match self.restrict_filesize.clone() {
+            RestrictFileSize::Initializing => (),
+            RestrictFileSize::UnrestrictedFileSize => {
+                let max_rows = settings.max_unrestricted_rows.max(1);
+
+                if self.lines.len() > max_rows {
+                    // Drop a bit more than needed, so the filter cache isn't recalculated for
+                    // every new batch of lines.
+                    let excess = (self.lines.len() - max_rows + max_rows / 10).min(self.lines.len());
+                    self.drop_oldest_lines(excess);
+                    self.recalculate_filter_cache = true;
+                }
+            }
+            RestrictFileSize::RestrictedFileSize => {
+                if self.lines.len() > MAX_ROWS as usize {
+                    self.drop_oldest_lines(self.lines.len() - MAX_ROWS as usize);
+                }
+            }
+            RestrictFileSize::ShowRestrictFileSizeDialog(size, sender) => {
+                egui::Window::new("Large File")
+                    .default_open(true)
+                    .default_size([384.0, 128.0])
+                    .collapsible(false)
+                    .show(ui.ctx(), |ui| {
+                        // TODO: Show human readable filesize and row number?
+                        ui.label(format!(
+                            r#"The file you're attempting to open is quite big ({}).
+Files larger than {max} require lots of RAM to open due to memory overhead.
+Do you want to open this file in restricted mode?
+
+Restricted mode only reads the last {max} and {MAX_ROWS} rows of the file."#,
+                            humanreadable_bytes(size),
+                            max = humanreadable_bytes(MAX_FILE_SIZE)
+                        ));
+
+                        ui.add_space(8.0);
+
+                        ui.horizontal(|ui| {
+                            if ui.button("Open in restricted mode").clicked() {
+                                self.restrict_filesize = RestrictFileSize::RestrictedFileSize;
+                                self.restrict_decision = Some(true);
+
+                                if let Err(e) = sender.send(true) {
+                                    error!("Unable to send data to file thread: {e:?}");
+                                }
+
+                                debug!("Open {} in restricted mode", self.filename);
+                            }
+
+                            if ui.button("Open unrestricted").clicked() {
+                                self.restrict_filesize = RestrictFileSize::UnrestrictedFileSize;
+                                self.restrict_decision = Some(false);
+
+                                if let Err(e) = sender.send(false) {
+                                    error!("Unable to send data to file thread: {e:?}");
+                                }
+
+                                debug!("Open {} in unrestricted mode", self.filename);
+                            }
+                        });
+                    });
+            }
+        }
+
+        self.update_filtering(ui.ctx(), &settings.default_format);
+
+        let new_lines_row = self
+            .new_lines_from
+            .and_then(|line_number| line_number.checked_sub(self.dropped_lines + 1))
+            .map(|index| match self.filter_cache.as_ref() {
+                Some(rows) => rows.partition_point(|&i| i < index as usize),
+                None => index as usize,
+            })
+            .filter(|&row| row > 0);
+
+        // Row below each mark's divider, which is drawn below the row before it to also show marks
+        // at the end of the file. Marks of dropped lines are left out. Encoding switches are marked
+        // the same way.
+        let marks = self.marks.iter().enumerate().map(|(n, (line_number, made))| {
+            let label = format!("⚑ Mark {} · {} ago", n + 1, humanreadable_duration(made.elapsed()));
+            (*line_number, label)
+        });
+        let switches = self
+            .encoding_switches
+            .iter()
+            .map(|(line_number, encoding)| (*line_number, format!("Encoding switched to {}", encoding.name())));
+        let notes = self
+            .snapshot
+            .iter()
+            .flat_map(|snapshot| &snapshot.notes)
+            .map(|(line_number, note)| (*line_number, format!("📝 {note}")));
+        let mark_rows: Vec<(usize, String)> = marks
+            .chain(switches)
+            .chain(notes)
+            .filter_map(|(line_number, label)| {
+                let index = line_number.checked_sub(self.dropped_lines + 1)? as usize;
+                let row = match self.filter_cache.as_ref() {
+                    Some(rows) => rows.partition_point(|&i| i < index),
+                    None => index,
+                };
+
+                (row > 0).then_some((row, label))
+            })
+            .collect();
+
+        // The existing contents of a file are read in a single batch, so once there are any lines
+        // the line to scroll to has either been read or is beyond the end of the file.
+        let scroll_to_row = match self.scroll_to_line {
+            Some(line_number) if !self.lines.is_empty() && self.filter_job.is_none() => {
+                self.scroll_to_line = None;
+                let index = line_number.saturating_sub(self.dropped_lines + 1) as usize;
+
+                Some(match self.filter_cache.as_ref() {
+                    Some(rows) => rows
+                        .partition_point(|&i| i < index)
+                        .min(rows.len().saturating_sub(1)),
+                    None => index.min(self.lines.len() - 1),
+                })
+            }
+            _ => None,
+        };
+
+        if self.source_deleted {
+            let color = ui.visuals().error_fg_color;
+
+            egui::Frame::none()
+                .fill(color.gamma_multiply(0.15))
+                .inner_margin(4.0)
+                .show(ui, |ui| {
+                    ui.set_width(ui.available_width());
+                    ui.colored_label(
+                        color,
+                        "⚠ The file has been deleted — tailing stopped. It resumes if the file is created again.",
+                    );
+                });
+        }
+
+        if let Some((offset, size)) = self.skipped_to.filter(|_| !self.lines.is_empty()) {
+            let color = ui.visuals().warn_fg_color;
+            let read = size.saturating_sub(offset).max(1);
+            // Going by the average length of the lines read.
+            let earlier_lines = (offset as f64 * (self.lines.len() as u64 + self.dropped_lines) as f64 / read as f64) as u64;
+
+            egui::Frame::none()
+                .fill(color.gamma_multiply(0.15))
+                .inner_margin(4.0)
+                .show(ui, |ui| {
+                    ui.set_width(ui.available_width());
+                    ui.horizontal(|ui| {
+                        ui.colored_label(
+                            color,
+                            format!(
+                                "Showing the last {} of {} — the first {} (~{} lines) aren't loaded",
+                                humanreadable_bytes(read),
+                                humanreadable_bytes(size),
+                                humanreadable_bytes(offset),
+                                humanreadable_count(earlier_lines),
+                            ),
+                        )
+                        .on_hover_text(format!("Restricted mode started reading at byte {offset}"));
+
+                        // Archive members can't be read from an offset.
+                        if self.archive_member.is_none()
+                            && ui
+                                .small_button("Read from earlier…")
+                                .on_hover_text("Open the file from an offset before the lines shown")
+                                .clicked()
+                        {
+                            self.earlier_requested = Some(offset.saturating_sub(read));
+                        }
+                    });
+                });
+        }
+
+        if self.lines.is_empty() {
+            ui.vertical_centered_justified(|ui| {
+                ui.add_space(50.0);
+ 
+                if !self.errors.is_empty() {
+                    ui.label("ERROR");
+
+                    for err in &self.errors {
+                        // TODO: Better way to display errors?
+                        ui.label(err.to_string());
+                    }
+                } else if self.reader_stopped {
+                    ui.label("Stopped reading the file.");
+                } else {
+                    ui.label("Loading data...");
+                    // TODO: Would be neat if we had some sort of byte or percentage counter here?
+                    ui.spinner();
+                }
+
+                if self.reader_stopped && ui.button("Retry").clicked() {
+                    self.retry();
+                }
+            });
+        } else {
+            let text_height = ui.text_style_height(&TextStyle::Body);
+            let font_id = self.display.font_id();
+            let row_height = ui.fonts(|f| f.row_height(&font_id));
+            let density = self.display.density.unwrap_or(settings.density);
+            let line_spacing = density.row_spacing(row_height) + self.display.line_spacing;
+
+            let mut clicked_encoding: Option<&'static Encoding> = None;
+            let mut redetect = false;
+
+            // Just the search text and a separator while collapsed.
+            let row_modifier_height = if self.row_modifier.collapsed { text_height * 2.0 } else { text_height * 8.0 };
+            // Scrolled through beyond a few of them, with a separator below them.
+            let pinned_height = (!self.pinned_lines.is_empty())
+                .then(|| self.pinned_lines.len().min(MAX_PINNED_ROWS) as f32 * (row_height.max(text_height) + line_spacing) + text_height);
+
+            ScrollArea::vertical()
+                .auto_shrink([false, false])
+                .show(ui, |ui| {
+                    let mut strip_builder = StripBuilder::new(ui);
+
+                    if let Some(height) = pinned_height {
+                        strip_builder = strip_builder.size(Size::exact(height));
+                    }
+
+                    strip_builder
+                        // TODO: I don't like these magic numbers. Is there a good way to calculate
+                        // these hardcoded numbers dynamically?
+                        .size(Size::remainder().at_least(text_height * 10.0))
+                        .size(Size::exact(row_modifier_height).at_least(text_height))
+                        .size(Size::exact(text_height * 2.0))
+                        .vertical(|mut strip| {
+                            if pinned_height.is_some() {
+                                strip.cell(|ui| {
+                                    self.pinned_ui(ui, &font_id, line_spacing);
+                                });
+                            }
+
+                            strip.cell(|ui| {
+                                ui.vertical(|ui| {
+                                    if self.show_raw {
+                                        self.raw_ui(ui, &font_id, row_height);
+                                        return;
+                                    }
+
+                                    if let Some(options) = self.display.table.clone() {
+                                        self.table_ui(ui, &options, &font_id, row_height, scroll_to_row);
+                                        return;
+                                    }
+
+                                    if self.diff_on_reload && self.previous_lines.is_some() {
+                                        self.diff_ui(ui, &font_id, row_height);
+                                        return;
+                                    }
+
+                                    let row_count = self
+                                        .filter_cache
+                                        .as_ref()
+                                        .map_or(self.lines.len(), Vec::len);
+
+                                    // Rows are laid out without their line terminators, so they
+                                    // are exactly one row high.
+                                    ui.spacing_mut().item_spacing = Vec2::new(0.0, line_spacing);
+
+                                    let mut scroll_area = ScrollArea::both()
+                                        .auto_shrink([false, true])
+                                        .stick_to_bottom(scroll_to_row.is_none());
+
+                                    if let Some(row) = scroll_to_row {
+                                        scroll_area = scroll_area.vertical_scroll_offset(
+                                            row as f32 * (row_height + line_spacing),
+                                        );
+                                    }
+
+                                    if std::mem::take(&mut self.scroll_to_line_start) {
+                                        scroll_area = scroll_area.horizontal_scroll_offset(0.0);
+                                    }
+
+                                    let wrap = match self.display.wrap_column {
+                                        Some(column) => Wrap::Column(column),
+                                        None if self.display.wrap || self.fit_to_width => Wrap::Width,
+                                        None => Wrap::None,
+                                    };
+
+                                    let mark_blank_lines = settings.mark_blank_lines;
+                                    let mut copied_reference = None;
+                                    let mut toggled_entry = None;
+                                    let mut toggled_pin = None;
+
+                                    let output = scroll_area
+                                        //.max_height(ui.available_height() - (text_height * 4.0))
+                                        .show_rows(
+                                            ui,
+                                            row_height,
+                                            row_count,
+                                            |ui, row_range| {
+                                                let line_at = |row: usize| match self.filter_cache.as_ref() {
+                                                    Some(rows) => rows.get(row).copied(),
+                                                    None => Some(row),
+                                                }
+                                                .map(|i| self.line_number(i));
+
+                                                let rows_above = row_range.len() / 2;
+                                                let centered_line = line_at(row_range.start + rows_above)
+                                                    .map(|line_number| (line_number, rows_above));
+
+                                                self.visible_lines = line_at(row_range.start)
+                                                    .zip(line_at(row_range.end.saturating_sub(1)))
+                                                    .map(|(first, last)| first..=last);
+                                                self.centered_line = centered_line;
+
+                                                for row_index in row_range {
+                                                    let index = match self.filter_cache.as_ref() {
+                                                        Some(rows) => rows.get(row_index).copied(),
+                                                        None => Some(row_index),
+                                                    };
+
+                                                    if let Some((index, line)) = index.and_then(|i| Some((i, self.lines.get(i)?))) {
+                                                        let mut base = TextFormat {
+                                                            font_id: font_id.clone(),
+                                                            ..Default::default()
+                                                        };
+
+                                                        if self.display.level_colors {
+                                                            if let Some(color) = format
+                                                                .severity(&self.display.ansi.apply(line))
+                                                                .and_then(|s| s.color())
+                                                            {
+                                                                base.color = color;
+                                                            }
+                                                        }
+
+                                                        let mut l = self
+                                                            .row_modifier
+                                                            .generate_line_with_format(line, &base, self.display.render_options());
+
+                                                        l.truncated_length = self.truncated_lengths.get(&self.line_number(index)).copied();
+
+                                                        if self.display.line_numbers {
+                                                            l.row_number = Some(self.line_number(index));
+                                                        }
+
+                                                        if self.display.byte_offsets {
+                                                            l.byte_offset = self.byte_offset(index);
+                                                        }
+
+                                                        if self.grouped() {
+                                                            l.fold = self
+                                                                .entry_starts
+                                                                .binary_search(&index)
+                                                                .ok()
+                                                                .map(|entry| Fold {
+                                                                    expanded: self.expanded_entries.contains(&index),
+                                                                    continuation_lines: self.entry_range(entry).len() - 1,
+                                                                })
+                                                                .filter(|f| f.continuation_lines > 0);
+                                                        }
+
+                                                        // Reserve a spot behind the row for the hover background.
+                                                        let background = ui.painter().add(egui::Shape::Noop);
+                                                        let response = l.ui(ui, wrap);
+                                                        let blank = mark_blank_lines && line.trim().is_empty();
+
+                                                        if l.fold.is_some() && response.clicked() {
+                                                            toggled_entry = Some(index);
+                                                        }
+
+                                                        response.context_menu(|ui| {
+                                                            if ui.button("Copy reference").on_hover_text("Copy e.g. app.log:123 to the clipboard").clicked() {
+                                                                copied_reference = Some(self.line_reference(index));
+                                                                ui.close_menu();
+                                                            }
+
+                                                            let line_number = self.line_number(index);
+                                                            let pin_label = if self.pinned_lines.contains(&line_number) { "Unpin" } else { "📌 Pin to top" };
+
+                                                            if ui.button(pin_label).on_hover_text("Keep the line in view above the rows").clicked() {
+                                                                toggled_pin = Some(line_number);
+                                                                ui.close_menu();
+                                                            }
+                                                        });
+
+                                                        let row_rect = Rect::from_x_y_ranges(
+                                                            ui.max_rect().x_range(),
+                                                            response.rect.y_range(),
+                                                        );
+
+                                                        let fill = if response.context_menu_opened() {
+                                                            Some(ui.visuals().selection.bg_fill.gamma_multiply(0.4))
+                                                        } else if ui.rect_contains_pointer(row_rect) {
+                                                            Some(ui.visuals().widgets.hovered.bg_fill.gamma_multiply(0.3))
+                                                        } else {
+                                                            None
+                                                        };
+
+                                                        if let Some(fill) = fill {
+                                                            ui.painter().set(background, egui::Shape::rect_filled(row_rect, 0.0, fill));
+                                                        }
+
+                                                        if blank {
+                                                            ui.painter().hline(
+                                                                row_rect.x_range(),
+                                                                row_rect.center().y,
+                                                                (1.0, ui.visuals().weak_text_color().gamma_multiply(0.4)),
+                                                            );
+                                                        }
+
+                                                        if new_lines_row == Some(row_index) {
+                                                            ui.painter().hline(
+                                                                row_rect.x_range(),
+                                                                row_rect.top() - line_spacing / 2.0,
+                                                                (1.0, ui.visuals().error_fg_color),
+                                                            );
+                                                        }
+
+                                                        for (_, label) in mark_rows.iter().filter(|(row, _)| *row == row_index + 1) {
+                                                            let y = row_rect.bottom() + line_spacing / 2.0;
+                                                            let color = ui.visuals().hyperlink_color;
+                                                            ui.painter().hline(row_rect.x_range(), y, (1.0, color));
+
+                                                            // Kept in view when scrolled horizontally, with a background covering the line.
+                                                            let background = ui.painter().add(egui::Shape::Noop);
+                                                            let text_rect = ui.painter().text(
+                                                                egui::pos2(row_rect.right().min(ui.clip_rect().right()) - 8.0, y),
+                                                                egui::Align2::RIGHT_CENTER,
+                                                                label,
+                                                                FontId::proportional(font_id.size * 0.8),
+                                                                color,
+                                                            );
+                                                            ui.painter().set(
+                                                                background,
+                                                                egui::Shape::rect_filled(text_rect.expand(2.0), 2.0, ui.visuals().extreme_bg_color),
+                                                            );
+                                                        }
+                                                    }
+                                                }
+                                            },
+                                        );
+
+                                    self.following = output.state.offset.y + output.inner_rect.height()
+                                        >= output.content_size.y - row_height;
+
+                                    if output.content_size.y > output.inner_rect.height() {
+                                        let top_row = ((output.state.offset.y / (row_height + line_spacing)) as usize)
+                                            .min(row_count.saturating_sub(1));
+                                        self.position_ui(ui, output.inner_rect, top_row, row_count);
+                                    }
+
+                                    let scrollable_width = output.content_size.x - output.inner_rect.width();
+                                    self.horizontal_scroll = (scrollable_width > 0.0)
+                                        .then(|| (output.state.offset.x / scrollable_width).clamp(0.0, 1.0));
+
+                                    if let Some(reference) = copied_reference {
+                                        ui.output_mut(|o| o.copied_text = reference);
+                                    }
+
+                                    if let Some(line_number) = toggled_pin {
+                                        self.toggle_pin(line_number);
+                                    }
+
+                                    if let Some(start) = toggled_entry {
+                                        if !self.expanded_entries.remove(&start) {
+                                            self.expanded_entries.insert(start);
+                                        }
+
+                                        self.filter_cache = Some(self.entry_rows(&self.matched_entries));
+                                        self.rows_changed();
+                                    }
+                                });
+                            });
+
+                            strip.cell(|ui| {
+                                ui.separator();
+                                self.row_modifier.filter.position = self.filter_match_position();
+                                self.row_modifier.ui(ui, &format.patterns.level);
+
+                                // Lines only highlighted when matched by the filter depend on it too.
+                                if self.row_modifier.highlights_changed() || self.row_modifier.filter.changed() {
+                                    self.match_rows = None;
+                                }
+
+                                let sample_size = self.lines.len().min(HIGHLIGHT_SAMPLE_LINES);
+
+                                if self.row_modifier.highlight_costs_due(ui.ctx(), sample_size) {
+                                    // The lines around the view, which are the ones being matched.
+                                    let visible = self.visible_lines.as_ref().map_or(0, |v| v.start().saturating_sub(self.dropped_lines + 1) as usize);
+                                    let start = visible.min(self.lines.len() - sample_size);
+                                    let rows_in_view = self.centered_line.map_or(HIGHLIGHT_SAMPLE_LINES, |(_, above)| above * 2 + 1);
+
+                                    self.row_modifier.measure_highlights(&self.lines[start..start + sample_size], rows_in_view);
+                                }
+
+                                if let Some((index, jump)) = self.row_modifier.take_highlight_jump() {
+                                    self.jump_to_highlight(index, jump);
+                                    ui.ctx().request_repaint();
+                                }
+
+                                if let Some(jump) = self.row_modifier.take_filter_jump() {
+                                    self.jump_to_match(Matcher::Filter, jump);
+                                    ui.ctx().request_repaint();
+                                }
+                            });
+
+                            strip.cell(|ui| {
+                                ui.separator();
+
+                                match self.bottom_bar_ui(ui, watcher, settings) {
+                                    Some(EncodingChoice::Redetect) => redetect = true,
+                                    Some(EncodingChoice::Encoding(enc)) => clicked_encoding = Some(enc),
+                                    None => (),
+                                }
+                            });
+                        });
+                });
+
+            self.encoding_switcher_key(ui);
+
+            match self.encoding_switcher_ui(ui) {
+                Some(EncodingChoice::Redetect) => redetect = true,
+                Some(EncodingChoice::Encoding(enc)) => clicked_encoding = Some(enc),
+                None => (),
+            }
+
+            if redetect {
+                self.redetect_encoding(settings.fallback_encoding.encoding());
+            }
+
+            if let Some(enc) = clicked_encoding {
+                if self.encoding_sample.is_empty() {
+                    self.reload_with_encoding(enc);
+                } else {
+                    self.preview_encoding = Some(enc);
+                }
+            }
+
+            self.encoding_preview_ui(ui);
+            self.regex_sandbox_ui(ui, &font_id);
+            self.quick_search_ui(ui, rect);
+        }
+
+        // After the filter's UI, which resets whether it changed.
+        self.filter_toggle_key(ui);
+
+        // TODO: Wait X miliseconds to await further changes?
+        if self.row_modifier.filter_changed() {
+            self.recalculate_filter_cache = true;
+            self.scroll_to_first_match = self.display.scroll_to_first_match && !self.following;
+
+            // Rather than jumping to wherever the same scroll offset ends up among the newly
+            // filtered rows. The line centered before the first of several changes in a row is
+            // kept, as the rows stay the same until the filtering is done.
+            if !self.following && self.keep_in_view.is_none() {
+                self.keep_in_view = self.centered_line;
+            }
+        }
+    }
+
+    /// The bar below the lines, with the file's actions and status. The encoding chosen in its
+    /// encoding menu, if any.
+    fn bottom_bar_ui(&mut self, ui: &mut egui::Ui, watcher: &FileWatcher, settings: &Settings) -> Option<EncodingChoice> {
+        let mut choice = None;
+
+        ui.horizontal(|ui| {
+            let mut paused = self.is_paused();
+            if ui
+                .toggle_value(&mut paused, "⏸ Pause updates")
+                .on_hover_text("Stop adding new lines from the file until resumed")
+                .changed()
+            {
+                self.set_paused(paused);
+            }
+
+            if let Some(job) = self.filter_job.as_ref() {
+                ui.spinner();
+                ui.label(format!("Filtering… ({}%)", job.percent()))
+                    .on_hover_text("New lines are added once it's done");
+                ui.ctx().request_repaint_after(Duration::from_millis(100));
+            }
+
+            if !self.display.wrap && self.display.wrap_column.is_none() {
+                ui.toggle_value(&mut self.fit_to_width, "↔ Fit to width")
+                    .on_hover_text("Wrap lines to the width of the view until toggled off");
+            }
+
+            if let Some(scroll) = self.horizontal_scroll {
+                if ui
+                    .add_enabled(scroll > 0.0, egui::Button::new(format!("⇤ {:.0}%", scroll * 100.0)))
+                    .on_hover_text("How far the lines are scrolled horizontally, click to scroll back to the start of the lines")
+                    .clicked()
+                {
+                    self.scroll_to_line_start = true;
+                }
+            }
+
+            self.notes_ui(ui);
+            self.config_link_ui(ui);
+
+            ui.toggle_value(&mut self.diff_on_reload, "± Diff on reload")
+                .on_hover_text("Keep the lines when the file is reloaded, and show what changed compared to them");
+
+            if self.diff_on_reload {
+                if ui.button("⟳ Reload").on_hover_text("Read the file again and compare").clicked() {
+                    self.reload();
+                }
+
+                if let Some(diff) = self.diff.as_ref().filter(|_| self.previous_lines.is_some()) {
+                    ui.colored_label(DIFF_ADDED_COLOR, format!("+{}", diff.added));
+                    ui.colored_label(DIFF_REMOVED_COLOR, format!("-{}", diff.removed));
+                }
+            } else if self.previous_lines.is_some() {
+                self.previous_lines = None;
+                self.diff = None;
+                self.diff_job = None;
+            }
+
+            if ui
+                .button("⚑ Mark")
+                .on_hover_text("Mark the current end of the file, e.g. right before reproducing an issue")
+                .clicked()
+            {
+                self.mark_now();
+            }
+
+            ui.menu_button("Display", |ui| {
+                self.display.ui(ui);
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.label("Records separated by").on_hover_text(
+                        "Read records which can span several lines, each shown as an entry, rather than lines. Reloads the file.",
+                    );
+
+                    for separator in [None, Some(RecordSeparator::Nul), Some(RecordSeparator::Rs)] {
+                        let label = separator.map_or("Line breaks", |s| s.label());
+
+                        if ui.selectable_label(self.record_separator == separator, label).clicked()
+                            && self.record_separator != separator
+                        {
+                            self.record_separator = separator;
+                            self.reload();
+                        }
+                    }
+                });
+            });
+
+            ui.menu_button("Format", |ui| {
+                let mut custom = self.format.is_some();
+
+                if ui.checkbox(&mut custom, "Custom format for this file").changed() {
+                    self.format = custom.then(|| settings.default_format.clone());
+                }
+
+                match self.format.as_mut() {
+                    Some(format) => {
+                        format.ui(ui);
+                    }
+                    None => {
+                        ui.label("Using the default format from the settings.");
+                    }
+                }
+            });
+
+            // Actions which are rarely used, keeping the bar short.
+            ui.menu_button("⋯ More", |ui| {
+                if ui
+                    .button("📷 Snapshot")
+                    .on_hover_text("Freeze the lines read so far in a new tab, which can be annotated while this one keeps following the file")
+                    .clicked()
+                {
+                    self.snapshot_requested = true;
+                    ui.close_menu();
+                }
+
+                if ui
+                    .button("🧪 Test regex")
+                    .on_hover_text(format!("Try out a search against the first {REGEX_SANDBOX_SAMPLE} lines before filtering the whole file by it"))
+                    .clicked()
+                {
+                    let search = match self.row_modifier.filter.search.mode {
+                        SearchMode::Regex => self.row_modifier.filter.search.clone(),
+                        _ => Search::new("", SearchMode::Regex, false),
+                    };
+
+                    self.regex_sandbox = Some(RegexSandbox { search, matching_only: false });
+                    ui.close_menu();
+                }
+
+                ui.menu_button("Copy matches", |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Capture group").on_hover_text("0 copies the whole match");
+                        ui.add(DragValue::new(&mut self.copy_matches_group).range(0..=99));
+                    });
+
+                    ui.checkbox(&mut self.copy_matches_deduplicate, "Remove duplicates");
+
+                    if ui
+                        .button("Copy")
+                        .on_hover_text("Copy what the filter matches in the shown lines, one match per line")
+                        .clicked()
+                    {
+                        let text = self.matched_texts(self.copy_matches_group, self.copy_matches_deduplicate);
+                        ui.output_mut(|o| o.copied_text = text);
+                        ui.close_menu();
+                    }
+                });
+
+                ui.menu_button("Copy as Markdown", |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Language");
+                        ui.add(egui::TextEdit::singleline(&mut self.markdown_language).hint_text("e.g. log").desired_width(80.0));
+                    });
+
+                    if ui
+                        .button("Copy")
+                        .on_hover_text("Copy the shown lines wrapped in a code block, e.g. to paste into an issue")
+                        .clicked()
+                    {
+                        let (text, copied, shown) = self.shown_lines_markdown(&self.markdown_language);
+                        ui.output_mut(|o| o.copied_text = text);
+
+                        self.markdown_truncated = (copied < shown).then_some((copied, shown));
+
+                        if self.markdown_truncated.is_none() {
+                            ui.close_menu();
+                        }
+                    }
+
+                    if let Some((copied, shown)) = self.markdown_truncated {
+                        ui.colored_label(
+                            ui.visuals().warn_fg_color,
+                            format!("⚠ Only the first {copied} of {shown} lines were copied, up to {}", humanreadable_bytes(MAX_MARKDOWN_COPY_BYTES as u64)),
+                        );
+                    }
+                });
+
+                if self.archive_member.is_none() && !self.streaming {
+                    self.scrub_ui(ui);
+                }
+
+                if self.thread.is_some() && self.archive_member.is_none() && self.scrub.is_none() {
+                    let polling = if self.streaming {
+                        format!("The stream is read again every {STREAM_POLL_INTERVAL:?} once all its writers have closed it")
+                    } else {
+                        match watcher.poll_interval() {
+                            Some(interval) => format!("The file is checked for changes every {interval:?}"),
+                            None => format!("Changes to the file are noticed using {}", watcher.backend()),
+                        }
+                    };
+
+                    if ui.button("⟳ Poll now").on_hover_text(format!(
+                        "{polling}, check for new lines right away"
+                    )).clicked() {
+                        self.poll_now();
+                        ui.close_menu();
+                    }
+                }
+
+                if !self.marks.is_empty() && ui.button(format!("Clear marks ({})", self.marks.len())).clicked() {
+                    self.clear_marks();
+                    ui.close_menu();
+                }
+
+                if ui
+                    .add_enabled(!self.lines.is_empty(), egui::Button::new("🗑 Clear buffer"))
+                    .on_hover_text("Clear the lines read so far and keep reading new ones, keeping the filters and highlights")
+                    .clicked()
+                {
+                    self.clear_buffer();
+                    ui.close_menu();
+                }
+            });
+
+            if self.dropped_lines > 0 {
+                ui.colored_label(
+                    ui.visuals().warn_fg_color,
+                    format!("⚠ Oldest lines dropped to conserve memory ({} so far)", self.dropped_lines),
+                )
+                .on_hover_text("The maximum number of rows in unrestricted mode can be changed in the settings");
+            }
+
+            if let Some(offset) = self.start_offset {
+                ui.label(format!("From {}", humanreadable_bytes(offset))).on_hover_text(format!(
+                    "Read from the first line starting at or after byte {offset}"
+                ));
+            }
+
+            if let Some(separator) = self.record_separator {
+                ui.label("Records").on_hover_text(format!(
+                    "Records separated by {} are read rather than lines",
+                    separator.label()
+                ));
+            }
+
+            if self.archive_member.is_some() {
+                ui.label("Archived").on_hover_text(
+                    "A file within an archive, it's read once rather than tailed",
+                );
+            }
+
+            if let Some(reason) = self.watch_error.as_ref() {
+                ui.colored_label(ui.visuals().warn_fg_color, "⚠ Not following changes")
+                    .on_hover_ui(|ui| {
+                        ui.label(format!("The file couldn't be watched using {}:", watcher.backend()));
+                        ui.label(reason);
+                    });
+
+                if ui.small_button("Retry").clicked() {
+                    self.reload();
+                }
+            }
+
+            if let Some(scrub) = self.scrub {
+                ui.label(format!("Scrubbed to {:.1}%", scrub.fraction * 100.0))
+                    .on_hover_text("Only a window of lines around there is read, scrub to the end to follow the file again");
+            }
+
+            if let Some(modified) = self.modified {
+                self.activity_ui(ui, modified);
+            }
+
+            if self.streaming {
+                ui.label("Streaming").on_hover_text(
+                    "Not a regular file, lines are read as they arrive without any size restriction",
+                );
+            }
+
+            if let Some(encoding) = self.encoding.as_ref() {
+                ui.add_space(1.0);
+
+                let unsure = self.encoding_detection.is_some_and(|d| !d.is_confident());
+                let label = format!("Encoding: {}{}", encoding.name(), if unsure { "?" } else { "" });
+
+                let response = ui.menu_button(label, |ui| {
+                    if ui
+                        .add_enabled(!self.streaming, egui::Button::new("🔍 Re-detect"))
+                        .on_hover_text("Detect the encoding of the start of the file again, rather than keeping the one it was read with")
+                        .clicked()
+                    {
+                        choice = Some(EncodingChoice::Redetect);
+                        ui.close_menu();
+                    }
+
+                    ui.separator();
+
+                    for enc in AVAILABLE_ENCODINGS {
+                        if ui.button(enc.name()).clicked() {
+                            choice = Some(EncodingChoice::Encoding(enc));
+                        }
+                    }
+                }).response;
+
+                let shortcut = "Ctrl+Shift+E to switch with the keyboard";
+
+                match self.encoding_detection {
+                    Some(detection) => response.on_hover_text(format!("{}\n{shortcut}", detection.description())),
+                    None => response.on_hover_text(shortcut),
+                };
+            }
+        });
+
+        choice
     }
 
-    pub fn ui(&mut self, ui: &mut egui::Ui) {
-        if let Some(receiver) = &self.receiver {
-            loop {
-                let res = receiver.try_recv();
-
-                match res {
-                    Ok(msg) => match msg {
-                        LogFileMessage::FileData(v) => {
-                            if let Some(cache) = self.filter_cache.as_mut() {
-                                if !self.row_modifier.filter.search.is_empty()
-                                    && self.row_modifier.filter.filter
-                                    && self.row_modifier.filter.search.regex.is_some()
-                                {
-                                    if let Some(filtered) = self.row_modifier.filter.filter(&v) {
-                                        cache.extend(filtered);
-                                    } else {
-                                        // Unable to incrementally fill the filter cache.
-                                        self.recalculate_filter_cache = true;
-                                    }
-                                }
-                            } else {
-                                self.recalculate_filter_cache = true;
-                            }
+    /// The pinned lines, formatted like the rows. Lines which aren't in memory, e.g. dropped ones,
+    /// are only listed by their line number.
+    fn pinned_ui(&mut self, ui: &mut egui::Ui, font_id: &FontId, line_spacing: f32) {
+        let mut unpinned = None;
 
-                            self.lines.extend(v);
-                        },
-                        LogFileMessage::ShowRestrictFileSizeDialog(size, sender) => {
-                            self.restrict_filesize = RestrictFileSize::ShowRestrictFileSizeDialog(size, sender);
-                        },
-                        LogFileMessage::RestrictFileSize(response) => {
-                            self.restrict_filesize = if response {
-                                RestrictFileSize::RestrictedFileSize
-                            } else {
-                                RestrictFileSize::UnrestrictedFileSize
-                            };
-                        },
-                        LogFileMessage::Error(e) => {
-                            error!("Error when handling file: {e:?}");
-                            self.errors.push(e);
-                        },
-                        LogFileMessage::SetEncoding(encoding) => {
-                            self.encoding = encoding;
-                        },
-                    },
-                    Err(e) => {
-                        match e {
-                            TryRecvError::Empty => (),
-                            TryRecvError::Disconnected => {
-                                self.receiver = None;
-                                self.lines.clear();
-                            }
+        ScrollArea::both()
+            .id_source("pinned_scroll")
+            .auto_shrink([false, true])
+            .max_height(ui.available_height() - ui.spacing().item_spacing.y * 2.0)
+            .show(ui, |ui| {
+                ui.spacing_mut().item_spacing.y = line_spacing;
+
+                for &line_number in &self.pinned_lines {
+                    ui.horizontal(|ui| {
+                        if ui.small_button("📌").on_hover_text("Unpin").clicked() {
+                            unpinned = Some(line_number);
+                        }
+
+                        let index = line_number.checked_sub(self.dropped_lines + 1).map(|i| i as usize);
+                        let Some(line) = index.and_then(|i| self.lines.get(i)) else {
+                            ui.weak(format!("Line {line_number} isn't in memory"));
+                            return;
+                        };
+
+                        let base = TextFormat {
+                            font_id: font_id.clone(),
+                            ..Default::default()
                         };
+                        let mut l = self.row_modifier.generate_line_with_format(line, &base, self.display.render_options());
+                        l.row_number = Some(line_number);
+                        l.truncated_length = self.truncated_lengths.get(&line_number).copied();
+                        l.ui(ui, Wrap::None);
+                    });
+                }
+            });
+
+        ui.separator();
+
+        if let Some(line_number) = unpinned {
+            self.toggle_pin(line_number);
+        }
+    }
 
-                        break;
+    /// Copying the filter, highlights and rewrite rules as a config link and applying one, in the
+    /// bottom bar.
+    fn config_link_ui(&mut self, ui: &mut egui::Ui) {
+        let mut applied = None;
+
+        ui.menu_button("🔗 Config link", |ui| {
+            if ui
+                .button("Copy config link")
+                .on_hover_text("Copy the filter, highlights and rewrite rules as a single line of text, e.g. to share in a chat")
+                .clicked()
+            {
+                match self.row_modifier.to_config_link() {
+                    Ok(link) => ui.output_mut(|o| o.copied_text = link),
+                    Err(e) => self.config_link_draft.1 = Some(format!("Unable to create config link: {e}")),
+                }
+
+                ui.close_menu();
+            }
+
+            ui.separator();
+
+            let (link, error) = &mut self.config_link_draft;
+
+            ui.horizontal(|ui| {
+                ui.add(TextEdit::singleline(link).hint_text("Paste a config link"));
+
+                if ui.add_enabled(!link.trim().is_empty(), egui::Button::new("Apply")).clicked() {
+                    match RowModifier::from_config_link(link) {
+                        Ok(row_modifier) => {
+                            applied = Some(row_modifier);
+                            link.clear();
+                            *error = None;
+                            ui.close_menu();
+                        }
+                        Err(e) => *error = Some(e),
                     }
                 }
+            });
+
+            if let Some(error) = error {
+                ui.colored_label(ui.visuals().error_fg_color, error.as_str());
             }
-        } else {
-            let (thread, receiver) = self.create_receiver(ui.ctx().clone());
-            self.thread = Some(thread);
-            self.receiver = Some(receiver);
-            self.recalculate_filter_cache = true;
+        });
+
+        if let Some(row_modifier) = applied {
+            self.apply_row_modifier(row_modifier);
         }
+    }
 
-        match self.restrict_filesize.clone() {
-            RestrictFileSize::Initializing => (),
-            RestrictFileSize::UnrestrictedFileSize => (), // NOOP
-            RestrictFileSize::RestrictedFileSize => {
-                while self.lines.len() > MAX_ROWS as usize {
-                    self.lines.remove(0);
+    /// Replaces the filter, highlights and rewrite rules, keeping how they're shown.
+    fn apply_row_modifier(&mut self, row_modifier: RowModifier) {
+        let collapsed = self.row_modifier.collapsed;
+
+        self.row_modifier = row_modifier;
+        self.row_modifier.collapsed = collapsed;
+        // Refiltered once the rest of the frame sees the change.
+        self.row_modifier.filter.changed = true;
+        self.row_modifier.highlights_changed = true;
+        self.rows_changed();
+    }
+
+    /// Adding notes to the lines of a snapshot and going to them, in the bottom bar.
+    fn notes_ui(&mut self, ui: &mut egui::Ui) {
+        let Some(snapshot) = self.snapshot.as_mut() else {
+            return;
+        };
+
+        let mut go_to = None;
+
+        ui.menu_button(format!("📝 Notes ({})", snapshot.notes.len()), |ui| {
+            ui.weak(format!("Snapshot taken at {}", clock_time(snapshot.taken)));
+
+            let (line_number, text) = &mut self.note_draft;
+
+            if *line_number == 0 {
+                *line_number = self.centered_line.map_or(1, |(line_number, _)| line_number);
+            }
+
+            ui.horizontal(|ui| {
+                ui.label("Line");
+                ui.add(DragValue::new(line_number).range(1..=u64::MAX));
+                ui.text_edit_singleline(text);
+
+                if ui.add_enabled(!text.trim().is_empty(), egui::Button::new("Add")).clicked() {
+                    snapshot.notes.push((*line_number, std::mem::take(text)));
+                    snapshot.notes.sort_by_key(|(line_number, _)| *line_number);
+                    *line_number = 0;
+                }
+            });
+
+            let mut note_to_remove = None;
+
+            for (index, (line_number, note)) in snapshot.notes.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    if ui.small_button(format!("Line {line_number}")).clicked() {
+                        go_to = Some(*line_number);
+                        ui.close_menu();
+                    }
+
+                    ui.label(note);
+
+                    if ui.small_button("X").on_hover_text("Remove the note").clicked() {
+                        note_to_remove = Some(index);
+                    }
+                });
+            }
+
+            if let Some(index) = note_to_remove {
+                snapshot.notes.remove(index);
+            }
+        });
+
+        if let Some(line_number) = go_to {
+            self.scroll_to_line(line_number);
+        }
+    }
+
+    /// A slider over the bytes of the file in the bottom bar's overflow menu, to scrub through a
+    /// file too large to read all of it. Where it's released a window of lines around the position
+    /// is read, rather than the whole file, and the file isn't followed until scrubbing to the end.
+    fn scrub_ui(&mut self, ui: &mut egui::Ui) {
+        let label = match self.scrub {
+            Some(scrub) => format!("🎚 Scrubbed to {:.1}%", scrub.fraction * 100.0),
+            None => String::from("🎚 Scrub"),
+        };
+
+        let response = ui.menu_button(label, |ui| {
+            let len = self
+                .scrub_file_len
+                .get_or_insert_with(|| std::fs::metadata(&self.path).map(|meta| meta.len()).map_err(|e| e.to_string()))
+                .clone();
+
+            let len = match len {
+                Ok(len) => len,
+                Err(e) => {
+                    ui.colored_label(ui.visuals().error_fg_color, format!("Unable to get the size of the file: {e}"));
+                    return;
                 }
+            };
+
+            let mut fraction = self
+                .scrub_draft
+                .or(self.scrub.map(|scrub| scrub.fraction))
+                .unwrap_or(1.0);
+
+            let response = ui.add(egui::Slider::new(&mut fraction, 0.0..=1.0).show_value(false));
+            self.scrub_draft = response.dragged().then_some(fraction);
+
+            ui.label(format!(
+                "{} of {}",
+                humanreadable_bytes((fraction as f64 * len as f64) as u64),
+                humanreadable_bytes(len)
+            ));
+            ui.weak(format!(
+                "Reads {} around where it's released, without following the file until at the end",
+                humanreadable_bytes(SCRUB_WINDOW)
+            ));
+
+            if response.drag_stopped() || (response.changed() && !response.dragged()) {
+                self.scrub_to(fraction, len);
             }
-            RestrictFileSize::ShowRestrictFileSizeDialog(size, sender) => {
-                egui::Window::new("Large File")
-                    .default_open(true)
-                    .default_size([384.0, 128.0])
-                    .collapsible(false)
-                    .show(ui.ctx(), |ui| {
-                        // TODO: Show human readable filesize and row number?
-                        ui.label(format!(
-                            r#"The file you're attempting to open is quite big ({}).
-Files larger than {max} require lots of RAM to open due to memory overhead.
-Do you want to open this file in restricted mode?
+        });
+
+        // Read again the next time the menu is opened, the file may have grown.
+        if response.inner.is_none() {
+            self.scrub_file_len = None;
+        }
+    }
+
+    /// Reads the window of the file's `len` bytes around `fraction` of them, or all of it and
+    /// follows it again at the end.
+    fn scrub_to(&mut self, fraction: f32, len: u64) {
+        self.scrub = (fraction < 1.0).then(|| Scrub {
+            fraction,
+            offset: ((fraction as f64 * len as f64) as u64).saturating_sub(SCRUB_WINDOW / 2),
+        });
+        self.sender = None;
+        self.reload();
+    }
+
+    /// Whether lines are arriving, with a dot pulsing while they are, and when the file was last
+    /// modified.
+    fn activity_ui(&mut self, ui: &mut egui::Ui, modified: SystemTime) {
+        let since_data = self.last_data.map(|t| t.elapsed());
+        let active = since_data.is_some_and(|d| d < ACTIVE_DURATION);
+        let age = SystemTime::now().duration_since(modified).unwrap_or_default();
+
+        let (rect, response) = ui.allocate_exact_size(Vec2::splat(ui.text_style_height(&TextStyle::Body)), egui::Sense::hover());
+
+        let color = if active {
+            let time = ui.input(|i| i.time);
+            // Until the lines have stopped arriving for long enough to be idle.
+            ui.ctx().request_repaint_after(Duration::from_millis(50));
+
+            Color32::GREEN.gamma_multiply(0.6 + 0.4 * (time * 4.0).sin().abs() as f32)
+        } else {
+            ui.visuals().weak_text_color()
+        };
+        ui.painter().circle_filled(rect.center(), rect.height() / 4.0, color);
+
+        let label = if active {
+            match self.throughput.rate(Instant::now()) {
+                Some((lines, bytes)) => format!(
+                    "Active, ~{} lines/s, {}/s",
+                    humanreadable_count(lines.round() as u64),
+                    humanreadable_bytes(bytes.round() as u64)
+                ),
+                None => String::from("Active"),
+            }
+        } else {
+            format!("Idle, modified {} ago", humanreadable_duration(age))
+        };
+
+        ui.label(label).union(response).on_hover_ui(|ui| {
+            ui.label(format!("Last modified {} ago", humanreadable_duration(age)));
+
+            match since_data {
+                Some(since) => ui.label(format!("Lines last arrived {} ago", humanreadable_duration(since))),
+                None => ui.label("No lines have arrived since the file was opened"),
+            };
+        });
+
+        // Briefly flashed once lines stop arriving while following the end, as the file turns idle,
+        // to tell a tail which is idle but live apart from one which has stopped.
+        let live = self.following && self.scrub.is_none() && !self.reader_stopped && !self.source_deleted && self.watch_error.is_none();
+
+        if let Some(shown) = since_data.and_then(|d| d.checked_sub(ACTIVE_DURATION)).filter(|_| live) {
+            if shown < CAUGHT_UP_FADE {
+                let opacity = 1.0 - shown.as_secs_f32() / CAUGHT_UP_FADE.as_secs_f32();
+                ui.colored_label(Color32::GREEN.gamma_multiply(opacity), "✔ Caught up")
+                    .on_hover_text("Showing the end of the file, new lines appear as they arrive");
+                ui.ctx().request_repaint();
+            }
+        }
+    }
+
+    /// A window to try out a search against the first lines of the file, showing which match and
+    /// what's matched as they're typed. Matching the whole file is left to the filter.
+    fn regex_sandbox_ui(&mut self, ui: &mut egui::Ui, font_id: &FontId) {
+        let Some(sandbox) = self.regex_sandbox.as_mut() else {
+            return;
+        };
+
+        let mut open = true;
+        let mut use_as_filter = false;
+
+        egui::Window::new("Test regex")
+            .id(ui.id().with("regex_sandbox"))
+            .open(&mut open)
+            .default_size([720.0, 480.0])
+            .show(ui.ctx(), |ui| {
+                sandbox.search.ui_labeled(ui, "Pattern", |_| {});
+
+                let sample = &self.lines[..self.lines.len().min(REGEX_SANDBOX_SAMPLE)];
+                let ready = sandbox.search.is_ready() && !sandbox.search.is_empty();
+                let matching: Vec<&String> = sample
+                    .iter()
+                    .filter(|line| !sandbox.matching_only || (ready && sandbox.search.is_match(line)))
+                    .collect();
+
+                ui.horizontal(|ui| {
+                    if ready {
+                        let count = sample.iter().filter(|line| sandbox.search.is_match(line)).count();
+                        ui.label(format!("{count} of the first {} lines match", sample.len()));
+                    } else {
+                        ui.label(format!("The first {} lines", sample.len()));
+                    }
+
+                    ui.checkbox(&mut sandbox.matching_only, "Only matching");
+
+                    if ui.add_enabled(ready, egui::Button::new("Use as filter")).clicked() {
+                        use_as_filter = true;
+                    }
+                });
+
+                ui.separator();
+
+                let row_modifier = RowModifier {
+                    filter: Filter::new(sandbox.search.clone()),
+                    match_style: self.row_modifier.match_style,
+                    ..Default::default()
+                };
+                let base = TextFormat {
+                    font_id: font_id.clone(),
+                    ..Default::default()
+                };
+                let options = RenderOptions {
+                    ansi: self.display.ansi,
+                    tab_width: self.display.tab_width,
+                    ..Default::default()
+                };
+                let row_height = ui.fonts(|f| f.row_height(font_id));
+
+                ScrollArea::both()
+                    .id_source("regex_sandbox_scroll")
+                    .auto_shrink([false, false])
+                    .show_rows(ui, row_height, matching.len(), |ui, row_range| {
+                        for line in &matching[row_range] {
+                            row_modifier
+                                .generate_line_with_format(line, &base, options)
+                                .ui(ui, Wrap::None);
+                        }
+                    });
+            });
+
+        if use_as_filter {
+            if let Some(sandbox) = self.regex_sandbox.take() {
+                self.row_modifier.filter.set_search(sandbox.search);
+            }
+        }
+
+        if !open {
+            self.regex_sandbox = None;
+        }
+    }
+
+    /// Handles Ctrl+Shift+E opening the encoding switcher, see [`Self::encoding_switcher_ui`].
+    fn encoding_switcher_key(&mut self, ui: &mut egui::Ui) {
+        if self.has_keyboard
+            && self.encoding.is_some()
+            && ui.input_mut(|i| i.consume_key(Modifiers::CTRL | Modifiers::SHIFT, Key::E))
+        {
+            let current = AVAILABLE_ENCODINGS.iter().position(|e| Some(*e) == self.encoding);
+            self.encoding_switcher = Some(current.map_or(0, |i| i + 1));
+        }
+    }
+
+    /// The encodings to switch to, like the encoding menu but navigated with the arrow keys. Enter
+    /// picks the selected entry and Escape closes it.
+    fn encoding_switcher_ui(&mut self, ui: &mut egui::Ui) -> Option<EncodingChoice> {
+        let mut selected = self.encoding_switcher?;
+        let count = AVAILABLE_ENCODINGS.len() + 1;
+
+        let (up, down, enter, escape) = ui.input_mut(|i| {
+            (
+                i.consume_key(Modifiers::NONE, Key::ArrowUp),
+                i.consume_key(Modifiers::NONE, Key::ArrowDown),
+                i.consume_key(Modifiers::NONE, Key::Enter),
+                i.consume_key(Modifiers::NONE, Key::Escape),
+            )
+        });
+
+        if up {
+            selected = (selected + count - 1) % count;
+        }
+
+        if down {
+            selected = (selected + 1) % count;
+        }
+
+        let mut picked = enter.then_some(selected);
+        let mut open = true;
+
+        egui::Window::new("Switch encoding")
+            .id(ui.id().with("encoding_switcher"))
+            .open(&mut open)
+            .collapsible(false)
+            .default_height(360.0)
+            .show(ui.ctx(), |ui| {
+                ui.weak("↑ and ↓ to select, Enter to reload with it, Esc to close");
+                ui.separator();
+
+                ScrollArea::vertical().auto_shrink([false, true]).show(ui, |ui| {
+                    let redetect = ui
+                        .add_enabled(!self.streaming, egui::SelectableLabel::new(selected == 0, "🔍 Re-detect"))
+                        .on_hover_text("Detect the encoding of the start of the file again");
+                    let mut responses = vec![redetect];
+
+                    for encoding in AVAILABLE_ENCODINGS {
+                        let label = if Some(encoding) == self.encoding {
+                            format!("{} (current)", encoding.name())
+                        } else {
+                            encoding.name().to_owned()
+                        };
+
+                        responses.push(ui.selectable_label(responses.len() == selected, label));
+                    }
+
+                    for (index, response) in responses.into_iter().enumerate() {
+                        if (up || down) && index == selected {
+                            response.scroll_to_me(None);
+                        }
+
+                        if response.clicked() {
+                            picked = Some(index);
+                        }
+                    }
+                });
+            });
+
+        self.encoding_switcher = (open && !escape && picked.is_none()).then_some(selected);
+
+        match picked? {
+            0 if self.streaming => None,
+            0 => Some(EncodingChoice::Redetect),
+            index => Some(EncodingChoice::Encoding(AVAILABLE_ENCODINGS[index - 1])),
+        }
+    }
+
+    /// Detects the encoding of the start of the file again, like when it was first read, and reads
+    /// it again with the result. Otherwise the encoding it was read with is kept, as it's
+    /// persisted, even if the guess turns out to be wrong.
+    fn redetect_encoding(&mut self, fallback: Option<&'static Encoding>) {
+        use std::io::Read;
+
+        // Archive members are sampled while being read, rather than decompressed again here.
+        let fresh = match self.archive_member {
+            Some(_) => None,
+            None => std::fs::File::open(&self.path)
+                .and_then(|file| {
+                    let mut sample = Vec::new();
+                    file.take(ENCODING_SAMPLE_SIZE).read_to_end(&mut sample)?;
+                    Ok(sample)
+                })
+                .inspect_err(|e| debug!("Unable to sample {} to detect its encoding: {e}", self.filename))
+                .ok(),
+        };
+
+        let sample = fresh.unwrap_or_else(|| self.encoding_sample.clone());
+
+        if sample.is_empty() {
+            debug!("Nothing to detect the encoding of {} from", self.filename);
+            return;
+        }
+
+        let (encoding, _) = detect_encoding(&sample, (sample.len() as u64) < ENCODING_SAMPLE_SIZE, fallback);
+        self.reload_with_encoding(encoding);
+    }
+
+    fn encoding_preview_ui(&mut self, ui: &mut egui::Ui) {
+        let Some(candidate) = self.preview_encoding else {
+            return;
+        };
+
+        let current = self.encoding.unwrap_or(encoding_rs::UTF_8);
+        let mut open = true;
+        let mut confirmed = false;
+        let mut cancelled = false;
+
+        egui::Window::new(format!("Preview encoding: {}", candidate.name()))
+            .id(ui.id().with("encoding_preview"))
+            .open(&mut open)
+            .default_size([720.0, 480.0])
+            .collapsible(false)
+            .show(ui.ctx(), |ui| {
+                ui.horizontal(|ui| {
+                    if ui.button(format!("Reload with {}", candidate.name())).clicked() {
+                        confirmed = true;
+                    }
+
+                    if ui.button("Cancel").clicked() {
+                        cancelled = true;
+                    }
+                });
+
+                ui.separator();
+
+                ui.columns(2, |columns| {
+                    for (ui, encoding) in columns.iter_mut().zip([current, candidate]) {
+                        ui.strong(encoding.name());
+
+                        ScrollArea::both()
+                            .id_source(encoding.name())
+                            .auto_shrink([false, false])
+                            .show(ui, |ui| {
+                                for line in preview_lines(&self.encoding_sample, encoding) {
+                                    Label::new(line).extend().ui(ui);
+                                }
+                            });
+                    }
+                });
+            });
+
+        if confirmed {
+            self.reload_with_encoding(candidate);
+        }
 
-Restricted mode only reads the last {max} and {MAX_ROWS} rows of the file."#,
-                            humanreadable_bytes(size),
-                            max = humanreadable_bytes(MAX_FILE_SIZE)
-                        ));
+        if confirmed || cancelled || !open {
+            self.preview_encoding = None;
+        }
+    }
+}
 
-                        ui.add_space(8.0);
+/// Number of lines from the start of the file a search is tried out against in the regex sandbox.
+const REGEX_SANDBOX_SAMPLE: usize = 500;
 
-                        ui.horizontal(|ui| {
-                            if ui.button("Open in restricted mode").clicked() {
-                                self.restrict_filesize = RestrictFileSize::RestrictedFileSize;
+/// A search tried out against the first lines of a file before filtering by it.
+#[derive(Debug)]
+struct RegexSandbox {
+    search: Search,
+    /// Only show the lines of the sample which match.
+    matching_only: bool,
+}
 
-                                if let Err(e) = sender.send(true) {
-                                    error!("Unable to send data to file thread: {e:?}");
-                                }
+/// Indices of the lines from `from` onwards which start an entry. With a record separator the
+/// entries are the records, which start after a line ending with the separator.
+fn find_entry_starts(lines: &[String], from: usize, format: &FormatProfile, separator: Option<RecordSeparator>) -> Vec<usize> {
+    (from..lines.len())
+        .into_par_iter()
+        .filter(|&i| {
+            i == 0
+                || match separator {
+                    Some(separator) => lines[i - 1].ends_with(char::from(separator.byte())),
+                    None => format.is_entry_start(&lines[i]),
+                }
+        })
+        .collect()
+}
 
-                                debug!("Open {} in restricted mode", self.filename);
-                            }
+/// Entries matching the filter, with the number of entries remaining after each step. All
+/// entries if the filter isn't active.
+fn filter_entries(
+    row_modifier: &RowModifier,
+    lines: &[String],
+    starts: &[usize],
+    control: &FilterControl,
+) -> (Vec<usize>, Vec<usize>) {
+    let all = || ((0..starts.len()).collect(), Vec::new());
+
+    if row_modifier.filter.search.is_empty() || !row_modifier.filter.filter {
+        return all();
+    }
 
-                            if ui.button("Open unrestricted").clicked() {
-                                self.restrict_filesize = RestrictFileSize::UnrestrictedFileSize;
+    row_modifier
+        .filter_items_controlled(
+            starts.len(),
+            |e| {
+                let end = starts.get(e + 1).copied().unwrap_or(lines.len());
+                Cow::Owned(lines[starts[e]..end].join("\n"))
+            },
+            control,
+        )
+        .unwrap_or_else(all)
+}
 
-                                if let Err(e) = sender.send(false) {
-                                    error!("Unable to send data to file thread: {e:?}");
-                                }
+/// Decodes the first lines of `sample` using `encoding`.
+fn preview_lines(sample: &[u8], encoding: &'static Encoding) -> Vec<String> {
+    let (decoded, _contains_invalid_content) = encoding.decode_with_bom_removal(sample);
 
-                                debug!("Open {} in unrestricted mode", self.filename);
-                            }
-                        });
-                    });
-            }
-        }
+    decoded
+        .lines()
+        .take(ENCODING_PREVIEW_LINES)
+        .map(str::to_owned)
+        .collect()
+}
 
-        if self.recalculate_filter_cache {
-            self.filter_cache =
-                if self.row_modifier.filter.search.is_empty() || !self.row_modifier.filter.filter {
-                    None
-                } else {
-                    // TODO: self.filter.regex should be some
-                    self.row_modifier.filter.filter(&self.lines)
-                };
+impl Debug for LogFile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&format!("LogFile {}", self.filename))
+    }
+}
 
-            self.recalculate_filter_cache = false;
-        }
+/// How long to wait before reading a stream again after all its writers have closed it.
+const STREAM_POLL_INTERVAL: Duration = Duration::from_millis(250);
+const ARCHIVE_BATCH_LINES: usize = 100_000;
 
-        if self.lines.is_empty() {
-            ui.vertical_centered_justified(|ui| {
-                ui.add_space(50.0);
- 
-                if self.errors.is_empty() {
-                    ui.label("Loading data...");
-                    // TODO: Would be neat if we had some sort of byte or percentage counter here?
-                    ui.spinner();
-                } else {
-                    ui.label("ERROR");
+/// Requests repaints on behalf of a reader.
+///
+/// New data only schedules a repaint `interval` from now, egui keeps the earliest pending request
+/// so a burst of batches results in at most one repaint per interval instead of one per batch.
+#[derive(Clone)]
+struct Repainter {
+    ctx: egui::Context,
+    paused: Arc<AtomicBool>,
+    interval: Duration,
+}
 
-                    for err in &self.errors {
-                        // TODO: Better way to display errors?
-                        ui.label(err.to_string());
-                    }
-                }
-            });
-        } else {
-            let text_height = ui.text_style_height(&TextStyle::Body);
+impl Repainter {
+    /// For new data, which isn't shown while paused anyway.
+    fn data(&self) {
+        if !self.paused.load(Ordering::Relaxed) {
+            self.ctx.request_repaint_after(self.interval);
+        }
+    }
 
-            let mut clicked_encoding: Option<&'static Encoding> = None;
+    /// For errors and state changes the user should see right away.
+    fn now(&self) {
+        self.ctx.request_repaint();
+    }
+}
 
-            ScrollArea::vertical()
-                .auto_shrink([false, false])
-                .show(ui, |ui| {
-                    StripBuilder::new(ui)
-                        // TODO: I don't like these magic numbers. Is there a good way to calculate
-                        // these hardcoded numbers dynamically?
-                        .size(Size::remainder().at_least(text_height * 10.0))
-                        .size(Size::exact(text_height * 8.0).at_least(text_height))
-                        .size(Size::exact(text_height * 2.0))
-                        .vertical(|mut strip| {
-                            strip.cell(|ui| {
-                                ui.vertical(|ui| {
-                                    let filtered = if let Some(f) = self.filter_cache.as_ref() {
-                                        f
-                                    } else {
-                                        self.lines.as_ref()
-                                    };
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SourceKind {
+    Regular,
+    Directory,
+    /// FIFOs and character devices, can't be seeked and might never reach the end.
+    Stream,
+    Unsupported(&'static str),
+}
 
-                                    // TODO: Is there a better way than using negative spacing?
-                                    ui.spacing_mut().item_spacing = Vec2::new(0.0, -10.0);
+impl SourceKind {
+    fn from_metadata(meta: &std::fs::Metadata) -> Self {
+        let file_type = meta.file_type();
 
-                                    ScrollArea::both()
-                                        .auto_shrink([false, true])
-                                        .stick_to_bottom(true)
-                                        //.max_height(ui.available_height() - (text_height * 4.0))
-                                        .show_rows(
-                                            ui,
-                                            text_height,
-                                            filtered.len(),
-                                            |ui, row_range| {
-                                                for row_index in row_range {
-                                                    if let Some(line) = filtered.get(row_index) {
-                                                        self.row_modifier
-                                                            .generate_line(line)
-                                                            .ui(ui);
-                                                    }
-                                                }
-                                            },
-                                        );
-                                });
-                            });
+        if file_type.is_dir() {
+            return Self::Directory;
+        }
 
-                            strip.cell(|ui| {
-                                ui.separator();
-                                self.row_modifier.ui(ui);
-                            });
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::FileTypeExt;
 
-                            strip.cell(|ui| {
-                                ui.separator();
-                                ui.horizontal(|ui| {
-                                    if let Some(encoding) = self.encoding.as_ref() {
-                                        ui.add_space(1.0);
-
-                                        ui.menu_button(format!("Encoding: {}", encoding.name()), |ui| {
-                                            for enc in AVAILABLE_ENCODINGS {
-                                                if ui.button(enc.name()).clicked() {
-                                                    clicked_encoding = Some(enc);
-                                                }
-                                            }
-                                        });
-                                    }
-                                });
-                            });
-                        });
-                });
+            if file_type.is_fifo() || file_type.is_char_device() {
+                return Self::Stream;
+            }
 
-            if let Some(enc) = clicked_encoding {
-                self.reload_with_encoding(enc);
+            if file_type.is_socket() {
+                return Self::Unsupported("socket");
             }
-        }
 
-        // TODO: Wait X miliseconds to await further changes?
-        if self.row_modifier.filter.changed() {
-            self.recalculate_filter_cache = true;
+            if file_type.is_block_device() {
+                return Self::Unsupported("block device");
+            }
         }
+
+        Self::Regular
     }
 }
 
-impl Debug for LogFile {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_str(&format!("LogFile {}", self.filename))
+async fn read_sample(file_path: &Path) -> Result<Vec<u8>, Error> {
+    let mut sample = Vec::new();
+    File::open(file_path)
+        .await?
+        .take(ENCODING_SAMPLE_SIZE)
+        .read_to_end(&mut sample)
+        .await?;
+
+    Ok(sample)
+}
+
+/// Detects the encoding of `bytes` by its BOM, or guesses it, using `fallback` instead if there is
+/// one and the guess isn't confident. `complete` tells whether `bytes` is all of the data rather
+/// than the start of it.
+fn detect_encoding(bytes: &[u8], complete: bool, fallback: Option<&'static Encoding>) -> (&'static Encoding, EncodingDetection) {
+    match Encoding::for_bom(bytes) {
+        Some((e, num_bom_bytes)) => {
+            debug!("Detected encoding: {}, based on {num_bom_bytes} BOM bytes", e.name());
+            (e, EncodingDetection::Bom)
+        },
+        None => {
+            let mut detector = chardetng::EncodingDetector::new();
+
+            detector.feed(bytes, complete);
+            // Hard to make it decide between
+            let (e, good_score) = detector.guess_assess(None, true);
+            debug!("Detected encoding: {}, based on {} bytes read. Is there likely a better encoding? {good_score}", e.name(), bytes.len());
+
+            match fallback {
+                Some(fallback) if !good_score => {
+                    debug!("Using fallback encoding {} instead", fallback.name());
+                    (fallback, EncodingDetection::Fallback)
+                }
+                _ => (e, EncodingDetection::Guessed { confident: good_score }),
+            }
+        }
     }
 }
 
-async fn init_reader(file_path: &Path, restrict_filesize: bool, encoding: Option<&'static Encoding>) -> Result<(BufReader<File>, &'static Encoding), Error> {
+async fn init_reader(file_path: &Path, restrict_filesize: bool, start_offset: u64, encoding: Option<&'static Encoding>, fallback_encoding: Option<&'static Encoding>, buffer_size: usize) -> Result<(BufReader<File>, &'static Encoding, EncodingDetection), Error> {
     let file = File::open(file_path).await?;
-    let mut reader = BufReader::new(file);
+    let mut reader = BufReader::with_capacity(buffer_size, file);
 
-    let encoding = match encoding {
-        Some(e) => e,
+    let (encoding, detection) = match encoding {
+        Some(e) => (e, EncodingDetection::Given),
         None => {
             let max_bytes_to_read = 24 * 1024 * 1024;
             let mut detection_buffer = vec![0; max_bytes_to_read];
@@ -762,21 +5835,7 @@ async fn init_reader(file_path: &Path, restrict_filesize: bool, encoding: Option
             let num_bytes = reader.read(&mut detection_buffer).await?;
             reader.seek(SeekFrom::Start(0)).await?;
 
-            match Encoding::for_bom(&detection_buffer[0 .. num_bytes]) {
-                Some((e, num_bom_bytes)) => {
-                    debug!("Detected encoding: {}, based on {num_bom_bytes} BOM bytes", e.name());
-                    e
-                },
-                None => {
-                    let mut detector = chardetng::EncodingDetector::new();
-
-                    detector.feed(&detection_buffer[0 .. num_bytes ], num_bytes < max_bytes_to_read);
-                    // Hard to make it decide between
-                    let (e, good_score) = detector.guess_assess(None, true);
-                    debug!("Detected encoding: {}, based on {num_bytes} bytes read. Is there likely a better encoding? {good_score}", e.name());
-                    e
-                }
-            }
+            detect_encoding(&detection_buffer[0 .. num_bytes], num_bytes < max_bytes_to_read, fallback_encoding)
         }
     };
 
@@ -788,64 +5847,368 @@ async fn init_reader(file_path: &Path, restrict_filesize: bool, encoding: Option
         meta.len() > MAX_FILE_SIZE
     );
 
-    if restrict_filesize && meta.len() > MAX_FILE_SIZE {
-        // Additional 512 bytes to increase likelyhood of not skipping too much data. E.g. include
-        // potential linebreaks etc
-        let seek_to = MAX_FILE_SIZE + 512;
+    // Additional 512 bytes to increase likelyhood of not skipping too much data. E.g. include
+    // potential linebreaks etc
+    let seek_to = MAX_FILE_SIZE + 512;
+
+    if restrict_filesize && meta.len().saturating_sub(start_offset) > MAX_FILE_SIZE && meta.len().saturating_sub(seek_to) > start_offset {
         debug!("File too big, only reading last {seek_to} bytes");
         let _ = reader.seek(SeekFrom::End(-(seek_to as i64))).await?;
         let mut l = Vec::new();
         debug!("Skipping until next new line.");
         let _ = reader.read_until(b'\n', &mut l).await?;
+    } else if start_offset > 0 {
+        // Skipping the rest of the line the byte before the offset is part of starts at the first
+        // line starting at or after the offset.
+        debug!("Starting at offset {start_offset}");
+        let _ = reader.seek(SeekFrom::Start(start_offset - 1)).await?;
+        let mut l = Vec::new();
+        let _ = reader.read_until(b'\n', &mut l).await?;
+    }
+
+    Ok((reader, encoding, detection))
+}
+
+/// Detects the encoding again when lines keep failing to decode, e.g. in logs of differently
+/// encoded files concatenated together.
+#[derive(Debug, Default)]
+struct EncodingRedetector {
+    /// The bytes of each of the most recently read lines which failed to decode.
+    recent: VecDeque<Option<Vec<u8>>>,
+    /// Byte offset of the first line decoded by each encoding switched to, not yet sent.
+    switches: Vec<(u64, &'static Encoding)>,
+}
+
+impl EncodingRedetector {
+    /// Keeps track of a line read, returns the encoding to decode it and the following lines with
+    /// if `current` no longer seems to be the right one.
+    fn line(&mut self, bytes: &[u8], had_errors: bool, current: &'static Encoding) -> Option<&'static Encoding> {
+        if self.recent.len() == REDETECT_WINDOW_LINES {
+            self.recent.pop_front();
+        }
+
+        self.recent.push_back(had_errors.then(|| bytes.to_vec()));
+
+        if self.recent.iter().flatten().count() < REDETECT_THRESHOLD {
+            return None;
+        }
+
+        let problematic: Vec<u8> = self.recent.drain(..).flatten().flatten().collect();
+        let (encoding, _) = detect_encoding(&problematic, false, None);
+        let (_, still_invalid) = encoding.decode_without_bom_handling(&problematic);
+
+        (encoding != current && !still_invalid).then_some(encoding)
+    }
+}
+
+/// The lines of a record read up to and including `separator`, each with its offset within the
+/// record. Records without a separator are lines of their own. The lines keep their terminators,
+/// the last one also the separator, which tells where the next record starts.
+fn split_record(record: &[u8], separator: Option<RecordSeparator>) -> Vec<(usize, &[u8])> {
+    let Some(separator) = separator else {
+        return vec![(0, record)];
+    };
+
+    let mut lines: Vec<(usize, &[u8])> = Vec::new();
+    let mut offset = 0;
+
+    for line in record.split_inclusive(|&b| b == b'\n') {
+        match lines.last_mut() {
+            // A separator right after a line break ends the record, rather than being a line.
+            Some((start, last)) if line == [separator.byte()] => {
+                *last = &record[*start..offset + line.len()];
+            }
+            _ => lines.push((offset, line)),
+        }
+
+        offset += line.len();
     }
 
-    Ok((reader, encoding))
+    lines
 }
 
+/// Reads the lines available from `reader`, along with their byte offsets within the file.
+/// `position` is the offset `reader` is at, it's advanced past the lines read. `encoding` is
+/// changed if `redetector` finds the lines to be in another one.
 async fn read_data_from_file(
     reader: &mut BufReader<File>,
+    position: &mut u64,
     restrict_row_number: bool,
-    encoding: &'static Encoding,
-) -> Result<Vec<String>, Error> {
+    encoding: &mut &'static Encoding,
+    options: &ReadOptions,
+    mut redetector: Option<&mut EncodingRedetector>,
+    // Stops once the line reaching this byte offset has been read, rather than at the end.
+    until: Option<u64>,
+) -> Result<(Vec<String>, Vec<u64>, Vec<(usize, usize)>), Error> {
+    let ReadOptions { max_line_length, record_separator: separator, .. } = *options;
     let mut read_data = VecDeque::new();
+    let mut offsets = VecDeque::new();
+    // By the number of lines read before them, some of which may have been dropped since.
+    let mut truncated = Vec::new();
 
     let mut lines = 0;
 
     loop {
         let mut buf = Vec::new();
-        let bytes_read = reader.read_until(b'\n', &mut buf).await?;
+        let bytes_read = reader.read_until(separator.map_or(b'\n', |s| s.byte()), &mut buf).await?;
 
         if bytes_read == 0 {
             break;
         }
 
-        let (output, _encoding, _contains_invalid_content) = encoding.decode(buf.as_slice());//encoding_rs::UTF_8.decode(buf.as_slice());
+        for (line_offset, line) in split_record(&buf, separator) {
+            let line_position = *position + line_offset as u64;
+            let (mut output, _encoding, contains_invalid_content) = encoding.decode(line);//encoding_rs::UTF_8.decode(buf.as_slice());
 
-        lines += 1;
+            if let Some(redetector) = redetector.as_deref_mut() {
+                if let Some(switched) = redetector.line(line, contains_invalid_content, encoding) {
+                    debug!("Switching from {} to {} at byte {line_position}", encoding.name(), switched.name());
+                    *encoding = switched;
+                    redetector.switches.push((line_position, switched));
+                    output = switched.decode(line).0;
+                }
+            }
 
-        if lines % 100000 == 0 {
-            debug!("{lines} lines read. Vec capacity: {}", read_data.capacity());
-        }
+            lines += 1;
 
-        if restrict_row_number && lines > MAX_ROWS {
-            read_data.pop_front();
+            if lines % 100000 == 0 {
+                debug!("{lines} lines read. Vec capacity: {}", read_data.capacity());
+            }
+
+            if restrict_row_number && lines > MAX_ROWS {
+                read_data.pop_front();
+                offsets.pop_front();
+            }
+
+            let mut output = output.into_owned();
+
+            if let Some(length) = truncate_line(&mut output, max_line_length) {
+                truncated.push((lines - 1, length));
+            }
+
+            read_data.push_back(output);
+            //read_data.push_back(String::from_utf8(buf)?)
+            offsets.push_back(line_position);
         }
 
-        read_data.push_back(output.to_string());
-        //read_data.push_back(String::from_utf8(buf)?)
+        *position += bytes_read as u64;
+
+        if until.is_some_and(|until| *position >= until) {
+            break;
+        }
     }
 
     read_data.shrink_to_fit();
 
-    Ok(read_data.into())
+    let dropped = lines - read_data.len() as u64;
+    let truncated = truncated
+        .into_iter()
+        .filter_map(|(n, length): (u64, usize)| Some((n.checked_sub(dropped)? as usize, length)))
+        .collect();
+
+    Ok((read_data.into(), offsets.into(), truncated))
+}
+
+/// How the readers read a file, from the file's and the application's settings.
+#[derive(Debug, Clone, Copy)]
+struct ReadOptions {
+    encoding: Option<&'static Encoding>,
+    /// Used instead of the detected encoding when the detection isn't confident.
+    fallback_encoding: Option<&'static Encoding>,
+    /// Detect the encoding again if lines part way through the file fail to decode.
+    redetect_encoding: bool,
+    /// Read records separated by this, rather than lines.
+    record_separator: Option<RecordSeparator>,
+    max_line_length: usize,
+    buffer_size: usize,
+    /// Whether to restrict a large file, rather than asking.
+    restrict_decision: Option<bool>,
+    /// Byte offset to start reading a regular file at.
+    start_offset: Option<u64>,
+    /// Read the lines of about this many bytes from the start offset once, rather than all of
+    /// them and following the file.
+    window: Option<u64>,
+}
+
+/// Reads a non-seekable stream, sending lines as they become available.
+async fn stream_reader(
+    file_path: &Path,
+    output: ReaderOutput,
+    repaint: Repainter,
+    poll_now: Arc<Notify>,
+    options: ReadOptions,
+) -> Result<(), Error> {
+    let ReadOptions { encoding, max_line_length, buffer_size, record_separator, .. } = options;
+
+    // Detecting the encoding requires reading ahead, which could block forever on a stream.
+    let (encoding, detection) = match encoding {
+        Some(e) => (e, EncodingDetection::Given),
+        None => (encoding_rs::UTF_8, EncodingDetection::Assumed),
+    };
+    output.send(LogFileMessage::SetEncoding(encoding, detection)).map_err(send_err_to_error)?;
+
+    // Opening a FIFO blocks until there's a writer.
+    let mut reader = BufReader::with_capacity(buffer_size, File::open(file_path).await?);
+    let mut batch = Vec::new();
+    let mut offsets = Vec::new();
+    let mut truncated = Vec::new();
+    // Bytes read from the stream so far, there's no position to ask it for.
+    let mut position = 0;
+
+    let separator = record_separator.map_or(b'\n', |s| s.byte());
+
+    loop {
+        let mut buf = Vec::new();
+        let bytes_read = reader.read_until(separator, &mut buf).await?;
+
+        for (line_offset, line) in split_record(&buf, record_separator).into_iter().filter(|_| bytes_read > 0) {
+            let (decoded, _encoding, _contains_invalid_content) = encoding.decode(line);
+            let mut decoded = decoded.into_owned();
+
+            if let Some(length) = truncate_line(&mut decoded, max_line_length) {
+                truncated.push((batch.len(), length));
+            }
+
+            batch.push(decoded);
+            offsets.push(position + line_offset as u64);
+        }
+
+        position += bytes_read as u64;
+
+        // Send once every finished line currently available has been read, rather than waiting for
+        // the rest of an unfinished one, which is read once finished or the writers close.
+        if (bytes_read == 0 || !reader.buffer().contains(&separator)) && !batch.is_empty() {
+            output.send(LogFileMessage::FileData(std::mem::take(&mut batch), std::mem::take(&mut offsets), std::mem::take(&mut truncated))).map_err(send_err_to_error)?;
+            repaint.data();
+        }
+
+        if bytes_read == 0 {
+            // All writers have closed the stream, wait for new ones.
+            tokio::select! {
+                _ = tokio::time::sleep(STREAM_POLL_INTERVAL) => (),
+                _ = poll_now.notified() => (),
+            }
+        }
+    }
+}
+
+/// Reads a member of an archive. Members can't be watched for changes, so it's read only once.
+fn archive_reader(
+    archive: &Path,
+    member: &str,
+    output: ReaderOutput,
+    repaint: Repainter,
+    options: ReadOptions,
+) -> Result<(), Error> {
+    use std::io::{BufRead, Read};
+
+    let ReadOptions { encoding, fallback_encoding, max_line_length, buffer_size, record_separator, restrict_decision, .. } = options;
+
+    let result = crate::archive::with_member(archive, member, |contents, size| {
+        // Restricted like a file of the member's size would be.
+        let restrict_filesize = match restrict_decision {
+            Some(restrict) if size > MAX_FILE_SIZE => {
+                output.send(LogFileMessage::RestrictFileSize(restrict)).map_err(send_err_to_error)?;
+                restrict
+            }
+            None if size > MAX_FILE_SIZE => {
+                let (tx, rx) = channel();
+                output.send(LogFileMessage::ShowRestrictFileSizeDialog(size, tx)).map_err(send_err_to_error)?;
+                repaint.now();
+
+                rx.recv()?
+            }
+            _ => {
+                output.send(LogFileMessage::RestrictFileSize(true)).map_err(send_err_to_error)?;
+                true
+            }
+        };
+
+        let mut contents = std::io::BufReader::with_capacity(buffer_size, contents);
+        // Bytes of the member skipped, which can only be done by decompressing them.
+        let mut position = 0;
+
+        if restrict_filesize && size > MAX_FILE_SIZE {
+            position = std::io::copy(&mut (&mut contents).take(size - MAX_FILE_SIZE), &mut std::io::sink())?;
+            // Along with the rest of the line the last skipped byte is part of.
+            position += contents.read_until(b'\n', &mut Vec::new())? as u64;
+            output.send(LogFileMessage::SkippedTo(position, size)).map_err(send_err_to_error)?;
+        }
+
+        let mut sample = Vec::new();
+        (&mut contents).take(ENCODING_SAMPLE_SIZE).read_to_end(&mut sample)?;
+
+        let (encoding, detection) = match encoding {
+            Some(e) => (e, EncodingDetection::Given),
+            None => detect_encoding(&sample, (sample.len() as u64) < ENCODING_SAMPLE_SIZE, fallback_encoding),
+        };
+        output.send(LogFileMessage::SetEncoding(encoding, detection)).map_err(send_err_to_error)?;
+        output.send(LogFileMessage::EncodingSample(sample.clone())).map_err(send_err_to_error)?;
+
+        let mut reader = Read::chain(std::io::Cursor::new(sample), contents);
+        let mut batch = Vec::new();
+        let mut offsets = Vec::new();
+        let mut truncated = Vec::new();
+
+        loop {
+            let mut buf = Vec::new();
+            let bytes_read = reader.read_until(record_separator.map_or(b'\n', |s| s.byte()), &mut buf)?;
+
+            for (line_offset, line) in split_record(&buf, record_separator).into_iter().filter(|_| bytes_read > 0) {
+                let (decoded, _encoding, _contains_invalid_content) = encoding.decode(line);
+                let mut decoded = decoded.into_owned();
+
+                if let Some(length) = truncate_line(&mut decoded, max_line_length) {
+                    truncated.push((batch.len(), length));
+                }
+
+                batch.push(decoded);
+                offsets.push(position + line_offset as u64);
+            }
+
+            position += bytes_read as u64;
+
+            // Show the lines in batches while decompressing, rather than all at the end.
+            if (bytes_read == 0 && !batch.is_empty()) || batch.len() >= ARCHIVE_BATCH_LINES {
+                output.send(LogFileMessage::FileData(std::mem::take(&mut batch), std::mem::take(&mut offsets), std::mem::take(&mut truncated))).map_err(send_err_to_error)?;
+                repaint.data();
+            }
+
+            if bytes_read == 0 {
+                return Ok(());
+            }
+        }
+    });
+
+    if let Err(e) = result {
+        let msg = format!("Unable to read {member} from {}: {e}", archive.display());
+        output.send(LogFileMessage::Error(e)).map_err(send_err_to_error)?;
+        repaint.now();
+        return Err(msg.into());
+    }
+
+    Ok(())
+}
+
+/// Sends the encoding switches found while reading, once the lines they refer to have been sent.
+fn send_encoding_switches(output: &ReaderOutput, redetector: Option<&mut EncodingRedetector>) -> Result<(), Error> {
+    for (offset, encoding) in redetector.map(|r| std::mem::take(&mut r.switches)).unwrap_or_default() {
+        output.send(LogFileMessage::EncodingSwitched(offset, encoding)).map_err(send_err_to_error)?;
+    }
+
+    Ok(())
 }
 
 async fn reader(
     file_path: &Path,
-    output: Sender<LogFileMessage>,
-    ctx: egui::Context,
-    encoding: Option<&'static Encoding>,
+    output: ReaderOutput,
+    repaint: Repainter,
+    watcher: FileWatcher,
+    poll_now: Arc<Notify>,
+    options: ReadOptions,
 ) -> Result<(), Error> {
+    let ReadOptions { encoding, fallback_encoding, redetect_encoding, buffer_size, restrict_decision, start_offset, window, .. } = options;
+    let start_offset = start_offset.unwrap_or(0);
     let filename = file_path.to_string_lossy();
     debug!("Opening {filename}");
 
@@ -866,103 +6229,185 @@ async fn reader(
         }
     };
 
-    let restrict_filesize = if file_meta.len() > MAX_FILE_SIZE {
-        debug!("File big ({}), open window.", file_meta.len());
-        let (tx, rx) = channel();
-        output.send(LogFileMessage::ShowRestrictFileSizeDialog(
-            file_meta.len(),
-            tx,
-        )).map_err(send_err_to_error)?;
-        ctx.request_repaint();
+    match SourceKind::from_metadata(&file_meta) {
+        SourceKind::Regular => (),
+        SourceKind::Directory => {
+            let msg = format!("{filename} is a directory, open a file within it instead.");
+            output.send(LogFileMessage::Error(msg.as_str().into())).map_err(send_err_to_error)?;
+            repaint.now();
+            return Err(msg.into());
+        }
+        SourceKind::Unsupported(kind) => {
+            let msg = format!("{filename} is a {kind}, which can't be opened.");
+            output.send(LogFileMessage::Error(msg.as_str().into())).map_err(send_err_to_error)?;
+            repaint.now();
+            return Err(msg.into());
+        }
+        SourceKind::Stream => {
+            debug!("{filename} is a stream, reading it without size restrictions");
+            // Restricting the rows still applies, a stream could go on forever.
+            output.send(LogFileMessage::RestrictFileSize(true)).map_err(send_err_to_error)?;
+            output.send(LogFileMessage::Streaming).map_err(send_err_to_error)?;
+            return stream_reader(file_path, output, repaint, poll_now, options).await;
+        }
+    }
+
+    if start_offset > file_meta.len() {
+        let msg = format!(
+            "Can't start reading {filename} at byte {start_offset}, it's only {} large.",
+            humanreadable_bytes(file_meta.len())
+        );
+        output.send(LogFileMessage::Error(msg.as_str().into())).map_err(send_err_to_error)?;
+        repaint.now();
+        return Err(msg.into());
+    }
+
+    // Scrubbing reads the window once, there's nothing to restrict or follow.
+    if let Some(window) = window {
+        let (mut reader, mut encoding, detection) = init_reader(file_path, false, start_offset, encoding, fallback_encoding, buffer_size).await?;
+        let mut position = reader.stream_position().await?;
+        output.send(LogFileMessage::SetEncoding(encoding, detection)).map_err(send_err_to_error)?;
 
-        rx.recv()?
-    } else {
-        output.send(LogFileMessage::RestrictFileSize(true)).map_err(send_err_to_error)?;
+        let (data, offsets, truncated) = read_data_from_file(&mut reader, &mut position, false, &mut encoding, &options, None, Some(start_offset + window)).await?;
+        output.send(LogFileMessage::FileData(data, offsets, truncated)).map_err(send_err_to_error)?;
+        repaint.now();
 
-        true
+        return Ok(());
+    }
+
+    // Only the part of the file after the offset is read.
+    let size = file_meta.len() - start_offset;
+
+    let restrict_filesize = match restrict_decision {
+        Some(restrict) if size > MAX_FILE_SIZE => {
+            debug!("File big ({size}), restricted as previously decided: {restrict}");
+            output.send(LogFileMessage::RestrictFileSize(restrict)).map_err(send_err_to_error)?;
+
+            restrict
+        }
+        None if size > MAX_FILE_SIZE => {
+            debug!("File big ({size}), open window.");
+            let (tx, rx) = channel();
+            output.send(LogFileMessage::ShowRestrictFileSizeDialog(
+                size,
+                tx,
+            )).map_err(send_err_to_error)?;
+            repaint.now();
+
+            rx.recv()?
+        }
+        _ => {
+            output.send(LogFileMessage::RestrictFileSize(true)).map_err(send_err_to_error)?;
+
+            true
+        }
     };
 
+    match read_sample(file_path).await {
+        Ok(sample) => {
+            output.send(LogFileMessage::EncodingSample(sample)).map_err(send_err_to_error)?;
+        }
+        Err(e) => error!("Unable to read encoding sample from {filename}: {e:?}"),
+    }
+
     let start = Instant::now();
     debug!("Reading from {filename}");
 
-    let (mut reader, mut encoding) = init_reader(&file_path, restrict_filesize, encoding).await?;
+    let (mut reader, mut encoding, detection) = init_reader(file_path, restrict_filesize, start_offset, encoding, fallback_encoding, buffer_size).await?;
+    // Restricted mode and a start offset start reading somewhere within the file.
+    let mut position = reader.stream_position().await?;
 
-    output.send(LogFileMessage::SetEncoding(Some(encoding))).map_err(send_err_to_error)?;
-    // TODO: Implement way to choose between recommended and poll? E.g. in case of file paths that
-    // don't quite support inotify etc.
+    output.send(LogFileMessage::SetEncoding(encoding, detection)).map_err(send_err_to_error)?;
 
-    let (tx, rx) = channel();
-    let mut watcher = notify::recommended_watcher(move |res| {
-        match res {
-            Ok(event) => {
-                match tx.send(event) {
-                    Ok(_) => (),
-                    Err(e) => panic!("Unable to send event: {e:?}"),
-                };
-            }
-            Err(e) => panic!("Unable to watch file: {e:?}"),
-        };
-    })?;
+    if restrict_filesize && size > MAX_FILE_SIZE && position > start_offset {
+        output.send(LogFileMessage::SkippedTo(position, file_meta.len())).map_err(send_err_to_error)?;
+    }
+
+    // The watcher is shared between all open files, only events concerning this file are received.
+    // The file is still read if it can't be watched, it's just not tailed unless polled manually.
+    let events = match watcher.register(file_path) {
+        Ok(events) => Some(events),
+        Err(e) => {
+            output.send(LogFileMessage::NotWatching(e.to_string())).map_err(send_err_to_error)?;
+            None
+        }
+    };
 
-    watcher.watch(
-        file_path.to_path_buf().parent().unwrap_or(Path::new(".")),
-        RecursiveMode::NonRecursive,
-    )?;
+    let mut redetector = redetect_encoding.then(EncodingRedetector::default);
 
     debug!("Read initial data from file");
     //let preexisting_data =
-    match read_data_from_file(&mut reader, restrict_filesize, encoding).await {
-        Ok(preexisting_data) => {
+    match read_data_from_file(&mut reader, &mut position, restrict_filesize, &mut encoding, &options, redetector.as_mut(), None).await {
+        Ok((preexisting_data, offsets, truncated)) => {
             if !preexisting_data.is_empty() {
-                output.send(LogFileMessage::FileData(preexisting_data)).map_err(send_err_to_error)?;
-                ctx.request_repaint();
+                output.send(LogFileMessage::FileData(preexisting_data, offsets, truncated)).map_err(send_err_to_error)?;
+                send_encoding_switches(&output, redetector.as_mut())?;
+                repaint.data();
             }
         },
         Err(e) => {
             output.send(LogFileMessage::Error(e)).map_err(send_err_to_error)?;
-            ctx.request_repaint();
+            repaint.now();
         }
     }
 
     debug!("Took {:?} to create reader and read existing data", Instant::now().duration_since(start));
 
-    while let Ok(evt) = rx.recv() {
-        if evt
-            .paths
-            .iter()
-            .filter_map(|p| p.file_name())
-            .filter(|s| s == &file_path.file_name().unwrap_or(OsStr::new("")))
-            .collect::<Vec<_>>()
-            .is_empty()
-        {
-            continue;
-        }
+    if let Ok(modified) = file_meta.modified() {
+        output.send(LogFileMessage::Modified(modified)).map_err(send_err_to_error)?;
+    }
+
+    let mut events = events;
+
+    loop {
+        // A manual poll reads the file just as if the watcher reported its data changed.
+        let kind = tokio::select! {
+            evt = next_event(events.as_mut()) => match evt {
+                Some(evt) => evt.kind,
+                None => break,
+            },
+            _ = poll_now.notified() => EventKind::Modify(ModifyKind::Data(DataChange::Any)),
+        };
 
-        match evt.kind {
-            EventKind::Create(_) => {
-                (reader, encoding) = init_reader(&file_path, restrict_filesize, Some(encoding)).await?;
+        match kind {
+            EventKind::Create(_) | EventKind::Modify(ModifyKind::Name(RenameMode::To)) => {
+                // The file was replaced, e.g. rotated, the offset doesn't apply to the new one.
+                (reader, encoding, _) = init_reader(file_path, restrict_filesize, 0, Some(encoding), None, buffer_size).await?;
+                position = reader.stream_position().await?;
+                output.send(LogFileMessage::SourceRestored).map_err(send_err_to_error)?;
+                repaint.now();
+            }
+            EventKind::Remove(_) | EventKind::Modify(ModifyKind::Name(RenameMode::From)) => {
+                debug!("{filename} was deleted or moved away");
+                output.send(LogFileMessage::SourceDeleted).map_err(send_err_to_error)?;
+                repaint.now();
             }
             EventKind::Modify(kind) => {
                 match kind {
                     ModifyKind::Data(_) => {
-                        match read_data_from_file(&mut reader, restrict_filesize, encoding).await {
-                            Ok(data) => {
+                        match read_data_from_file(&mut reader, &mut position, restrict_filesize, &mut encoding, &options, redetector.as_mut(), None).await {
+                            Ok((data, offsets, truncated)) => {
                                 if !data.is_empty() {
-                                    output.send(LogFileMessage::FileData(data)).map_err(send_err_to_error)?;
-                                    ctx.request_repaint();
+                                    output.send(LogFileMessage::FileData(data, offsets, truncated)).map_err(send_err_to_error)?;
+                                    send_encoding_switches(&output, redetector.as_mut())?;
+
+                                    if let Ok(modified) = tokio::fs::metadata(file_path).await.and_then(|m| m.modified()) {
+                                        output.send(LogFileMessage::Modified(modified)).map_err(send_err_to_error)?;
+                                    }
+
+                                    // No point in repainting while the data is left unread.
+                                    repaint.data();
                                 }
                             },
                             Err(e) => {
                                 output.send(LogFileMessage::Error(e)).map_err(send_err_to_error)?;
-                                ctx.request_repaint();
+                                repaint.now();
                             }
                         }
                     }
-                    ModifyKind::Metadata(k) => {
-                        if k == MetadataKind::Any {
-                            // When watching a file directly, these event can mean that a file has
-                            // been deleted.
-                        }
+                    ModifyKind::Metadata(MetadataKind::Any) => {
+                        // When watching a file directly, these event can mean that a file has
+                        // been deleted.
                     }
                     _ => (),
                 }
@@ -974,11 +6419,94 @@ async fn reader(
     Ok(())
 }
 
+/// The next event from the watcher, never if the file isn't watched.
+async fn next_event(events: Option<&mut WatchRegistration>) -> Option<notify::Event> {
+    match events {
+        Some(events) => events.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
 #[cfg(test)]
 mod test {
     // TODO: Make code more test-able
-    // TODO: Some tests for the file-reading parts and the RowModifier::generate_line
-    use super::{Filter, Search};
+    // TODO: Some tests for the file-reading parts
+    use super::{
+        expand_tabs, filter_entries, find_entry_starts, humanreadable_count, markdown_code_block, read_data_from_file, split_record, stream_reader, truncate_line, whitespace_ranges,
+        AnsiEscapes, ColumnRange, DisplayOptions, EncodingRedetector, Filter, FilterControl, Jump, Line, LogFile, LogFileMessage, Matcher, MatchStyle, QuickSearch, ReadOptions, ReaderOutput, RenderOptions, Repainter, RewriteRule, RowHighlight,
+        RecordSeparator, RowModifier, Search, SearchMode, TabStops, Throughput, REDETECT_THRESHOLD,
+    };
+    use std::path::PathBuf;
+    use std::time::{Duration, Instant};
+    use crate::format::{FormatPatterns, FormatProfile};
+    use crate::severity::Severity;
+    use eframe::egui::{self, Color32, TextFormat};
+
+    /// Filters all lines of `file` and waits for it to be done.
+    fn wait_for_filtering(file: &mut LogFile) {
+        file.start_filtering(egui::Context::default(), FormatProfile::default());
+        let job = file.filter_job.as_ref().expect("Should be filtering");
+        let outcome = job.receiver.recv().expect("Filtering should finish");
+        file.apply_filter_outcome(outcome);
+    }
+
+    /// Receives `message` as if the reader of `file` had sent it.
+    fn feed(file: &mut LogFile, message: LogFileMessage) {
+        let sender = file.sender.get_or_insert_with(|| {
+            let (sender, receiver) = std::sync::mpsc::channel();
+            file.receiver = Some(receiver);
+            ReaderOutput { sender, epoch: file.epoch }
+        });
+
+        sender.send(message).expect("Receiver should be alive");
+        file.receive_messages(&FormatProfile::default());
+    }
+
+    /// Receives `lines` as if the reader of `file` had read them after the lines read so far.
+    fn feed_lines(file: &mut LogFile, lines: &[&str]) {
+        let mut offset = file.line_offsets.last().zip(file.lines.last()).map_or(0, |(offset, line)| offset + line.len() as u64);
+        let offsets = lines
+            .iter()
+            .map(|line| {
+                offset += line.len() as u64;
+                offset - line.len() as u64
+            })
+            .collect();
+
+        feed(file, LogFileMessage::FileData(lines.iter().map(|line| line.to_string()).collect(), offsets, Vec::new()));
+    }
+
+    /// Options reading a file as it is, without detecting its encoding or limiting its lines.
+    fn read_options() -> ReadOptions {
+        ReadOptions {
+            encoding: None,
+            fallback_encoding: None,
+            redetect_encoding: false,
+            record_separator: None,
+            max_line_length: 0,
+            buffer_size: 4096,
+            restrict_decision: None,
+            start_offset: None,
+            window: None,
+        }
+    }
+
+    /// The text of each chunk of `line` with its color, None for chunks in the line's format.
+    fn chunk_colors(line: &Line) -> Vec<(&str, Option<Color32>)> {
+        line.chunks
+            .as_ref()
+            .expect("Line should be chunked")
+            .iter()
+            .map(|c| (c.text.as_str(), c.format.as_ref().map(|f| f.color)))
+            .collect()
+    }
+
+    fn filtering(search: Search) -> RowModifier {
+        RowModifier {
+            filter: Filter::new(search),
+            ..Default::default()
+        }
+    }
 
     #[test]
     pub fn test_filter_casesensitive() {
@@ -1000,10 +6528,15 @@ mod test {
                 changed: false,
                 regex: None,
                 string: String::from("which"),
-                is_regex: false,
+                mode: SearchMode::Substring,
                 case_insensitive: false,
+                ..Default::default()
             },
+            columns: None,
+            exclude: Search::default(),
             changed: false,
+            jump: None,
+            position: None,
         };
 
         filter.search.regex = Some(filter.search.create_regex().expect("Should result in a valid regex"));
@@ -1035,10 +6568,15 @@ mod test {
                 changed: false,
                 regex: None,
                 string: String::from("which"),
-                is_regex: false,
+                mode: SearchMode::Substring,
                 case_insensitive: true,
+                ..Default::default()
             },
+            columns: None,
+            exclude: Search::default(),
             changed: false,
+            jump: None,
+            position: None,
         };
 
         filter.search.regex = Some(filter.search.create_regex().expect("Should result in a valid regex"));
@@ -1070,10 +6608,15 @@ mod test {
                 changed: false,
                 regex: None,
                 string: String::from(r#"(which|should\b)"#),
-                is_regex: true,
+                mode: SearchMode::Regex,
                 case_insensitive: true,
+                ..Default::default()
             },
+            columns: None,
+            exclude: Search::default(),
             changed: false,
+            jump: None,
+            position: None,
         };
 
         filter.search.regex = Some(filter.search.create_regex().expect("Should result in a valid regex"));
@@ -1104,10 +6647,15 @@ mod test {
                 changed: false,
                 regex: None,
                 string: String::from(r#"(which|should\b)"#),
-                is_regex: true,
+                mode: SearchMode::Regex,
                 case_insensitive: false,
+                ..Default::default()
             },
+            columns: None,
+            exclude: Search::default(),
             changed: false,
+            jump: None,
+            position: None,
         };
 
         filter.search.regex = Some(filter.search.create_regex().expect("Should result in a valid regex"));
@@ -1117,4 +6665,894 @@ mod test {
         assert_eq!(filtered_lines, expected_lines);
         assert_ne!(filtered_lines, lines);
     }
+
+    #[test]
+    pub fn test_filter_columns() {
+        let lines = vec![
+            String::from("2024-01-01 12:00:00 ERROR Connection lost\n"),
+            String::from("2024-01-01 12:00:01 INFO  ERROR count reset\n"),
+            String::from("2024-01-01 ERROR\n"),
+        ];
+
+        let mut filter = Filter::new(Search::new("ERROR", SearchMode::Substring, false));
+        filter.columns = Some(ColumnRange { start: 20, end: 25 });
+
+        let filtered_lines = filter.filter(&lines).expect("Result should've been filtered");
+
+        assert_eq!(filtered_lines, vec![lines[0].clone()]);
+        assert_eq!(filter.find_ranges(&lines[0]), vec![20..25]);
+        assert_eq!(ColumnRange { start: 11, end: 16 }.byte_range("åäö 12:00 ERROR"), None);
+        assert_eq!(ColumnRange { start: 10, end: 15 }.byte_range("åäö 12:00 ERROR"), Some(13..18));
+    }
+
+    #[test]
+    pub fn test_filter_exclude() {
+        let lines = vec![
+            String::from("ERROR Connection lost\n"),
+            String::from("ERROR Health check failed\n"),
+            String::from("INFO Connection established\n"),
+        ];
+
+        let mut filter = Filter::new(Search::new("ERROR", SearchMode::Substring, false));
+        filter.exclude = Search::new("health", SearchMode::Substring, true);
+
+        let filtered_lines = filter.filter(&lines).expect("Result should've been filtered");
+        assert_eq!(filtered_lines, vec![lines[0].clone()]);
+        assert!(filter.find_ranges(&lines[1]).is_empty());
+
+        // An empty exclusion excludes nothing.
+        filter.exclude = Search::default();
+        let filtered_lines = filter.filter(&lines).expect("Result should've been filtered");
+        assert_eq!(filtered_lines, lines[..2].to_vec());
+    }
+
+    #[test]
+    pub fn test_filter_matched_texts() {
+        let line = "GET /api request_id=abc123 took 5ms, retry of request_id=def456\r\n";
+
+        let filter = Filter::new(Search::new(r"request_id=(\w+)", SearchMode::Regex, false));
+        assert_eq!(filter.matched_texts(line, 1), vec!["abc123", "def456"]);
+        assert_eq!(filter.matched_texts(line, 0), vec!["request_id=abc123", "request_id=def456"]);
+        assert!(filter.matched_texts(line, 2).is_empty());
+
+        let filter = Filter::new(Search::new("REQUEST_ID", SearchMode::Substring, true));
+        assert_eq!(filter.matched_texts(line, 0), vec!["request_id", "request_id"]);
+    }
+
+    #[test]
+    pub fn test_search_fuzzy() {
+        let search = Search::new("npe", SearchMode::Fuzzy, true);
+
+        assert!(search.is_ready());
+        assert!(search.is_match("java.lang.NullPointerException"));
+        assert!(!search.is_match("Connection refused"));
+        assert!(search.regex.is_none());
+
+        let ranges = search.find_ranges("åä NullPointerException");
+        let matched: String = ranges.iter().map(|r| &"åä NullPointerException"[r.clone()]).collect();
+        assert_eq!(matched.to_lowercase(), "npe");
+    }
+
+    #[test]
+    pub fn test_truncate_line() {
+        let line = String::from("åäö and some more text");
+
+        let mut truncated = line.clone();
+        assert_eq!(truncate_line(&mut truncated, 0), None);
+        assert_eq!(truncate_line(&mut truncated, line.len()), None);
+        assert_eq!(truncated, line);
+
+        // Cutting in the middle of 'ä' should back up to the previous char boundary.
+        assert_eq!(truncate_line(&mut truncated, 3), Some(line.len()));
+        assert_eq!(truncated, "å");
+    }
+
+    #[test]
+    pub fn test_expand_tabs() {
+        let (expanded, stops) = expand_tabs("a\tbc\td", 4);
+        assert_eq!(expanded, "a   bc  d");
+        // The offsets of 'b' and 'd' in the original text.
+        assert_eq!(stops.map(2), 4);
+        assert_eq!(stops.map(5), 8);
+
+        assert_eq!(expand_tabs("a\tb", 0).0, "a\tb");
+    }
+
+    #[test]
+    pub fn test_from_lines() {
+        let mut file = LogFile::from_lines("in memory", vec![String::from("INFO started\n"), String::from("ERROR failed\n")]);
+        file.row_modifier.filter = Filter::new(Search::new("ERROR", SearchMode::Substring, false));
+        wait_for_filtering(&mut file);
+
+        assert!(file.is_in_memory());
+        assert!(file.snapshot.is_none());
+        assert!(file.thread.is_none(), "Shouldn't read anything");
+        assert_eq!(file.lines.len(), 2);
+        assert_eq!(file.filter_cache, Some(vec![1]));
+    }
+
+    #[test]
+    pub fn test_snapshot() {
+        let mut file = LogFile::new(PathBuf::from("app.log"), vec![String::from("first\n"), String::from("second\n")], DisplayOptions::default());
+        file.row_modifier.filter = Filter::new(Search::new("second", SearchMode::Substring, false));
+
+        let mut snapshot = file.snapshot();
+        assert!(snapshot.title().starts_with("app.log (snapshot @ "));
+        assert_eq!(snapshot.row_modifier.filter.search.string, "second");
+
+        snapshot.snapshot.as_mut().expect("Should be a snapshot").notes.push((2, String::from("Look here")));
+
+        // Unlike those of other files, the lines are persisted.
+        let json = serde_json::to_string(&snapshot).expect("Should serialize");
+        let restored: LogFile = serde_json::from_str(&json).expect("Should deserialize");
+        let restored = restored.snapshot.expect("Should still be a snapshot");
+        assert_eq!(restored.lines.as_slice(), ["first\n", "second\n"]);
+        assert_eq!(restored.notes, vec![(2, String::from("Look here"))]);
+
+        // Only the last lines of large snapshots are persisted, keeping their line numbers.
+        let lines: Vec<String> = (0..5).map(|i| i.to_string().repeat(1024 * 1024)).collect();
+        let snapshot = LogFile::new(PathBuf::from("app.log"), lines, DisplayOptions::default()).snapshot();
+        let json = serde_json::to_string(&snapshot).expect("Should serialize");
+        let restored: LogFile = serde_json::from_str(&json).expect("Should deserialize");
+        let restored = restored.snapshot.expect("Should still be a snapshot");
+        assert_eq!(restored.lines.len(), 4);
+        assert!(restored.lines[0].starts_with('1'));
+        assert_eq!(restored.dropped_lines, 1);
+    }
+
+    #[test]
+    pub fn test_throughput() {
+        let start = Instant::now();
+        let mut throughput = Throughput::default();
+        assert_eq!(throughput.rate(start), None);
+
+        throughput.record(start, 100, 1000);
+        // A single batch is averaged over a second.
+        assert_eq!(throughput.rate(start), Some((100.0, 1000.0)));
+
+        throughput.record(start + Duration::from_secs(2), 300, 3000);
+        assert_eq!(throughput.rate(start + Duration::from_secs(4)), Some((100.0, 1000.0)));
+
+        // The first batch has left the window.
+        assert_eq!(throughput.rate(start + Duration::from_secs(11)), Some((300.0 / 9.0, 3000.0 / 9.0)));
+        assert_eq!(throughput.rate(start + Duration::from_secs(13)), None);
+    }
+
+    #[test]
+    pub fn test_show_whitespace() {
+        let (expanded, stops) = expand_tabs("a\tb \t", 4);
+        assert_eq!(whitespace_ranges(&expanded, &stops), vec![1..4, 5..8]);
+        assert_eq!(whitespace_ranges("a\tb", &TabStops::default()), vec![1..2]);
+        assert!(whitespace_ranges("a b", &TabStops::default()).is_empty());
+
+        let line = RowModifier::default().generate_line_with_format(
+            "a\tb  \n",
+            &TextFormat::default(),
+            RenderOptions {
+                ansi: AnsiEscapes::Keep,
+                tab_width: 4,
+                show_whitespace: true,
+                logfmt: false,
+            },
+        );
+        assert_eq!(line.full, "a   b  ");
+
+        let chunks: Vec<(&str, bool)> = line
+            .chunks
+            .as_ref()
+            .expect("Whitespace should be split into chunks")
+            .iter()
+            .map(|c| (c.text.as_str(), c.format.is_some()))
+            .collect();
+        assert_eq!(chunks, vec![("a", false), ("   ", true), ("b", false), ("  ", true)]);
+    }
+
+    #[test]
+    pub fn test_reload_with_encoding_discards_previous_reader() {
+        let mut file = LogFile::new("test.log".into(), vec![String::from("Decoded as UTF-8\n")], DisplayOptions::default());
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let sender = ReaderOutput { sender, epoch: file.epoch };
+        file.receiver = Some(receiver);
+        file.sender = Some(sender.clone());
+
+        file.reload_with_encoding(encoding_rs::WINDOWS_1252);
+
+        assert_eq!(file.encoding, Some(encoding_rs::WINDOWS_1252));
+        assert!(file.lines.is_empty());
+        // Whatever the previous reader still sends goes nowhere.
+        assert!(sender.send(LogFileMessage::FileData(vec![String::from("Stale\n")], vec![0], Vec::new())).is_err());
+    }
+
+    #[tokio::test]
+    pub async fn test_read_window() {
+        let path = std::env::temp_dir().join(format!("logglance_test_read_window_{}.log", std::process::id()));
+        std::fs::write(&path, "first\nsecond\nthird\nfourth\n").expect("Should be able to write test file");
+
+        let options = read_options();
+        let mut reader = tokio::io::BufReader::new(tokio::fs::File::open(&path).await.expect("Should open test file"));
+        let mut position = 0;
+        let mut encoding = encoding_rs::UTF_8;
+
+        // Byte 8 is within the second line, which is read to its end.
+        let read = read_data_from_file(&mut reader, &mut position, false, &mut encoding, &options, None, Some(8)).await;
+        drop(reader);
+        let _ = std::fs::remove_file(&path);
+        let (lines, offsets, truncated) = read.expect("Should read the window");
+
+        assert_eq!(lines, vec![String::from("first\n"), String::from("second\n")]);
+        assert_eq!(offsets, vec![0, 6]);
+        assert!(truncated.is_empty());
+        assert_eq!(position, 13);
+    }
+
+    #[test]
+    pub fn test_line_numbers_after_dropping_lines() {
+        let lines = ["INFO a\n", "ERROR b\n", "INFO c\n", "ERROR d\n"].map(String::from).to_vec();
+        let mut file = LogFile::new("test.log".into(), lines, DisplayOptions::default());
+        file.row_modifier = filtering(Search::new("ERROR", SearchMode::Substring, false));
+        wait_for_filtering(&mut file);
+        assert_eq!(file.filter_cache, Some(vec![1, 3]));
+
+        file.drop_oldest_lines(2);
+
+        // The rows shown are the same lines, numbered as in the file.
+        assert_eq!(file.filter_cache, Some(vec![1]));
+        assert_eq!(file.line_number(0), 3);
+        assert_eq!(file.line_number(1), 4);
+    }
+
+    #[tokio::test]
+    #[cfg(unix)]
+    pub async fn test_stream_partial_last_line() {
+        let path = std::env::temp_dir().join(format!("logglance_test_stream_partial_last_line_{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let status = std::process::Command::new("mkfifo").arg(&path).status().expect("Should run mkfifo");
+        assert!(status.success(), "Should create a FIFO");
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let output = ReaderOutput { sender, epoch: 0 };
+        let repaint = Repainter { ctx: egui::Context::default(), paused: Default::default(), interval: Duration::ZERO };
+        let options = read_options();
+        let reader_path = path.clone();
+        let reader = tokio::spawn(async move { stream_reader(&reader_path, output, repaint, Default::default(), options).await });
+
+        // The writer leaves its last line unfinished, until closing the stream. Opening a FIFO
+        // blocks until the reader opens it too.
+        let (close, closed) = std::sync::mpsc::channel::<()>();
+        let writer_path = path.clone();
+        let writer = std::thread::spawn(move || {
+            use std::io::Write;
+            let mut fifo = std::fs::OpenOptions::new().write(true).open(writer_path)?;
+            fifo.write_all(b"first\npartial")?;
+            let _ = closed.recv();
+            Ok::<_, std::io::Error>(())
+        });
+
+        let mut lines = Vec::new();
+        let deadline = Instant::now() + Duration::from_secs(5);
+
+        while Instant::now() < deadline {
+            match receiver.try_recv() {
+                Ok((_, LogFileMessage::FileData(data, _, _))) => lines.extend(data),
+                Ok(_) => (),
+                Err(_) if lines.is_empty() => tokio::time::sleep(Duration::from_millis(10)).await,
+                Err(_) if lines.len() == 1 => {
+                    // The finished line is shown without waiting for the rest of the next one.
+                    let _ = close.send(());
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                }
+                Err(_) => break,
+            }
+        }
+
+        reader.abort();
+        drop(close);
+        writer.join().expect("Writer shouldn't panic").expect("Should write to the FIFO");
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(lines, vec![String::from("first\n"), String::from("partial")]);
+    }
+
+    #[test]
+    pub fn test_clear_buffer() {
+        let mut file = LogFile::new("test.log".into(), Vec::new(), DisplayOptions::default());
+
+        feed(
+            &mut file,
+            LogFileMessage::FileData(vec![String::from("ERROR failed\n"), String::from("INFO started\n")], vec![0, 13], vec![(1, 40)]),
+        );
+        assert_eq!(file.max_severity, Some(Severity::Error));
+        assert_eq!(file.truncated_lengths.get(&2), Some(&40));
+
+        file.clear_buffer();
+
+        assert!(file.lines.is_empty());
+        assert!(file.line_offsets.is_empty());
+        assert!(file.truncated_lengths.is_empty());
+        assert_eq!(file.max_severity, None);
+
+        // The reader keeps adding lines, numbered after the cleared ones.
+        feed(&mut file, LogFileMessage::FileData(vec![String::from("INFO stopped\n")], vec![26], Vec::new()));
+
+        assert_eq!(file.lines.len(), 1);
+        assert_eq!(file.line_number(0), 3);
+        assert_eq!(file.byte_offset(0), Some(26));
+    }
+
+    #[test]
+    pub fn test_backlog_isnt_throughput() {
+        let mut file = LogFile::new("test.log".into(), Vec::new(), DisplayOptions::default());
+        file.modified = Some(std::time::SystemTime::now());
+
+        // Sent while the tab was hidden.
+        file.receiving_backlog = true;
+        feed_lines(&mut file, &["INFO started\n"; 1000]);
+        assert_eq!(file.throughput.rate(Instant::now()), None);
+
+        file.receiving_backlog = false;
+        feed_lines(&mut file, &["INFO stopped\n"]);
+        assert_eq!(file.throughput.rate(Instant::now()).map(|(lines, _)| lines), Some(1.0));
+    }
+
+    #[test]
+    pub fn test_messages_of_replaced_reader_are_ignored() {
+        let mut file = LogFile::new("test.log".into(), Vec::new(), DisplayOptions::default());
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let stale = ReaderOutput { sender: sender.clone(), epoch: file.epoch };
+        file.epoch += 1;
+        file.sender = Some(ReaderOutput { sender, epoch: file.epoch });
+        file.receiver = Some(receiver);
+
+        stale
+            .send(LogFileMessage::FileData(vec![String::from("Stale\n")], vec![0], Vec::new()))
+            .expect("Receiver should be alive");
+        feed_lines(&mut file, &["Current\n"]);
+
+        assert_eq!(file.lines.as_slice(), &[String::from("Current\n")]);
+    }
+
+    #[test]
+    pub fn test_unfiltered_batch_isnt_refiltered() {
+        let mut file = LogFile::new("test.log".into(), vec![String::from("INFO started\n")], DisplayOptions::default());
+        feed_lines(&mut file, &["ERROR failed\n"]);
+
+        assert_eq!(file.lines.len(), 2);
+        assert_eq!(file.max_severity, Some(Severity::Error));
+        // Every line is shown as is, so there's no filtering to start.
+        assert!(!file.recalculate_filter_cache);
+        assert!(file.filter_job.is_none());
+    }
+
+    #[test]
+    pub fn test_config_link() {
+        let mut row_modifier = filtering(Search::new("ERROR", SearchMode::Substring, false));
+        row_modifier.row_highlights.push(RowHighlight::new(
+            Search::new(r"\d+ms", SearchMode::Regex, false),
+            Color32::YELLOW,
+            Color32::BLACK,
+        ));
+
+        let link = row_modifier.to_config_link().expect("Should create config link");
+        assert!(link.bytes().all(|b| b.is_ascii_alphanumeric() || b"-_:".contains(&b)));
+
+        let applied = RowModifier::from_config_link(&link).expect("Should apply config link");
+        assert_eq!(applied.filter.search.string, "ERROR");
+        assert!(applied.row_highlights[0].search.is_ready());
+
+        assert!(RowModifier::from_config_link("hello").is_err());
+        assert!(RowModifier::from_config_link(&link[..link.len() - 5]).is_err());
+        assert!(RowModifier::from_config_link("logglance-config:e30").is_err());
+    }
+
+    #[test]
+    pub fn test_match_style() {
+        let mut row_modifier = filtering(Search::new("b", SearchMode::Substring, false));
+        row_modifier.filter.search.rebuild_regex();
+
+        row_modifier.match_style = MatchStyle::Box;
+        let line = row_modifier.generate_line("abc");
+        let chunks: Vec<(&str, bool)> = line
+            .chunks
+            .as_ref()
+            .expect("Line should be chunked")
+            .iter()
+            .map(|c| (c.text.as_str(), c.boxed))
+            .collect();
+        assert_eq!(chunks, vec![("a", false), ("b", true), ("c", false)]);
+        assert_eq!(chunk_colors(&line)[1], ("b", Some(TextFormat::default().color)));
+
+        row_modifier.match_style = MatchStyle::Underline;
+        let line = row_modifier.generate_line("abc");
+        let chunks = line.chunks.as_ref().expect("Line should be chunked");
+        assert!(!chunks[1].boxed);
+        assert_eq!(chunks[1].format.as_ref().map(|f| f.underline.width), Some(1.5));
+    }
+
+    #[test]
+    pub fn test_regex_flags() {
+        let mut search = Search::new(r"^b.c$", SearchMode::Regex, false);
+        assert!(!search.is_match("a\nb\nc"));
+
+        search.multi_line = true;
+        search.dot_matches_new_line = true;
+        search.rebuild_regex();
+        assert!(search.is_match("a\nb\nc"));
+
+        // The flags are ignored outside of regex mode.
+        search.mode = SearchMode::Substring;
+        search.string = String::from("^");
+        search.rebuild_regex();
+        assert!(!search.is_match("a\nb"));
+    }
+
+    #[test]
+    pub fn test_encoding_redetector() {
+        let mut redetector = EncodingRedetector::default();
+        let (line, _, _) = encoding_rs::WINDOWS_1251.encode("Ошибка при чтении файла конфигурации\n");
+
+        for _ in 0..REDETECT_THRESHOLD - 1 {
+            assert_eq!(redetector.line(&line, true, encoding_rs::UTF_8), None);
+        }
+
+        let switched = redetector.line(&line, true, encoding_rs::UTF_8).expect("Should switch encoding");
+        assert_ne!(switched, encoding_rs::UTF_8);
+        assert!(redetector.recent.is_empty());
+    }
+
+    #[test]
+    pub fn test_humanreadable_count() {
+        assert_eq!(humanreadable_count(0), "0");
+        assert_eq!(humanreadable_count(412), "412");
+        assert_eq!(humanreadable_count(412_003), "412,003");
+        assert_eq!(humanreadable_count(1_203_114), "1,203,114");
+    }
+
+    #[test]
+    pub fn test_highlight_filter_matches_only() {
+        let mut row_modifier = RowModifier {
+            filter: Filter {
+                filter: false,
+                ..Filter::new(Search::new("error", SearchMode::Substring, false))
+            },
+            row_highlights: vec![RowHighlight {
+                search: Search::new("disk", SearchMode::Substring, false),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        assert_eq!(row_modifier.generate_line("disk full").default_format.background, Color32::DARK_GREEN);
+
+        row_modifier.highlight_filter_matches_only = true;
+        assert_eq!(row_modifier.generate_line("disk full").default_format.background, Color32::TRANSPARENT);
+        assert_eq!(row_modifier.generate_line("error: disk full").default_format.background, Color32::DARK_GREEN);
+        assert!(row_modifier.highlights_apply("error: disk full"));
+        assert!(!row_modifier.highlights_apply("disk full"));
+    }
+
+    #[test]
+    pub fn test_split_record() {
+        let record = b"first\nsecond\n\x1e";
+        assert_eq!(
+            split_record(record, Some(RecordSeparator::Rs)),
+            vec![(0, &b"first\n"[..]), (6, &b"second\n\x1e"[..])]
+        );
+        assert_eq!(split_record(b"single\0", Some(RecordSeparator::Nul)), vec![(0, &b"single\0"[..])]);
+        assert_eq!(split_record(record, None), vec![(0, &record[..])]);
+
+        let lines = ["a\n", "b\n\x1e", "c\x1e", "d"].map(String::from);
+        assert_eq!(
+            find_entry_starts(&lines, 0, &FormatProfile::default(), Some(RecordSeparator::Rs)),
+            vec![0, 2, 3]
+        );
+    }
+
+    #[test]
+    pub fn test_max_highlights() {
+        let highlight = |string: &str, bg_color| RowHighlight::new(Search::new(string, SearchMode::Substring, false), bg_color, Color32::WHITE);
+        let mut row_modifier = RowModifier {
+            row_highlights: vec![highlight("disk", Color32::RED), highlight("full", Color32::BLUE)],
+            ..Default::default()
+        };
+
+        row_modifier.row_highlights[0].disabled = true;
+        assert_eq!(row_modifier.generate_line("disk full").default_format.background, Color32::BLUE);
+
+        row_modifier.row_highlights[0].disabled = false;
+        row_modifier.max_highlights = 1;
+        let line = row_modifier.generate_line("disk full");
+        assert_eq!(line.default_format.background, Color32::RED);
+        assert_eq!(chunk_colors(&line)[1], (" full", None));
+    }
+
+    #[test]
+    pub fn test_rebuild_regexes() {
+        let search = Search {
+            changed: false,
+            regex: None,
+            string: String::from("which"),
+            mode: SearchMode::Substring,
+            case_insensitive: false,
+            ..Default::default()
+        };
+
+        let mut row_modifier = RowModifier {
+            filter: Filter {
+                filter: true,
+                search: search.clone(),
+                columns: None,
+                exclude: Search::default(),
+                changed: false,
+                jump: None,
+                position: None,
+            },
+            row_highlights: vec![
+                RowHighlight {
+                    search,
+                    ..Default::default()
+                },
+                RowHighlight::default(),
+            ],
+            ..Default::default()
+        };
+
+        row_modifier.rebuild_regexes();
+
+        assert!(row_modifier.filter.search.regex.is_some());
+        assert!(row_modifier.row_highlights[0].search.regex.is_some());
+        // Empty searches shouldn't match anything.
+        assert!(row_modifier.row_highlights[1].search.regex.is_none());
+    }
+
+    #[test]
+    pub fn test_rewrite_rules() {
+        let mut row_modifier = RowModifier {
+            filter: Filter {
+                filter: true,
+                search: Search {
+                    string: String::from("done"),
+                    ..Default::default()
+                },
+                columns: None,
+                exclude: Search::default(),
+                changed: false,
+                jump: None,
+                position: None,
+            },
+            rewrite_rules: vec![RewriteRule {
+                search: Search {
+                    string: String::from(r"\[thread-\d+\]"),
+                    mode: SearchMode::Regex,
+                    ..Default::default()
+                },
+                replacement: String::from("[t]"),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        row_modifier.rebuild_regexes();
+
+        let line = row_modifier.generate_line("[thread-1234567] done");
+        assert_eq!(line.full, "[t] done");
+
+        let chunks = line.chunks.expect("Filter matches should be chunked");
+        let matched: Vec<&str> = chunks
+            .iter()
+            .filter(|c| c.format.is_some())
+            .map(|c| c.text.as_str())
+            .collect();
+        assert_eq!(matched, vec!["done"]);
+
+        // Filtering uses the original lines unless told otherwise.
+        let lines = vec![String::from("[thread-1] [t] done"), String::from("[thread-2] done")];
+        row_modifier.filter.search.string = String::from("[t]");
+        row_modifier.rebuild_regexes();
+        assert_eq!(row_modifier.filter(&lines).map(|l| l.len()), Some(1));
+
+        row_modifier.filter_rewritten = true;
+        assert_eq!(row_modifier.filter(&lines).map(|l| l.len()), Some(2));
+    }
+
+    #[test]
+    pub fn test_generate_line_matches() {
+        let row_modifier = filtering(Search::new("ERROR", SearchMode::Substring, false));
+
+        let line = row_modifier.generate_line("ERROR at the start");
+        assert_eq!(chunk_colors(&line), vec![("ERROR", Some(Color32::RED)), (" at the start", None)]);
+
+        let line = row_modifier.generate_line("at the end ERROR\r\n");
+        assert_eq!(line.full, "at the end ERROR");
+        assert_eq!(chunk_colors(&line), vec![("at the end ", None), ("ERROR", Some(Color32::RED))]);
+
+        let line = row_modifier.generate_line("ERROR");
+        assert_eq!(chunk_colors(&line), vec![("ERROR", Some(Color32::RED))]);
+
+        let line = row_modifier.generate_line("no match");
+        assert_eq!(chunk_colors(&line), vec![("no match", None)]);
+
+        let line = row_modifier.generate_line("");
+        assert_eq!(line.full, "");
+        assert!(chunk_colors(&line).is_empty());
+
+        // Adjacent matches are separate chunks.
+        let row_modifier = filtering(Search::new("ab", SearchMode::Substring, false));
+        let line = row_modifier.generate_line("xababy");
+        assert_eq!(
+            chunk_colors(&line),
+            vec![("x", None), ("ab", Some(Color32::RED)), ("ab", Some(Color32::RED)), ("y", None)]
+        );
+
+        // Multi-byte characters around a match.
+        let row_modifier = filtering(Search::new("ö", SearchMode::Substring, false));
+        let line = row_modifier.generate_line("åöä");
+        assert_eq!(chunk_colors(&line), vec![("å", None), ("ö", Some(Color32::RED)), ("ä", None)]);
+
+        // Nothing to match with leaves the line in one piece.
+        let line = RowModifier::default().generate_line("plain line");
+        assert!(line.chunks.is_none());
+        assert_eq!(line.default_format, TextFormat::default());
+    }
+
+    #[test]
+    pub fn test_generate_line_highlights() {
+        let mut row_modifier = filtering(Search::new("lost", SearchMode::Substring, false));
+        row_modifier.row_highlights = vec![
+            RowHighlight::new(Search::default(), Color32::DARK_RED, Color32::WHITE),
+            RowHighlight::new(
+                Search::new("connection", SearchMode::Substring, true),
+                Color32::DARK_BLUE,
+                Color32::WHITE,
+            ),
+            RowHighlight::new(
+                Search::new("lost", SearchMode::Substring, false),
+                Color32::DARK_GREEN,
+                Color32::BLACK,
+            ),
+        ];
+
+        // The first matching highlight colors the whole row, empty ones are skipped.
+        let line = row_modifier.generate_line("Connection lost");
+        assert_eq!(line.default_format.background, Color32::DARK_BLUE);
+        assert_eq!(line.default_format.color, Color32::WHITE);
+        assert_eq!(
+            chunk_colors(&line),
+            vec![("Connection", Some(Color32::WHITE)), (" ", None), ("lost", Some(Color32::RED))]
+        );
+
+        let line = row_modifier.generate_line("Signal lost");
+        assert_eq!(line.default_format.background, Color32::DARK_GREEN);
+
+        let line = row_modifier.generate_line("All good");
+        assert_eq!(line.default_format, TextFormat::default());
+        assert_eq!(chunk_colors(&line), vec![("All good", None)]);
+
+        // Later highlights still color the text they match, on top of the earlier ones.
+        row_modifier.filter = Filter::default();
+        row_modifier.row_highlights.push(RowHighlight::new(
+            Search::new("on l", SearchMode::Substring, false),
+            Color32::YELLOW,
+            Color32::BLACK,
+        ));
+
+        let line = row_modifier.generate_line("Connection lost");
+        assert_eq!(line.default_format.background, Color32::DARK_BLUE);
+
+        let backgrounds: Vec<(&str, Option<Color32>)> = line
+            .chunks
+            .as_ref()
+            .expect("Line should be chunked")
+            .iter()
+            .map(|c| (c.text.as_str(), c.format.as_ref().map(|f| f.background)))
+            .collect();
+        assert_eq!(
+            backgrounds,
+            vec![
+                ("Connecti", Some(Color32::DARK_BLUE)),
+                ("on", Some(Color32::YELLOW)),
+                (" ", Some(Color32::YELLOW)),
+                ("l", Some(Color32::YELLOW)),
+                ("ost", Some(Color32::DARK_GREEN)),
+            ]
+        );
+
+        // Filter matches keep the background of the highlight below them.
+        row_modifier.filter = Filter::new(Search::new("lost", SearchMode::Substring, false));
+        let line = row_modifier.generate_line("Signal lost");
+        let format = line.chunks.as_ref().and_then(|c| c[1].format.clone()).expect("Match should be formatted");
+        assert_eq!((format.color, format.background), (Color32::RED, Color32::DARK_GREEN));
+    }
+
+    #[test]
+    pub fn test_jump_to_highlight() {
+        let lines = ["panic: a", "ok", "ok", "panic: b", "ok"].map(String::from).to_vec();
+
+        let mut file = LogFile::new("test.log".into(), lines, DisplayOptions::default());
+        file.add_highlight(RowHighlight::new(
+            Search::new("panic", SearchMode::Substring, false),
+            Color32::DARK_RED,
+            Color32::WHITE,
+        ));
+        file.visible_lines = Some(1..=3);
+
+        file.jump_to_highlight(0, Jump::Next);
+        assert_eq!(file.scroll_to_line, Some(4));
+
+        // Wraps around, navigating from the line last navigated to while it's in view.
+        file.visible_lines = Some(4..=5);
+        file.jump_to_highlight(0, Jump::Next);
+        assert_eq!(file.scroll_to_line, Some(1));
+
+        file.visible_lines = Some(1..=3);
+        file.jump_to_highlight(0, Jump::Previous);
+        assert_eq!(file.scroll_to_line, Some(4));
+
+        // Lines read later are matched as they arrive, rather than all of them again.
+        feed_lines(&mut file, &["panic: c"]);
+        assert_eq!(file.match_rows.as_ref().map(|(_, rows)| rows.as_slice()), Some([0, 3, 5].as_slice()));
+    }
+
+    #[test]
+    pub fn test_filter_match_navigation() {
+        let lines = ["ERROR a", "ok", "ERROR b", "ok", "ERROR c"].map(String::from).to_vec();
+
+        let mut file = LogFile::new("test.log".into(), lines, DisplayOptions::default())
+            .with_filter(Filter::new(Search::new("ERROR", SearchMode::Substring, false)));
+        file.row_modifier.filter.filter = false;
+        assert_eq!(file.filter_match_position(), None);
+
+        file.visible_lines = Some(1..=2);
+        assert!(file.jump_to_match(Matcher::Filter, Jump::Next));
+        assert_eq!(file.scroll_to_line, Some(3));
+        assert_eq!(file.filter_match_position(), Some((Some(2), 3)));
+
+        file.visible_lines = Some(3..=4);
+        assert!(file.jump_to_match(Matcher::Filter, Jump::Previous));
+        assert_eq!(file.scroll_to_line, Some(1));
+        assert_eq!(file.filter_match_position(), Some((Some(1), 3)));
+
+        // Wraps around to the last match.
+        file.visible_lines = Some(1..=2);
+        assert!(file.jump_to_match(Matcher::Filter, Jump::Previous));
+        assert_eq!(file.scroll_to_line, Some(5));
+        assert_eq!(file.filter_match_position(), Some((Some(3), 3)));
+
+        // Not one of the matches once scrolled away and navigated elsewhere.
+        file.match_position = Some(2);
+        assert_eq!(file.filter_match_position(), Some((None, 3)));
+    }
+
+    #[test]
+    pub fn test_quick_search() {
+        let lines = ["GET /a", "POST /b", "GET /c"].map(String::from).to_vec();
+
+        let mut file = LogFile::new("test.log".into(), lines, DisplayOptions::default());
+        file.quick_search = Some(QuickSearch {
+            search: Search::new("^GET", SearchMode::Regex, false),
+            direction: Jump::Previous,
+        });
+
+        assert!(file.jump_to_match_from(Matcher::QuickSearch, Jump::Previous, 3));
+        assert_eq!(file.scroll_to_line, Some(1));
+
+        // Wraps around.
+        assert!(file.jump_to_match_from(Matcher::QuickSearch, Jump::Previous, 1));
+        assert_eq!(file.scroll_to_line, Some(3));
+
+        file.quick_search = Some(QuickSearch {
+            search: Search::new("DELETE", SearchMode::Regex, false),
+            direction: Jump::Next,
+        });
+        file.match_rows = None;
+        assert!(!file.jump_to_match_from(Matcher::QuickSearch, Jump::Next, 1));
+    }
+
+    #[test]
+    pub fn test_markdown_code_block() {
+        let lines = ["first\n", "```rust", "last\r\n"];
+
+        assert_eq!(
+            markdown_code_block(lines, "log", 1024),
+            (String::from("````log\nfirst\n```rust\nlast\n````\n"), 3)
+        );
+        assert_eq!(markdown_code_block(lines, "", 8), (String::from("```\nfirst\n```\n"), 1));
+    }
+
+    #[test]
+    pub fn test_nearest_row() {
+        let lines = (1..=10).map(|i| format!("line {i}")).collect();
+        let mut file = LogFile::new("test.log".into(), lines, DisplayOptions::default());
+
+        assert_eq!(file.nearest_row(20), Some(9));
+
+        file.filter_cache = Some(vec![1, 5, 8]);
+        assert_eq!(file.nearest_row(1), Some(0));
+        assert_eq!(file.nearest_row(3), Some(0));
+        assert_eq!(file.nearest_row(5), Some(1));
+        assert_eq!(file.nearest_row(10), Some(2));
+
+        file.filter_cache = Some(Vec::new());
+        assert_eq!(file.nearest_row(5), None);
+    }
+
+    #[test]
+    pub fn test_logfile_filtered_lines() {
+        let lines = vec![
+            String::from("This is a line, it shouldn't match the filter"),
+            String::from("This is a line which should match the filter."),
+            String::from("Another line which the filter should match."),
+        ];
+
+        let mut file = LogFile::new("test.log".into(), lines.clone(), DisplayOptions::default());
+        assert_eq!(file.filtered_lines(), lines);
+
+        file = file.with_filter(Filter::new(Search::new("which", SearchMode::Substring, false)));
+        wait_for_filtering(&mut file);
+        assert_eq!(file.filtered_lines(), lines[1..].to_vec());
+        assert_eq!(file.count_matches(), (2, 3));
+
+        file.row_modifier.filter.filter = false;
+        wait_for_filtering(&mut file);
+        assert_eq!(file.filtered_lines(), lines);
+    }
+
+    #[test]
+    pub fn test_filter_preserves_order() {
+        // Enough lines for rayon to split the work between threads.
+        let lines: Vec<String> = (0..20_000)
+            .map(|i| if i % 7 == 0 { format!("{i} match") } else { format!("{i} other") })
+            .collect();
+        let expected: Vec<usize> = (0..lines.len()).step_by(7).collect();
+
+        let filter = Filter::new(Search::new("match", SearchMode::Substring, false));
+        let filtered = filter.filter(&lines).expect("Filter should be ready");
+        assert_eq!(filtered, expected.iter().map(|&i| lines[i].clone()).collect::<Vec<_>>());
+
+        let row_modifier = filtering(Search::new("match", SearchMode::Substring, false));
+        let (rows, _) = row_modifier.filter_rows(&lines).expect("Filter should be ready");
+        assert_eq!(rows, expected);
+
+        // Lines arriving in batches are filtered on their own and appended to the rows, offset by
+        // the lines before them, the way the filter cache is extended.
+        let mut rows = Vec::new();
+
+        for (batch, chunk) in lines.chunks(3_000).enumerate() {
+            let (batch_rows, _) = row_modifier.filter_rows(chunk).expect("Filter should be ready");
+            rows.extend(batch_rows.into_iter().map(|i| i + batch * 3_000));
+        }
+
+        assert_eq!(rows, expected);
+
+        let mut file = LogFile::new("test.log".into(), lines.clone(), DisplayOptions::default())
+            .with_filter(Filter::new(Search::new("match", SearchMode::Substring, false)));
+        wait_for_filtering(&mut file);
+        assert_eq!(file.filtered_lines(), filtered);
+    }
+
+    #[test]
+    pub fn test_entry_grouping() {
+        let lines = vec![
+            String::from("12:00:00 Starting"),
+            String::from("12:00:01 Request failed"),
+            String::from("java.lang.NullPointerException"),
+            String::from("    at Main.main(Main.java:3)"),
+            String::from("12:00:02 Done"),
+        ];
+        let format = FormatProfile::from(FormatPatterns {
+            timestamp: String::from(r"\d\d:\d\d:\d\d"),
+            ..Default::default()
+        });
+
+        let mut file = LogFile::new("test.log".into(), lines, DisplayOptions::default());
+        file.display.group_entries = true;
+        file.entry_starts = find_entry_starts(&file.lines, 0, &format, None);
+        assert_eq!(file.entry_starts, vec![0, 1, 4]);
+
+        // The filter matches against the whole entry.
+        file = file.with_filter(Filter::new(Search::new("NullPointer", SearchMode::Substring, false)));
+        let (entries, _) = filter_entries(&file.row_modifier, &file.lines, &file.entry_starts, &FilterControl::default());
+        assert_eq!(entries, vec![1]);
+        assert_eq!(file.entry_rows(&entries), vec![1]);
+
+        file.expanded_entries.insert(1);
+        assert_eq!(file.entry_rows(&entries), vec![1, 2, 3]);
+    }
 }
@@ -0,0 +1,171 @@
+//! Logfmt, lines of `key=value` pairs such as `level=info msg="Request done" dur=12ms`, shown with
+//! their keys dimmed and values emphasized without configuring any highlights.
+
+use std::ops::Range;
+
+use eframe::egui::Color32;
+
+use crate::severity::Severity;
+
+/// Values of pairs other than the level, readable in both the dark and the light theme.
+const VALUE_COLOR: Color32 = Color32::from_rgb(86, 156, 214);
+/// Values of the level pair for levels without a color of their own, see [`Severity::color`].
+const LEVEL_COLOR: Color32 = Color32::from_rgb(78, 201, 120);
+
+/// A `key=value` pair, or a key on its own, by the byte ranges of its parts within the line. The
+/// value of a quoted value includes the quotes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pair {
+    pub key: Range<usize>,
+    pub value: Option<Range<usize>>,
+}
+
+/// The pairs of `line`, None if it isn't logfmt, i.e. it's malformed or doesn't have a single
+/// `key=value` pair.
+pub fn parse(line: &str) -> Option<Vec<Pair>> {
+    let bytes = line.as_bytes();
+    let len = bytes.len();
+    let mut pairs = Vec::new();
+    let mut i = 0;
+
+    loop {
+        while i < len && matches!(bytes[i], b' ' | b'\t') {
+            i += 1;
+        }
+
+        if i == len {
+            break;
+        }
+
+        let start = i;
+
+        while i < len && bytes[i] > b' ' && bytes[i] != b'=' && bytes[i] != b'"' {
+            i += 1;
+        }
+
+        if i == start {
+            return None;
+        }
+
+        let key = start..i;
+
+        if i < len && bytes[i] == b'"' {
+            return None;
+        }
+
+        if i == len || bytes[i] != b'=' {
+            pairs.push(Pair { key, value: None });
+            continue;
+        }
+
+        i += 1;
+        let value_start = i;
+
+        if i < len && bytes[i] == b'"' {
+            i += 1;
+
+            loop {
+                match bytes.get(i) {
+                    None => return None,
+                    Some(b'\\') => i += 2,
+                    Some(b'"') => {
+                        i += 1;
+                        break;
+                    }
+                    Some(_) => i += 1,
+                }
+            }
+
+            if i < len && bytes[i] > b' ' {
+                return None;
+            }
+        } else {
+            while i < len && bytes[i] > b' ' {
+                if bytes[i] == b'"' {
+                    return None;
+                }
+
+                i += 1;
+            }
+        }
+
+        pairs.push(Pair {
+            key,
+            value: Some(value_start..i),
+        });
+    }
+
+    pairs.iter().any(|p| p.value.is_some()).then_some(pairs)
+}
+
+/// The colors of the parts of `line` if it's logfmt: keys in a dimmed `base` color, values
+/// emphasized and the value of `level` in the color of its severity. Empty if it isn't logfmt.
+pub fn colors(line: &str, base: Color32) -> Vec<(Range<usize>, Color32)> {
+    let Some(pairs) = parse(line) else {
+        return Vec::new();
+    };
+
+    let key_color = base.gamma_multiply(0.6);
+    let mut colors = Vec::new();
+
+    for Pair { key, value } in pairs {
+        let Some(value) = value else {
+            continue;
+        };
+
+        let value_color = if matches!(
+            line[key.clone()].to_ascii_lowercase().as_str(),
+            "level" | "lvl"
+        ) {
+            Severity::from_level_str(line[value.clone()].trim_matches('"'))
+                .map(|s| s.color().unwrap_or(LEVEL_COLOR))
+                .unwrap_or(VALUE_COLOR)
+        } else {
+            VALUE_COLOR
+        };
+
+        // The `=` is dimmed along with the key.
+        colors.push((key.start..value.start, key_color));
+        colors.push((value, value_color));
+    }
+
+    colors
+}
+
+#[cfg(test)]
+mod test {
+    use super::{colors, parse, Pair, VALUE_COLOR};
+    use eframe::egui::Color32;
+
+    #[test]
+    pub fn test_parse_logfmt() {
+        let line = r#"level=info msg="Request \"done\"" dur=12ms cached"#;
+        let pairs = parse(line).expect("Should be logfmt");
+
+        let parts: Vec<(&str, Option<&str>)> = pairs
+            .iter()
+            .map(|Pair { key, value }| (&line[key.clone()], value.clone().map(|v| &line[v])))
+            .collect();
+        assert_eq!(
+            parts,
+            vec![
+                ("level", Some("info")),
+                ("msg", Some(r#""Request \"done\"""#)),
+                ("dur", Some("12ms")),
+                ("cached", None),
+            ]
+        );
+
+        // Not logfmt, or malformed.
+        assert_eq!(parse("Just a regular line"), None);
+        assert_eq!(parse(r#"msg="unterminated"#), None);
+        assert_eq!(parse(r#"a="b"c"#), None);
+        assert_eq!(parse("=value"), None);
+        assert_eq!(parse(""), None);
+
+        let colors = colors("level=error x=1", Color32::GRAY);
+        assert_eq!(colors[1], (6..11, Color32::RED));
+        assert_eq!(colors[2], (12..14, Color32::GRAY.gamma_multiply(0.6)));
+        assert_eq!(colors[3], (14..15, VALUE_COLOR));
+    }
+}
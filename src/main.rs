@@ -4,11 +4,15 @@ use logglance::LogTool;
 // When compiling natively:
 #[cfg(not(target_arch = "wasm32"))]
 fn main() -> eframe::Result<()> {
-    if let None = std::env::var_os("RUST_LOG") {
+    if std::env::var_os("RUST_LOG").is_none() {
         std::env::set_var("RUST_LOG", "info");
     }
 
-    env_logger::init(); // Log to stderr (if you run with `RUST_LOG=debug`).
+    // Log to stderr (if you run with `RUST_LOG=debug`) and to the in-app diagnostics window.
+    let env_logger = env_logger::Builder::from_default_env().build();
+    let max_level = env_logger.filter();
+    logglance::diagnostics::init(Box::new(env_logger), max_level)
+        .expect("Unable to initialize logger");
 
     let rt = tokio::runtime::Builder::new_multi_thread()
         .enable_all()
@@ -28,10 +32,26 @@ fn main() -> eframe::Result<()> {
         ..Default::default()
     };
 
+    // Files given as arguments, optionally with a line to scroll to, e.g. `app.log:14233`.
+    let locations: Vec<_> = std::env::args_os()
+        .skip(1)
+        .map(logglance::parse_location)
+        .collect();
+
     eframe::run_native(
         logglance::APPLICATION_NAME,
         native_options,
-        Box::new(|cc| Ok(Box::new(LogTool::new(cc)))),
+        Box::new(|cc| {
+            let mut app = LogTool::new(cc);
+
+            for (path, line_number) in locations {
+                // Relative to the working directory rather than the log root.
+                let path = std::path::absolute(&path).unwrap_or(path);
+                app.open_location(path, line_number);
+            }
+
+            Ok(Box::new(app))
+        }),
     )?;
 
     rt.shutdown_background();
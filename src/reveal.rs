@@ -0,0 +1,63 @@
+use std::path::Path;
+use std::process::Command;
+
+use log::debug;
+
+use crate::Error;
+
+/// Opens the file manager of the platform at `path`, with the file selected where the file manager
+/// supports it.
+pub fn reveal(path: &Path) -> Result<(), Error> {
+    if !path.exists() {
+        return Err(format!("{} no longer exists", path.display()).into());
+    }
+
+    let path = std::path::absolute(path)?;
+    let mut command = reveal_command(&path)?;
+
+    debug!("Revealing {} using {command:?}", path.display());
+    let mut child = command.spawn()?;
+
+    // Waited for so it doesn't linger as a zombie once it exits. Explorer exits with an error even
+    // when it succeeds, so the status is only logged.
+    std::thread::spawn(move || match child.wait() {
+        Ok(status) => debug!("File manager exited with {status}"),
+        Err(e) => debug!("Unable to wait for the file manager: {e}"),
+    });
+
+    Ok(())
+}
+
+#[cfg(target_os = "windows")]
+fn reveal_command(path: &Path) -> Result<Command, Error> {
+    let mut select = std::ffi::OsString::from("/select,");
+    select.push(path);
+
+    let mut command = Command::new("explorer");
+    command.arg(select);
+    Ok(command)
+}
+
+#[cfg(target_os = "macos")]
+fn reveal_command(path: &Path) -> Result<Command, Error> {
+    let mut command = Command::new("open");
+    command.arg("-R").arg(path);
+    Ok(command)
+}
+
+/// There's no common way to select a file, so the directory containing it is opened instead.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn reveal_command(path: &Path) -> Result<Command, Error> {
+    let dir = path
+        .parent()
+        .ok_or_else(|| format!("{} isn't within a directory", path.display()))?;
+
+    let mut command = Command::new("xdg-open");
+    command.arg(dir);
+    Ok(command)
+}
+
+#[cfg(not(any(unix, target_os = "windows")))]
+fn reveal_command(_path: &Path) -> Result<Command, Error> {
+    Err("Showing files in a file manager isn't supported on this platform".into())
+}
@@ -0,0 +1,537 @@
+use std::path::{Path, PathBuf};
+
+use eframe::egui::{self, ComboBox, DragValue, Grid, TextEdit};
+use encoding_rs::Encoding;
+use serde::{Deserialize, Serialize};
+
+use crate::format::FormatProfile;
+use crate::logfile::{humanreadable_bytes, Density, MatchStyle, AVAILABLE_ENCODINGS};
+
+const DEFAULT_MAX_LINE_LENGTH: usize = 64 * 1024; // 64KiB
+const DEFAULT_MAX_UNRESTRICTED_ROWS: usize = 250_000_000;
+const DEFAULT_REPAINT_INTERVAL_MS: u64 = 50;
+const DEFAULT_READ_BUFFER_SIZE: usize = 64 * 1024; // 64KiB
+const DEFAULT_MAX_OPEN_FILES: usize = 50;
+
+/// What to do when opening a file which is already open.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ReopenBehaviour {
+    /// Ask whether to go to the existing view or open a new one.
+    #[default]
+    Ask,
+    GoToExisting,
+    /// Open a new view of the file, with its own filters and highlights.
+    OpenNew,
+}
+
+impl ReopenBehaviour {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ReopenBehaviour::Ask => "Ask",
+            ReopenBehaviour::GoToExisting => "Go to existing view",
+            ReopenBehaviour::OpenNew => "Open a new view",
+        }
+    }
+}
+
+/// Encoding used when the detection isn't confident about its guess.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FallbackEncoding {
+    /// Use the guess regardless.
+    #[default]
+    None,
+    /// The encoding of the system's locale, see [`system_encoding`].
+    System,
+    Encoding(&'static Encoding),
+}
+
+impl FallbackEncoding {
+    pub fn encoding(&self) -> Option<&'static Encoding> {
+        match self {
+            FallbackEncoding::None => None,
+            FallbackEncoding::System => system_encoding(),
+            FallbackEncoding::Encoding(encoding) => Some(encoding),
+        }
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            FallbackEncoding::None => String::from("None, use the guess"),
+            FallbackEncoding::System => match system_encoding() {
+                Some(encoding) => format!("System ({})", encoding.name()),
+                None => String::from("System (unknown)"),
+            },
+            FallbackEncoding::Encoding(encoding) => encoding.name().to_string(),
+        }
+    }
+}
+
+/// Files whose path matches `pattern` are read using `encoding` rather than detecting it.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct EncodingRule {
+    /// A glob, where `*` matches anything and `?` a single character. Matched against the file
+    /// name if it doesn't contain a `/`, against the whole path otherwise.
+    pub pattern: String,
+    pub encoding: &'static Encoding,
+}
+
+impl EncodingRule {
+    pub fn matches(&self, path: &Path) -> bool {
+        let pattern = self.pattern.trim();
+
+        if pattern.is_empty() {
+            return false;
+        }
+
+        if pattern.contains('/') {
+            // Separators are compared as `/` on Windows as well.
+            glob_match(pattern, &path.to_string_lossy().replace('\\', "/"))
+        } else {
+            path.file_name()
+                .is_some_and(|name| glob_match(pattern, &name.to_string_lossy()))
+        }
+    }
+}
+
+/// Whether all of `text` matches the glob `pattern`, see [`EncodingRule::pattern`].
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Where the last `*` was, and the text it has matched up to, to backtrack to.
+    let mut star: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                Some((star_p, star_t)) => {
+                    p = star_p + 1;
+                    t = star_t + 1;
+                    star = Some((star_p, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// The encoding of the system's locale, going by its ANSI code page.
+#[cfg(windows)]
+pub fn system_encoding() -> Option<&'static Encoding> {
+    extern "system" {
+        fn GetACP() -> u32;
+    }
+
+    // SAFETY: GetACP takes no arguments and only returns the code page identifier.
+    let code_page = unsafe { GetACP() };
+
+    let label = match code_page {
+        65001 => String::from("utf-8"),
+        866 => String::from("ibm866"),
+        932 => String::from("shift_jis"),
+        936 => String::from("gbk"),
+        949 => String::from("euc-kr"),
+        950 => String::from("big5"),
+        code_page => format!("windows-{code_page}"),
+    };
+
+    Encoding::for_label(label.as_bytes())
+}
+
+/// The encoding of the system's locale, going by the charset of a locale such as `ru_RU.CP1251`
+/// in the `LC_ALL`, `LC_CTYPE` or `LANG` environment variables.
+#[cfg(not(windows))]
+pub fn system_encoding() -> Option<&'static Encoding> {
+    let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+        .into_iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.is_empty())?;
+    let charset = locale.split_once('.')?.1.split('@').next()?;
+
+    Encoding::for_label(charset.as_bytes())
+}
+
+/// Application wide settings, persisted together with the rest of the app state.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct Settings {
+    /// Lines longer than this many bytes are truncated when read. 0 disables truncation.
+    pub max_line_length: usize,
+    /// Even files opened in unrestricted mode drop their oldest lines beyond this many rows, to
+    /// avoid running out of memory when tailing a file which keeps growing.
+    pub max_unrestricted_rows: usize,
+    /// Colors tab titles by the most severe level among the file's visible lines.
+    pub color_tabs_by_severity: bool,
+    /// Format used by files which don't have a format of their own.
+    pub default_format: FormatProfile,
+    /// Reopen the files which were open when the application was closed.
+    pub restore_session: bool,
+    /// Minimum time between repaints caused by new data, to keep bursts of writes from pegging a
+    /// core.
+    pub repaint_interval_ms: u64,
+    /// What to do when opening a file which is already open.
+    pub reopen: ReopenBehaviour,
+    /// Capacity of the buffer files are read through. Loading a large file with the 8KiB default
+    /// of `BufReader` is measurably slower, gains level off around 64KiB.
+    pub read_buffer_size: usize,
+    /// Show the footer crediting egui and eframe, which is also found in the About dialog.
+    pub show_footer: bool,
+    /// Open large files in restricted mode or not as previously decided for the same path, rather
+    /// than asking every time.
+    pub remember_restrict_decisions: bool,
+    /// How tightly rows are packed, unless overridden by a file's display options.
+    pub density: Density,
+    /// How filter matches are emphasized, e.g. by a box rather than a color.
+    pub match_style: MatchStyle,
+    /// Draw a faint separator through blank lines, keeping paragraph breaks in the logs visible.
+    pub mark_blank_lines: bool,
+    /// Each open file has a reader and is watched, opening more than this many files warns about
+    /// it.
+    pub max_open_files: usize,
+    /// Close the least recently viewed files when opening more than `max_open_files`, rather than
+    /// warning about it.
+    pub close_least_recently_viewed: bool,
+    /// Encoding used when the detection isn't confident about its guess.
+    pub fallback_encoding: FallbackEncoding,
+    /// Detect the encoding again when lines part way through a file fail to decode, e.g. for logs
+    /// concatenated from differently encoded files.
+    pub redetect_encoding: bool,
+    /// Directory the logs are commonly found under. Recent files and the files of workspaces
+    /// within it are stored relative to it, so workspaces work where it's somewhere else.
+    pub log_root: Option<PathBuf>,
+    /// Encodings to read files with by their paths, skipping the detection. The first matching
+    /// rule applies.
+    pub encoding_rules: Vec<EncodingRule>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            max_line_length: DEFAULT_MAX_LINE_LENGTH,
+            max_unrestricted_rows: DEFAULT_MAX_UNRESTRICTED_ROWS,
+            color_tabs_by_severity: true,
+            default_format: FormatProfile::default(),
+            restore_session: true,
+            repaint_interval_ms: DEFAULT_REPAINT_INTERVAL_MS,
+            reopen: ReopenBehaviour::default(),
+            read_buffer_size: DEFAULT_READ_BUFFER_SIZE,
+            show_footer: true,
+            remember_restrict_decisions: true,
+            density: Density::default(),
+            match_style: MatchStyle::default(),
+            mark_blank_lines: false,
+            max_open_files: DEFAULT_MAX_OPEN_FILES,
+            close_least_recently_viewed: false,
+            fallback_encoding: FallbackEncoding::default(),
+            redetect_encoding: false,
+            log_root: None,
+            encoding_rules: Vec::new(),
+        }
+    }
+}
+
+impl Settings {
+    /// `path` relative to the log root if it's within it, as is otherwise.
+    pub fn relative_to_log_root(&self, path: &Path) -> PathBuf {
+        match self.log_root.as_deref().and_then(|root| path.strip_prefix(root).ok()) {
+            Some(relative) if relative != Path::new("") => relative.to_owned(),
+            _ => path.to_owned(),
+        }
+    }
+
+    fn encoding_rules_ui(&mut self, ui: &mut egui::Ui) {
+        ui.label("Encoding rules").on_hover_ui(|ui| {
+            ui.label("Files matching a pattern are read using its encoding rather than detecting it, e.g. *.sjis.log or /var/log/legacy/*.");
+            ui.label("Patterns without a / are matched against the file name. Applies to files opened or reloaded after the change.");
+        });
+
+        let mut rule_to_remove = None;
+
+        for (index, rule) in self.encoding_rules.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                ui.add(
+                    TextEdit::singleline(&mut rule.pattern)
+                        .hint_text("*.log")
+                        .desired_width(240.0),
+                );
+
+                ComboBox::from_id_source(("encoding_rule", index))
+                    .selected_text(rule.encoding.name())
+                    .show_ui(ui, |ui| {
+                        for encoding in AVAILABLE_ENCODINGS {
+                            ui.selectable_value(&mut rule.encoding, encoding, encoding.name());
+                        }
+                    });
+
+                if ui
+                    .small_button("X")
+                    .on_hover_text("Remove the rule")
+                    .clicked()
+                {
+                    rule_to_remove = Some(index);
+                }
+            });
+        }
+
+        if let Some(index) = rule_to_remove {
+            self.encoding_rules.remove(index);
+        }
+
+        if ui.button("Add rule").clicked() {
+            self.encoding_rules.push(EncodingRule {
+                pattern: String::new(),
+                encoding: encoding_rs::UTF_8,
+            });
+        }
+    }
+
+    /// The encoding of the first encoding rule matching `path`.
+    pub fn encoding_for(&self, path: &Path) -> Option<&'static Encoding> {
+        self.encoding_rules
+            .iter()
+            .find(|rule| rule.matches(path))
+            .map(|rule| rule.encoding)
+    }
+
+    /// `path` within the log root if it's relative, as is otherwise.
+    pub fn resolve_in_log_root(&self, path: &Path) -> PathBuf {
+        match self.log_root.as_deref() {
+            Some(root) if path.is_relative() => root.join(path),
+            _ => path.to_owned(),
+        }
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        Grid::new("settings_grid")
+            .num_columns(2)
+            .striped(true)
+            .show(ui, |ui| {
+                ui.label("Max line length").on_hover_ui(|ui| {
+                    ui.label(
+                        "Lines longer than this are truncated when read, 0 disables truncation.",
+                    );
+                    ui.label("Applies to files opened or reloaded after the change.");
+                });
+                ui.horizontal(|ui| {
+                    ui.add(
+                        DragValue::new(&mut self.max_line_length)
+                            .range(0..=usize::MAX)
+                            .speed(1024)
+                            .suffix(" bytes"),
+                    );
+
+                    if self.max_line_length > 0 {
+                        ui.label(humanreadable_bytes(self.max_line_length as u64));
+                    }
+                });
+                ui.end_row();
+
+                ui.label("Max rows in unrestricted mode").on_hover_text(
+                    "The oldest lines are dropped beyond this, to conserve memory.",
+                );
+                ui.add(
+                    DragValue::new(&mut self.max_unrestricted_rows)
+                        .range(1_000..=usize::MAX)
+                        .speed(100_000),
+                );
+                ui.end_row();
+
+                ui.label("Repaint interval").on_hover_ui(|ui| {
+                    ui.label("Minimum time between repaints caused by new data in a file.");
+                    ui.label("Applies to files opened or reloaded after the change.");
+                });
+                ui.add(
+                    DragValue::new(&mut self.repaint_interval_ms)
+                        .range(0..=1000)
+                        .suffix(" ms"),
+                );
+                ui.end_row();
+
+                ui.label("Read buffer size").on_hover_ui(|ui| {
+                    ui.label("Size of the buffer files are read through.");
+                    ui.label("Applies to files opened or reloaded after the change.");
+                });
+                ui.horizontal(|ui| {
+                    ui.add(
+                        DragValue::new(&mut self.read_buffer_size)
+                            .range(1024..=64 * 1024 * 1024)
+                            .speed(1024)
+                            .suffix(" bytes"),
+                    );
+                    ui.label(humanreadable_bytes(self.read_buffer_size as u64));
+                });
+                ui.end_row();
+
+                ui.label("Fallback encoding").on_hover_ui(|ui| {
+                    ui.label("Used instead of the detected encoding when the detection isn't sure about its guess.");
+                    ui.label("Applies to files opened or reloaded after the change.");
+                });
+                ComboBox::from_id_source("fallback_encoding")
+                    .selected_text(self.fallback_encoding.label())
+                    .show_ui(ui, |ui| {
+                        let fallbacks = [FallbackEncoding::None, FallbackEncoding::System]
+                            .into_iter()
+                            .chain(AVAILABLE_ENCODINGS.map(FallbackEncoding::Encoding));
+
+                        for fallback in fallbacks {
+                            ui.selectable_value(&mut self.fallback_encoding, fallback, fallback.label());
+                        }
+                    });
+                ui.end_row();
+
+                ui.label("Detect encoding changes").on_hover_ui(|ui| {
+                    ui.label("Detect the encoding again when lines part way through a file fail to decode, e.g. for logs concatenated from differently encoded files.");
+                    ui.label("Applies to files opened or reloaded after the change.");
+                });
+                ui.checkbox(&mut self.redetect_encoding, "");
+                ui.end_row();
+
+                ui.label("Log root").on_hover_ui(|ui| {
+                    ui.label("Directory the logs are commonly found under, e.g. /var/log/myapp.");
+                    ui.label("Recent files and the files of workspaces within it are stored relative to it, so workspaces can be shared between machines where it's somewhere else.");
+                });
+                let mut log_root = self
+                    .log_root
+                    .as_ref()
+                    .map(|root| root.display().to_string())
+                    .unwrap_or_default();
+
+                if ui
+                    .add(TextEdit::singleline(&mut log_root).hint_text("None"))
+                    .changed()
+                {
+                    let log_root = log_root.trim();
+                    self.log_root = (!log_root.is_empty()).then(|| PathBuf::from(log_root));
+                }
+                ui.end_row();
+
+                ui.label("Density")
+                    .on_hover_text("Can be overridden by the display options of each file");
+                ui.horizontal(|ui| {
+                    for density in [Density::Compact, Density::Comfortable] {
+                        ui.selectable_value(&mut self.density, density, density.label());
+                    }
+                });
+                ui.end_row();
+
+                ui.label("Filter matches")
+                    .on_hover_text("How the text matching the filter is emphasized");
+                ui.horizontal(|ui| {
+                    for style in [MatchStyle::Color, MatchStyle::Underline, MatchStyle::Box] {
+                        ui.selectable_value(&mut self.match_style, style, style.label());
+                    }
+                });
+                ui.end_row();
+
+                ui.label("Mark blank lines")
+                    .on_hover_text("Draw a faint separator through blank lines, to keep paragraph breaks visible");
+                ui.checkbox(&mut self.mark_blank_lines, "");
+                ui.end_row();
+
+                ui.label("Opening a file which is already open");
+                ComboBox::from_id_source("reopen_behaviour")
+                    .selected_text(self.reopen.label())
+                    .show_ui(ui, |ui| {
+                        for reopen in [
+                            ReopenBehaviour::Ask,
+                            ReopenBehaviour::GoToExisting,
+                            ReopenBehaviour::OpenNew,
+                        ] {
+                            ui.selectable_value(&mut self.reopen, reopen, reopen.label());
+                        }
+                    });
+                ui.end_row();
+
+                ui.label("Max open files").on_hover_text(
+                    "Each open file is read and watched for changes, which uses up resources.",
+                );
+                ui.add(DragValue::new(&mut self.max_open_files).range(1..=10_000));
+                ui.end_row();
+
+                ui.label("Close least recently viewed files").on_hover_text(
+                    "Close the files viewed the longest time ago when opening more than the max, \
+                    instead of warning about it",
+                );
+                ui.checkbox(&mut self.close_least_recently_viewed, "");
+                ui.end_row();
+
+                ui.label("Remember restricted mode choices").on_hover_text(
+                    "Open large files the way they were last opened, instead of asking each time",
+                );
+                ui.checkbox(&mut self.remember_restrict_decisions, "");
+                ui.end_row();
+
+                ui.label("Restore open files on startup");
+                ui.checkbox(&mut self.restore_session, "");
+                ui.end_row();
+
+                ui.label("Show footer")
+                    .on_hover_text("The credits are also found in Help > About");
+                ui.checkbox(&mut self.show_footer, "");
+                ui.end_row();
+
+                ui.label("Color tabs by severity").on_hover_text(
+                    "Color tab titles red if an error is among the visible lines, orange for warnings.",
+                );
+                ui.checkbox(&mut self.color_tabs_by_severity, "");
+                ui.end_row();
+            });
+
+        ui.separator();
+        self.encoding_rules_ui(ui);
+
+        ui.separator();
+        ui.label("Default log format");
+        self.default_format.ui(ui);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use super::{glob_match, EncodingRule, Settings};
+
+    #[test]
+    pub fn test_encoding_rules() {
+        assert!(glob_match("*.sjis.log", "app.sjis.log"));
+        assert!(glob_match("app-?.log", "app-1.log"));
+        assert!(glob_match("*a*b", "xaab"));
+        assert!(!glob_match("*.sjis.log", "app.log"));
+        assert!(!glob_match("app-?.log", "app-12.log"));
+
+        let settings = Settings {
+            encoding_rules: vec![
+                EncodingRule {
+                    pattern: String::from("*.sjis.log"),
+                    encoding: encoding_rs::SHIFT_JIS,
+                },
+                EncodingRule {
+                    pattern: String::from("/var/log/legacy/*"),
+                    encoding: encoding_rs::WINDOWS_1251,
+                },
+            ],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            settings.encoding_for(Path::new("/tmp/app.sjis.log")),
+            Some(encoding_rs::SHIFT_JIS)
+        );
+        assert_eq!(
+            settings.encoding_for(Path::new("/var/log/legacy/app.log")),
+            Some(encoding_rs::WINDOWS_1251)
+        );
+        assert_eq!(settings.encoding_for(Path::new("/var/log/app.log")), None);
+    }
+}
@@ -0,0 +1,223 @@
+use std::ops::Range;
+use std::sync::OnceLock;
+
+use eframe::egui::Color32;
+use rayon::prelude::*;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::format::FormatProfile;
+
+/// Severity of a log line, ordered from least to most severe.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl Severity {
+    pub const ALL: [Severity; 5] = [
+        Self::Trace,
+        Self::Debug,
+        Self::Info,
+        Self::Warn,
+        Self::Error,
+    ];
+
+    /// The level names recognized as the severity, in upper case.
+    pub fn level_names(&self) -> &'static [&'static str] {
+        match self {
+            Self::Trace => &["TRACE"],
+            Self::Debug => &["DEBUG"],
+            Self::Info => &["INFO", "INFORMATION"],
+            Self::Warn => &["WARN", "WARNING"],
+            Self::Error => &["ERROR", "ERR", "FATAL", "CRITICAL"],
+        }
+    }
+
+    pub fn from_level_str(level: &str) -> Option<Self> {
+        let level = level.to_ascii_uppercase();
+
+        Self::ALL
+            .into_iter()
+            .find(|s| s.level_names().contains(&level.as_str()))
+    }
+
+    /// Color used to signal the severity, e.g. in tab titles. None for the less severe levels.
+    pub fn color(&self) -> Option<Color32> {
+        match self {
+            Self::Error => Some(Color32::RED),
+            Self::Warn => Some(Color32::from_rgb(255, 165, 0)),
+            _ => None,
+        }
+    }
+}
+
+/// A regex matching lines at one of `severities`. With `format_level`, the level pattern of a
+/// format, its group the level is extracted from (see [`FormatProfile::severity`]) only matches
+/// their level names. Otherwise, or if the level is the whole match, it matches the level names
+/// as whole words, the way [`detect_severity`] finds them.
+pub fn level_pattern(severities: &[Severity], format_level: &str) -> String {
+    let names: Vec<&str> = severities
+        .iter()
+        .flat_map(|s| s.level_names().iter().copied())
+        .collect();
+    let names = names.join("|");
+
+    match level_group(format_level) {
+        // The level is compared in upper case.
+        Some(group) => format!(
+            "{}(?i:{names}){}",
+            &format_level[..group.start],
+            &format_level[group.end..]
+        ),
+        None => format!(r"\b({names})\b"),
+    }
+}
+
+/// The range of the contents of the group of `pattern` the level is extracted from, the one named
+/// `level` if there is one, otherwise the first one.
+fn level_group(pattern: &str) -> Option<Range<usize>> {
+    enum Group {
+        Level,
+        Capturing(usize),
+        Other,
+    }
+
+    let mut open: Vec<(usize, Group)> = Vec::new();
+    let mut captures = 0;
+    let mut class_depth = 0;
+    let mut named = None;
+    let mut first = None;
+    let mut chars = pattern.char_indices();
+
+    while let Some((i, c)) = chars.next() {
+        let rest = &pattern[i + 1..];
+
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '[' => class_depth += 1,
+            ']' if class_depth > 0 => class_depth -= 1,
+            _ if class_depth > 0 => (),
+            '(' => {
+                let group = if let Some(after) = rest.strip_prefix("?P<").or_else(|| rest.strip_prefix("?<")) {
+                    let (name, _) = after.split_once('>')?;
+                    // Right after the name.
+                    let start = pattern.len() - after.len() + name.len() + 1;
+                    captures += 1;
+
+                    if name == "level" {
+                        (start, Group::Level)
+                    } else {
+                        (start, Group::Capturing(captures - 1))
+                    }
+                } else if rest.starts_with('?') {
+                    (0, Group::Other)
+                } else {
+                    captures += 1;
+                    (i + 1, Group::Capturing(captures - 1))
+                };
+
+                open.push(group);
+            }
+            ')' => match open.pop()? {
+                (start, Group::Level) => named = Some(start..i),
+                (start, Group::Capturing(0)) => first = Some(start..i),
+                _ => (),
+            },
+            _ => (),
+        }
+    }
+
+    named.or(first)
+}
+
+fn default_level_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(&level_pattern(&Severity::ALL, "")).expect("Default level regex should be valid")
+    })
+}
+
+/// Detects the severity of a line based on the first upper case level name within it.
+pub fn detect_severity(line: &str) -> Option<Severity> {
+    default_level_regex()
+        .find(line)
+        .and_then(|m| Severity::from_level_str(m.as_str()))
+}
+
+/// The highest severity found among `lines`, as extracted by `format`.
+pub fn max_severity(lines: &[String], format: &FormatProfile) -> Option<Severity> {
+    lines.par_iter().filter_map(|l| format.severity(l)).max()
+}
+
+/// Like [`max_severity`], among the `rows` of `lines`.
+pub fn max_severity_of_rows(
+    lines: &[String],
+    rows: &[usize],
+    format: &FormatProfile,
+) -> Option<Severity> {
+    rows.par_iter()
+        .filter_map(|&i| lines.get(i))
+        .filter_map(|l| format.severity(l))
+        .max()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{detect_severity, level_pattern, max_severity, Severity};
+    use regex::Regex;
+    use crate::format::{FormatPatterns, FormatProfile};
+
+    #[test]
+    pub fn test_detect_severity() {
+        assert_eq!(detect_severity("2024-07-17 12:00:00 INFO Started"), Some(Severity::Info));
+        assert_eq!(detect_severity("[WARNING] disk almost full"), Some(Severity::Warn));
+        // Only upper case level names count, to avoid matching regular words.
+        assert_eq!(detect_severity("no error here"), None);
+
+        let lines = vec![
+            String::from("DEBUG a"),
+            String::from("ERROR b"),
+            String::from("WARN c"),
+        ];
+        assert_eq!(max_severity(&lines, &FormatProfile::default()), Some(Severity::Error));
+
+        let format = FormatProfile::from(FormatPatterns {
+            timestamp: String::new(),
+            level: String::from(r"^(?P<level>\w+) [ac]"),
+            ..Default::default()
+        });
+        assert_eq!(max_severity(&lines, &format), Some(Severity::Warn));
+    }
+
+    #[test]
+    pub fn test_level_pattern() {
+        let regex = Regex::new(&level_pattern(&[Severity::Warn, Severity::Error], ""))
+            .expect("Level pattern should be valid");
+
+        assert!(regex.is_match("12:00 WARNING disk almost full"));
+        assert!(regex.is_match("12:00 FATAL out of memory"));
+        assert!(!regex.is_match("12:00 INFO no errors, no warnings"));
+        assert!(!regex.is_match("12:00 INFO ERRORS=0"));
+
+        // Goes by the group the format extracts the level from.
+        let pattern = level_pattern(&[Severity::Error], r"^\S+ (?P<level>[a-z]+) ");
+        assert_eq!(pattern, r"^\S+ (?P<level>(?i:ERROR|ERR|FATAL|CRITICAL)) ");
+        let regex = Regex::new(&pattern).expect("Level pattern should be valid");
+        assert!(regex.is_match("12:00 error disk full"));
+        assert!(!regex.is_match("12:00 info ERROR count is 0"));
+
+        assert_eq!(
+            level_pattern(&[Severity::Warn], r"(?:\d+) \[(\w+)\] (\w+)"),
+            r"(?:\d+) \[((?i:WARN|WARNING))\] (\w+)"
+        );
+        // The level is the whole match.
+        assert_eq!(level_pattern(&[Severity::Warn], r"[A-Z()]+"), r"\b(WARN|WARNING)\b");
+    }
+}
@@ -0,0 +1,253 @@
+use std::borrow::Cow;
+use std::cmp::Ordering;
+
+use eframe::egui::{self, TextEdit};
+use serde::{Deserialize, Serialize};
+
+/// Delimiters offered in the display options, any other character can be entered.
+const DELIMITERS: [(char, &str); 5] = [
+    (',', "Comma"),
+    ('\t', "Tab"),
+    ('|', "Pipe"),
+    (';', "Semicolon"),
+    (' ', "Space"),
+];
+
+/// How delimited lines, e.g. CSV, are split into the columns of a table.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct TableOptions {
+    pub delimiter: char,
+    /// Use the fields of the first line of the file as the names of the columns.
+    pub header: bool,
+}
+
+impl Default for TableOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: ',',
+            header: true,
+        }
+    }
+}
+
+impl TableOptions {
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            for (delimiter, label) in DELIMITERS {
+                ui.selectable_value(&mut self.delimiter, delimiter, label);
+            }
+
+            let mut other = if DELIMITERS.iter().any(|(d, _)| *d == self.delimiter) {
+                String::new()
+            } else {
+                self.delimiter.to_string()
+            };
+
+            if ui
+                .add(
+                    TextEdit::singleline(&mut other)
+                        .hint_text("Other")
+                        .desired_width(40.0),
+                )
+                .changed()
+            {
+                if let Some(delimiter) = other.chars().last() {
+                    self.delimiter = delimiter;
+                }
+            }
+        });
+
+        ui.checkbox(&mut self.header, "First line is a header");
+    }
+}
+
+/// Splits `line` into the fields separated by `delimiter`. Fields can be quoted as in CSV to
+/// contain the delimiter, `""` being a quote within a quoted field.
+pub fn split_fields(line: &str, delimiter: char) -> Vec<Cow<'_, str>> {
+    let line = line.trim_end_matches(['\n', '\r']);
+    let mut fields = Vec::new();
+    let mut rest = line;
+
+    loop {
+        if let Some(quoted) = rest.strip_prefix('"') {
+            let mut field = String::new();
+            let mut chars = quoted.char_indices();
+            let mut end = quoted.len();
+
+            while let Some((i, c)) = chars.next() {
+                if c != '"' {
+                    field.push(c);
+                } else if quoted[i + 1..].starts_with('"') {
+                    field.push('"');
+                    chars.next();
+                } else {
+                    end = i + 1;
+                    break;
+                }
+            }
+
+            // Anything between the closing quote and the delimiter is kept as is.
+            let after = &quoted[end..];
+            let next = after.find(delimiter);
+            field.push_str(&after[..next.unwrap_or(after.len())]);
+            fields.push(Cow::Owned(field));
+
+            match next {
+                Some(next) => rest = &after[next + delimiter.len_utf8()..],
+                None => return fields,
+            }
+        } else {
+            match rest.find(delimiter) {
+                Some(next) => {
+                    fields.push(Cow::Borrowed(&rest[..next]));
+                    rest = &rest[next + delimiter.len_utf8()..];
+                }
+                None => {
+                    fields.push(Cow::Borrowed(rest));
+                    return fields;
+                }
+            }
+        }
+    }
+}
+
+/// The column a table is sorted by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TableSort {
+    pub column: usize,
+    pub descending: bool,
+}
+
+impl TableSort {
+    /// Sorting by `column` after its header is clicked, cycling between ascending, descending and
+    /// not sorted.
+    pub fn toggle(sort: Option<Self>, column: usize) -> Option<Self> {
+        match sort {
+            Some(sort) if sort.column == column && sort.descending => None,
+            Some(sort) if sort.column == column => Some(Self {
+                column,
+                descending: true,
+            }),
+            _ => Some(Self {
+                column,
+                descending: false,
+            }),
+        }
+    }
+
+    /// The field of the column within `line`, and its value if it's a number.
+    fn key(&self, line: &str, delimiter: char) -> (Option<f64>, Option<String>) {
+        let field = split_fields(line, delimiter)
+            .into_iter()
+            .nth(self.column)
+            .map(Cow::into_owned);
+        let number = field.as_ref().and_then(|f| f.trim().parse().ok());
+
+        (number, field)
+    }
+
+    fn compare(&self, a: &(Option<f64>, Option<String>), b: &(Option<f64>, Option<String>)) -> Ordering {
+        let ordering = match (a, b) {
+            ((Some(a), _), (Some(b), _)) => a.total_cmp(b),
+            ((Some(_), _), _) => Ordering::Less,
+            (_, (Some(_), _)) => Ordering::Greater,
+            ((None, a), (None, b)) => match (a, b) {
+                (Some(a), Some(b)) => a.cmp(b),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            },
+        };
+
+        if self.descending {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    }
+
+    /// Sorts `rows`, indices of `lines`, by the field of the column. Numbers are compared by their
+    /// value and come before text, lines without the column come last.
+    pub fn sort(&self, lines: &[String], rows: &mut [usize], delimiter: char) {
+        let keys: Vec<_> = rows.iter().map(|&i| self.key(&lines[i], delimiter)).collect();
+
+        let mut order: Vec<usize> = (0..rows.len()).collect();
+        order.sort_by(|&a, &b| self.compare(&keys[a], &keys[b]));
+
+        let sorted: Vec<usize> = order.into_iter().map(|i| rows[i]).collect();
+        rows.copy_from_slice(&sorted);
+    }
+
+    /// Adds `new_rows` to `rows` already sorted, as if all of them had been sorted, without
+    /// sorting `rows` again. The new rows come after the rows with an equal field, they're
+    /// expected to be of lines after those of `rows`.
+    pub fn merge(&self, lines: &[String], rows: &mut Vec<usize>, mut new_rows: Vec<usize>, delimiter: char) {
+        self.sort(lines, &mut new_rows, delimiter);
+
+        let mut merged = Vec::with_capacity(rows.len() + new_rows.len());
+        let mut start = 0;
+
+        for new_row in new_rows {
+            let key = self.key(&lines[new_row], delimiter);
+            let position = start
+                + rows[start..].partition_point(|&row| {
+                    self.compare(&self.key(&lines[row], delimiter), &key) != Ordering::Greater
+                });
+
+            merged.extend_from_slice(&rows[start..position]);
+            merged.push(new_row);
+            start = position;
+        }
+
+        merged.extend_from_slice(&rows[start..]);
+        *rows = merged;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{split_fields, TableSort};
+
+    #[test]
+    pub fn test_split_fields() {
+        assert_eq!(split_fields("a,b,,c\n", ','), vec!["a", "b", "", "c"]);
+        assert_eq!(
+            split_fields(r#"1,"Hello, ""world""",x"#, ','),
+            vec!["1", r#"Hello, "world""#, "x"]
+        );
+        assert_eq!(split_fields("a\tb", '\t'), vec!["a", "b"]);
+
+        let lines = ["b,10", "a,9", "c,x", "d"].map(String::from);
+        let mut rows = vec![0, 1, 2, 3];
+
+        TableSort {
+            column: 1,
+            descending: false,
+        }
+        .sort(&lines, &mut rows, ',');
+        assert_eq!(rows, vec![1, 0, 2, 3]);
+
+        TableSort {
+            column: 0,
+            descending: true,
+        }
+        .sort(&lines, &mut rows, ',');
+        assert_eq!(rows, vec![3, 2, 0, 1]);
+
+        // Lines arriving later are merged in where sorting would have put them.
+        let lines = ["b,10", "a,9", "c,x", "d", "e,9", "f,1"].map(String::from);
+        let sort = TableSort {
+            column: 1,
+            descending: false,
+        };
+        let mut rows = vec![0, 1, 2, 3];
+        sort.sort(&lines, &mut rows, ',');
+        sort.merge(&lines, &mut rows, vec![4, 5], ',');
+
+        let mut sorted = vec![0, 1, 2, 3, 4, 5];
+        sort.sort(&lines, &mut sorted, ',');
+        assert_eq!(rows, sorted);
+        assert_eq!(rows, vec![5, 1, 4, 0, 2, 3]);
+    }
+}
@@ -0,0 +1,455 @@
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use log::{debug, error};
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+
+use crate::Error;
+
+/// Paths reported by notify are absolute, make sure the paths we compare them to are as well.
+fn absolute_path(path: &Path) -> PathBuf {
+    std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Resolves symlinks and `..` so the path matches the ones in events. Files that don't exist yet
+/// are resolved through their parent directory.
+fn canonical_path(path: &Path) -> PathBuf {
+    if let Ok(path) = std::fs::canonicalize(path) {
+        return path;
+    }
+
+    let path = absolute_path(path);
+
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) => std::fs::canonicalize(parent)
+            .map(|parent| parent.join(name))
+            .unwrap_or(path),
+        _ => path,
+    }
+}
+
+fn watch_dir(path: &Path) -> PathBuf {
+    path.parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or(Path::new("."))
+        .to_path_buf()
+}
+
+#[derive(Default)]
+struct Registry {
+    /// Readers interested in events for a specific file.
+    files: HashMap<PathBuf, Vec<(u64, UnboundedSender<Event>)>>,
+    /// Number of registered files within each watched directory.
+    dirs: HashMap<PathBuf, usize>,
+}
+
+struct Inner {
+    // NOTE: Lock order is always `watcher` then `registry`. The registry lock must never be held
+    // while calling into the watcher, since the watcher's event thread needs it to dispatch.
+    watcher: Mutex<Option<RecommendedWatcher>>,
+    registry: Arc<Mutex<Registry>>,
+    next_id: AtomicU64,
+    /// Why the watcher couldn't be created, or the last directory couldn't be watched.
+    last_error: Mutex<Option<String>>,
+}
+
+/// A single filesystem watcher shared between all open files.
+///
+/// Watches the parent directory of each registered file (once per directory, no matter how many
+/// files within it are open) and dispatches the events to the readers interested in them.
+#[derive(Clone)]
+pub struct FileWatcher {
+    inner: Arc<Inner>,
+}
+
+/// A reader's interest in a file, unregisters itself from the [`FileWatcher`] when dropped.
+pub struct WatchRegistration {
+    path: PathBuf,
+    id: u64,
+    receiver: UnboundedReceiver<Event>,
+    watcher: FileWatcher,
+}
+
+impl WatchRegistration {
+    pub async fn recv(&mut self) -> Option<Event> {
+        self.receiver.recv().await
+    }
+}
+
+impl Drop for WatchRegistration {
+    fn drop(&mut self) {
+        self.watcher.unregister(&self.path, self.id);
+    }
+}
+
+impl FileWatcher {
+    pub fn new() -> Self {
+        let registry: Arc<Mutex<Registry>> = Arc::default();
+        let dispatch_registry = registry.clone();
+
+        let watcher = notify::recommended_watcher(move |res: notify::Result<Event>| match res {
+            Ok(event) => dispatch(&dispatch_registry, event),
+            Err(e) => error!("Unable to watch file: {e:?}"),
+        });
+
+        let mut last_error = None;
+        let watcher = match watcher {
+            Ok(w) => Some(w),
+            Err(e) => {
+                error!("Unable to create file watcher: {e:?}");
+                last_error = Some(describe_error(&e));
+                None
+            }
+        };
+
+        Self {
+            inner: Arc::new(Inner {
+                watcher: Mutex::new(watcher),
+                registry,
+                next_id: AtomicU64::new(0),
+                last_error: Mutex::new(last_error),
+            }),
+        }
+    }
+
+    /// Registers interest in events concerning `path`.
+    pub fn register(&self, path: &Path) -> Result<WatchRegistration, Error> {
+        let path = canonical_path(path);
+        let dir = watch_dir(&path);
+        let (sender, receiver) = unbounded_channel();
+        let id = self.inner.next_id.fetch_add(1, Ordering::Relaxed);
+
+        let mut watcher = self.inner.watcher.lock().map_err(|e| e.to_string())?;
+
+        let already_watched = self
+            .inner
+            .registry
+            .lock()
+            .map_err(|e| e.to_string())?
+            .dirs
+            .contains_key(&dir);
+
+        if !already_watched {
+            let result = match watcher.as_mut() {
+                Some(w) => w.watch(&dir, RecursiveMode::NonRecursive),
+                None => return Err("No file watcher available".into()),
+            };
+
+            if let Err(e) = result {
+                let description = describe_error(&e);
+                error!("Unable to watch {dir:?}: {e:?}");
+
+                if let Ok(mut last_error) = self.inner.last_error.lock() {
+                    *last_error = Some(description.clone());
+                }
+
+                return Err(description.into());
+            }
+        }
+
+        let mut registry = self.inner.registry.lock().map_err(|e| e.to_string())?;
+
+        if !registry.files.contains_key(&path) {
+            *registry.dirs.entry(dir).or_insert(0) += 1;
+        }
+
+        registry
+            .files
+            .entry(path.clone())
+            .or_default()
+            .push((id, sender));
+
+        debug!("Watching {} directories", registry.dirs.len());
+
+        Ok(WatchRegistration {
+            path,
+            id,
+            receiver,
+            watcher: self.clone(),
+        })
+    }
+
+    fn unregister(&self, path: &Path, id: u64) {
+        let Ok(mut watcher) = self.inner.watcher.lock() else {
+            return;
+        };
+
+        let dir_to_unwatch = {
+            let Ok(mut registry) = self.inner.registry.lock() else {
+                return;
+            };
+
+            let Some(senders) = registry.files.get_mut(path) else {
+                return;
+            };
+
+            senders.retain(|(i, _)| *i != id);
+
+            if !senders.is_empty() {
+                return;
+            }
+
+            registry.files.remove(path);
+
+            let dir = watch_dir(path);
+            let Some(count) = registry.dirs.get_mut(&dir) else {
+                return;
+            };
+
+            *count = count.saturating_sub(1);
+
+            if *count > 0 {
+                return;
+            }
+
+            registry.dirs.remove(&dir);
+            dir
+        };
+
+        debug!("No files left to watch in {dir_to_unwatch:?}");
+
+        if let Some(w) = watcher.as_mut() {
+            if let Err(e) = w.unwatch(&dir_to_unwatch) {
+                error!("Unable to stop watching {dir_to_unwatch:?}: {e:?}");
+            }
+        }
+    }
+
+    /// Name of the platform's mechanism for watching files.
+    pub fn backend(&self) -> &'static str {
+        if cfg!(any(target_os = "linux", target_os = "android")) {
+            "inotify"
+        } else if cfg!(target_os = "macos") {
+            "FSEvents"
+        } else if cfg!(target_os = "windows") {
+            "ReadDirectoryChangesW"
+        } else if cfg!(any(
+            target_os = "freebsd",
+            target_os = "openbsd",
+            target_os = "netbsd",
+            target_os = "dragonfly",
+            target_os = "ios"
+        )) {
+            "kqueue"
+        } else {
+            "polling"
+        }
+    }
+
+    /// How often files are checked for changes, if the backend polls rather than being notified.
+    pub fn poll_interval(&self) -> Option<Duration> {
+        (self.backend() == "polling")
+            .then(|| notify::Config::default().poll_interval_v2())
+            .flatten()
+    }
+
+    /// Whether the watcher could be created, files can't be followed otherwise.
+    pub fn is_available(&self) -> bool {
+        self.inner.watcher.lock().is_ok_and(|w| w.is_some())
+    }
+
+    /// Why the watcher couldn't be created, or the last directory couldn't be watched.
+    pub fn last_error(&self) -> Option<String> {
+        self.inner.last_error.lock().ok().and_then(|e| e.clone())
+    }
+
+    /// Number of directories currently being watched.
+    pub fn watched_dirs(&self) -> usize {
+        self.inner
+            .registry
+            .lock()
+            .map(|r| r.dirs.len())
+            .unwrap_or(0)
+    }
+}
+
+impl Default for FileWatcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Debug for FileWatcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&format!("FileWatcher ({} dirs)", self.watched_dirs()))
+    }
+}
+
+/// Describes `error`, with advice on how to fix the common ones.
+pub fn describe_error(error: &notify::Error) -> String {
+    let is_limit = matches!(error.kind, notify::ErrorKind::MaxFilesWatch)
+        || matches!(&error.kind, notify::ErrorKind::Io(e) if e.raw_os_error() == Some(28));
+
+    if is_limit && cfg!(any(target_os = "linux", target_os = "android")) {
+        String::from(
+            "The limit of inotify watches was reached. Increase fs.inotify.max_user_watches, e.g. \
+             `sudo sysctl fs.inotify.max_user_watches=524288`, or close other applications \
+             watching many files.",
+        )
+    } else if matches!(&error.kind, notify::ErrorKind::Io(e) if e.raw_os_error() == Some(24))
+        && cfg!(any(target_os = "linux", target_os = "android"))
+    {
+        // Either limit makes creating an inotify instance fail with EMFILE.
+        String::from(
+            "Too many open files. Close other files or raise the limit of open files (`ulimit -n`), \
+             or the limit of inotify instances may have been reached, increase \
+             fs.inotify.max_user_instances, e.g. `sudo sysctl fs.inotify.max_user_instances=512`.",
+        )
+    } else {
+        error.to_string()
+    }
+}
+
+fn dispatch(registry: &Mutex<Registry>, event: Event) {
+    let Ok(registry) = registry.lock() else {
+        error!("File watcher registry is poisoned");
+        return;
+    };
+
+    // The paths are in the watched directories, which are canonical like the registered paths.
+    for path in &event.paths {
+        if let Some(senders) = registry.files.get(path) {
+            for (_, sender) in senders {
+                // Fails only if the reader is shutting down, it'll unregister itself.
+                let _ = sender.send(event.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Instant;
+
+    use super::{describe_error, FileWatcher};
+
+    #[test]
+    pub fn test_one_watch_per_directory() {
+        let dir = std::env::temp_dir().join("logglance_test_one_watch_per_directory");
+        std::fs::create_dir_all(&dir).expect("Should be able to create test directory");
+
+        let watcher = FileWatcher::new();
+
+        let first = watcher
+            .register(&dir.join("first.log"))
+            .expect("Should register first file");
+        let second = watcher
+            .register(&dir.join("second.log"))
+            .expect("Should register second file");
+        let third = watcher
+            .register(&dir.join("second.log"))
+            .expect("Should register same file twice");
+
+        assert_eq!(watcher.watched_dirs(), 1);
+
+        drop(first);
+        drop(second);
+        assert_eq!(watcher.watched_dirs(), 1);
+
+        drop(third);
+        assert_eq!(watcher.watched_dirs(), 0);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    pub fn test_register_through_symlink() {
+        let dir = std::env::temp_dir().join("logglance_test_register_through_symlink");
+        let real = dir.join("real");
+        let link = dir.join("link");
+        std::fs::create_dir_all(&real).expect("Should be able to create test directory");
+        let _ = std::fs::remove_file(&link);
+        std::os::unix::fs::symlink(&real, &link).expect("Should be able to create symlink");
+
+        let watcher = FileWatcher::new();
+
+        let _direct = watcher
+            .register(&real.join("app.log"))
+            .expect("Should register file");
+        let _linked = watcher
+            .register(&link.join("app.log"))
+            .expect("Should register file through symlink");
+        let _dotted = watcher
+            .register(&link.join("..").join("real").join("app.log"))
+            .expect("Should register file through ..");
+
+        assert_eq!(watcher.watched_dirs(), 1);
+    }
+
+    /// Number of inotify watches held by this process.
+    #[cfg(target_os = "linux")]
+    fn inotify_watches() -> usize {
+        let Ok(entries) = std::fs::read_dir("/proc/self/fdinfo") else {
+            return 0;
+        };
+
+        entries
+            .flatten()
+            .filter_map(|entry| std::fs::read_to_string(entry.path()).ok())
+            .map(|info| {
+                info.lines()
+                    .filter(|line| line.starts_with("inotify wd:"))
+                    .count()
+            })
+            .sum()
+    }
+
+    /// Benchmark, run on its own with `cargo test bench_watch_count -- --ignored --nocapture`,
+    /// since watches held by other tests are counted as well.
+    #[test]
+    #[ignore]
+    #[cfg(target_os = "linux")]
+    pub fn bench_watch_count() {
+        const DIRS: usize = 20;
+        const FILES_PER_DIR: usize = 500;
+
+        let root = std::env::temp_dir().join("logglance_bench_watch_count");
+        let watcher = FileWatcher::new();
+        let before = inotify_watches();
+        let start = Instant::now();
+
+        let registrations = (0..DIRS)
+            .flat_map(|d| (0..FILES_PER_DIR).map(move |f| (d, f)))
+            .map(|(d, f)| {
+                let dir = root.join(format!("dir{d}"));
+                std::fs::create_dir_all(&dir).expect("Should be able to create test directory");
+                watcher
+                    .register(&dir.join(format!("file{f}.log")))
+                    .expect("Should register file")
+            })
+            .collect::<Vec<_>>();
+
+        let watches = inotify_watches() - before;
+
+        println!(
+            "Registered {} files in {:?}, using {watches} inotify watches",
+            registrations.len(),
+            start.elapsed()
+        );
+
+        assert_eq!(watcher.watched_dirs(), DIRS);
+        assert_eq!(watches, DIRS);
+
+        drop(registrations);
+        assert_eq!(inotify_watches(), before);
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    pub fn test_describe_watch_limit() {
+        let error = notify::Error::new(notify::ErrorKind::MaxFilesWatch);
+        assert!(describe_error(&error).contains("fs.inotify.max_user_watches"));
+
+        let error = notify::Error::io(std::io::Error::from_raw_os_error(24));
+        let description = describe_error(&error);
+        assert!(description.starts_with("Too many open files"));
+        assert!(!description.contains("fs.inotify.max_user_watches"));
+
+        let error = notify::Error::generic("something else");
+        assert_eq!(describe_error(&error), error.to_string());
+    }
+}
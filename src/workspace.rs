@@ -0,0 +1,116 @@
+//! Workspaces, the open files together with how each of them is filtered, highlighted and
+//! displayed, saved to a JSON file to switch between sets of files or to share them.
+//!
+//! Unlike the session restored on startup, which is kept in eframe's storage, a workspace is a
+//! file of its own which can be named, copied and opened on another machine. The paths of files
+//! within the log root (see [`crate::settings::Settings::log_root`]) are stored relative to it.
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::logfile::LogFile;
+use crate::Error;
+
+#[derive(Serialize)]
+struct SavedWorkspace {
+    files: Vec<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct Workspace {
+    files: Vec<LogFile>,
+}
+
+/// Writes `files`, in the order of their tabs, to the workspace file at `path`. Only what's
+/// persisted of each file is written, e.g. its path, alias, encoding, filter, highlights and
+/// display options, not its lines. Snapshots and files of lines which aren't read from anywhere are
+/// left out, as they're nothing without their lines.
+/// Paths within `root` are written relative to it.
+pub fn save(path: &Path, files: Vec<&LogFile>, root: Option<&Path>) -> Result<(), Error> {
+    let files = files
+        .into_iter()
+        .filter(|file| file.snapshot.is_none() && !file.is_in_memory())
+        .map(|file| {
+            let mut value = serde_json::to_value(file)?;
+
+            if let Some(relative) = root.and_then(|root| file.path.strip_prefix(root).ok()) {
+                value["path"] = serde_json::to_value(relative)?;
+            }
+
+            Ok(value)
+        })
+        .collect::<Result<Vec<_>, serde_json::Error>>()
+        .map_err(|e| Error::Other(e.into()))?;
+
+    let json = serde_json::to_string_pretty(&SavedWorkspace { files })
+        .map_err(|e| Error::Other(e.into()))?;
+
+    std::fs::write(path, json)?;
+    Ok(())
+}
+
+/// The files of the workspace file at `path`, which start reading once shown. Relative paths are
+/// resolved within `root`, or the directory of the workspace file if there's no root.
+pub fn load(path: &Path, root: Option<&Path>) -> Result<Vec<LogFile>, Error> {
+    let json = std::fs::read_to_string(path)?;
+    let workspace: Workspace = serde_json::from_str(&json)
+        .map_err(|e| format!("{} isn't a valid workspace: {e}", path.display()))?;
+
+    let root = root.or_else(|| path.parent());
+    let mut files = workspace.files;
+
+    for file in files.iter_mut().filter(|file| file.path.is_relative()) {
+        if let Some(root) = root {
+            file.set_path(root.join(&file.path));
+        }
+    }
+
+    Ok(files)
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use super::{load, save};
+    use crate::logfile::{DisplayOptions, Filter, LogFile, Search, SearchMode};
+
+    #[test]
+    pub fn test_workspace_round_trip() {
+        let path = std::env::temp_dir().join("logglance_test_workspace.json");
+
+        let mut file = LogFile::new(
+            PathBuf::from("app.log"),
+            vec![String::from("not saved\n")],
+            DisplayOptions::default(),
+        );
+        file.alias = Some(String::from("App"));
+        file.encoding = Some(encoding_rs::WINDOWS_1252);
+        file.row_modifier.filter = Filter::new(Search::new("ERROR", SearchMode::Substring, false));
+
+        // Snapshots aren't saved along with the file.
+        let snapshot = file.snapshot();
+        save(&path, vec![&file, &snapshot], None).expect("Should save workspace");
+        let files = load(&path, Some(&PathBuf::from("/var/log/other"))).expect("Should load workspace");
+
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].path, PathBuf::from("/var/log/other/app.log"));
+        assert_eq!(files[0].filename, "/var/log/other/app.log");
+        assert_eq!(files[0].alias.as_deref(), Some("App"));
+        assert_eq!(files[0].encoding, Some(encoding_rs::WINDOWS_1252));
+        assert_eq!(files[0].row_modifier.filter.search.string, "ERROR");
+        assert!(files[0].lines.is_empty());
+
+        // Stored relative to the log root, and resolved within the root where it's opened.
+        file.set_path(PathBuf::from("/var/log/myapp/app.log"));
+        save(&path, vec![&file], Some(&PathBuf::from("/var/log/myapp"))).expect("Should save workspace");
+        assert!(std::fs::read_to_string(&path)
+            .expect("Should read workspace")
+            .contains(r#""path": "app.log""#));
+
+        let files = load(&path, Some(&PathBuf::from("/srv/logs"))).expect("Should load workspace");
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(files[0].path, PathBuf::from("/srv/logs/app.log"));
+    }
+}